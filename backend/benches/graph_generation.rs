@@ -125,6 +125,7 @@ fn benchmark_routing(c: &mut Criterion) {
         end: Coordinate { lat: 45.02, lon: 5.02 },
         w_pop: 1.0,
         w_paved: 1.0,
+        options: Default::default(),
     };
 
     let mut group = c.benchmark_group("routing");
@@ -158,6 +159,9 @@ fn benchmark_routing(c: &mut Criterion) {
                     surface: SurfaceType::Trail,
                     length_m: 200.0,
                     waypoints: vec![],
+                    routes: vec![],
+                    attributes: Default::default(),
+                    environment: Default::default(),
                 });
             }
             // Vertical edge
@@ -169,12 +173,15 @@ fn benchmark_routing(c: &mut Criterion) {
                     surface: SurfaceType::Dirt,
                     length_m: 200.0,
                     waypoints: vec![],
+                    routes: vec![],
+                    attributes: Default::default(),
+                    environment: Default::default(),
                 });
             }
         }
     }
 
-    let graph_file = GraphFile { nodes, edges };
+    let graph_file = GraphFile { nodes, edges, pois: vec![], routes: vec![], names: vec![] };
     let grid_engine = RouteEngine::from_graph_file(graph_file).expect("grid graph");
 
     // Route from corner (0,0) to corner (21,21)
@@ -186,6 +193,7 @@ fn benchmark_routing(c: &mut Criterion) {
         },
         w_pop: 1.0,
         w_paved: 1.0,
+        options: Default::default(),
     };
 
     group.bench_function("find_path_grid_484nodes", |b| {
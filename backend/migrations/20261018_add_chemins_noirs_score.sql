@@ -0,0 +1,10 @@
+-- Migration: Add the chemins noirs score to saved routes
+-- Description: Composite route quality, to sort saved routes by
+-- Date: 2026-10-18
+
+ALTER TABLE saved_routes
+ADD COLUMN IF NOT EXISTS chemins_noirs_score REAL;
+
+CREATE INDEX IF NOT EXISTS idx_saved_routes_chemins_noirs_score ON saved_routes(chemins_noirs_score DESC NULLS LAST);
+
+COMMENT ON COLUMN saved_routes.chemins_noirs_score IS 'Chemins noirs score from 0 to 100, copied from route_data (NULL when unknown)';
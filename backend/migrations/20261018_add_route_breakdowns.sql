@@ -0,0 +1,12 @@
+-- Migration: Add surface and highway breakdowns to saved routes
+-- Description: Distance per surface and per highway class, queryable without route_data
+-- Date: 2026-10-18
+
+ALTER TABLE saved_routes
+ADD COLUMN IF NOT EXISTS surface_breakdown JSONB;
+
+ALTER TABLE saved_routes
+ADD COLUMN IF NOT EXISTS highway_breakdown JSONB;
+
+COMMENT ON COLUMN saved_routes.surface_breakdown IS 'Distance per surface as [["paved", km], ...] (NULL when unknown)';
+COMMENT ON COLUMN saved_routes.highway_breakdown IS 'Distance per highway class as [["track", km], ...] (NULL when unknown)';
@@ -0,0 +1,36 @@
+-- Migration: Create route_groups table
+-- Description: Group saved routes, e.g. the daily stages of a multi-day trek
+-- Date: 2026-10-18
+
+CREATE TABLE IF NOT EXISTS route_groups (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT route_groups_name_not_empty CHECK (length(trim(name)) > 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_route_groups_created_at ON route_groups(created_at DESC);
+
+DROP TRIGGER IF EXISTS update_route_groups_updated_at ON route_groups;
+
+CREATE TRIGGER update_route_groups_updated_at
+    BEFORE UPDATE ON route_groups
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Membership of saved routes (NULL for standalone routes)
+ALTER TABLE saved_routes
+ADD COLUMN IF NOT EXISTS group_id INTEGER REFERENCES route_groups(id) ON DELETE CASCADE;
+
+ALTER TABLE saved_routes
+ADD COLUMN IF NOT EXISTS group_position INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_saved_routes_group ON saved_routes(group_id, group_position)
+    WHERE group_id IS NOT NULL;
+
+COMMENT ON TABLE route_groups IS 'Ordered collections of saved routes (e.g. stages of a multi-day itinerary)';
+COMMENT ON COLUMN saved_routes.group_id IS 'Route group this route belongs to (NULL for standalone routes)';
+COMMENT ON COLUMN saved_routes.group_position IS 'Position of the route within its group (0 = first stage)';
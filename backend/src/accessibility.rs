@@ -0,0 +1,207 @@
+//! Wheelchair and stroller profile.
+//!
+//! Steps, raised kerbs, very rough surfaces, ways tagged `wheelchair=no` and
+//! grades above the limit are impassable; everything else is passable with a
+//! penalty growing with roughness. Tags come from [`WayAttributes`], grades
+//! from the `incline` tag and the DEM (see [`annotate_grades`]).
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dem::ArcAsciiDem,
+    geo_utils::{haversine_km, sample_polyline},
+    graph::{EdgeEnvironment, EdgeRecord, NodeRecord, WayAttributes},
+    models::SurfaceType,
+};
+
+/// Grade limit when the request does not set `max_grade_percent`: the usual
+/// maximum for ramps
+pub const DEFAULT_MAX_GRADE_PERCENT: f64 = 8.0;
+/// DEM samples along an edge are this far apart when measuring its grade
+const GRADE_SAMPLE_SPACING_M: f64 = 30.0;
+
+/// `kerb=*`, ordered from the easiest to get over to the hardest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Kerb {
+    No,
+    Flush,
+    Lowered,
+    Rolled,
+    Yes,
+    Raised,
+}
+
+impl Kerb {
+    pub fn from_tag(value: &str) -> Option<Self> {
+        match value {
+            "no" => Some(Self::No),
+            "flush" => Some(Self::Flush),
+            "lowered" => Some(Self::Lowered),
+            "rolled" => Some(Self::Rolled),
+            "yes" => Some(Self::Yes),
+            "raised" => Some(Self::Raised),
+            _ => None,
+        }
+    }
+}
+
+/// `smoothness=*`, ordered from the smoothest to the roughest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Smoothness {
+    Excellent,
+    Good,
+    Intermediate,
+    Bad,
+    VeryBad,
+    Horrible,
+    VeryHorrible,
+    Impassable,
+}
+
+impl Smoothness {
+    pub fn from_tag(value: &str) -> Option<Self> {
+        match value {
+            "excellent" => Some(Self::Excellent),
+            "good" => Some(Self::Good),
+            "intermediate" => Some(Self::Intermediate),
+            "bad" => Some(Self::Bad),
+            "very_bad" => Some(Self::VeryBad),
+            "horrible" => Some(Self::Horrible),
+            "very_horrible" => Some(Self::VeryHorrible),
+            "impassable" => Some(Self::Impassable),
+            _ => None,
+        }
+    }
+}
+
+/// Extra cost per km, relative to the length, for a wheelchair on this way,
+/// or `None` if it cannot be used at all.
+pub fn wheelchair_penalty(attributes: &WayAttributes, surface: SurfaceType) -> Option<f64> {
+    if attributes.is_steps() {
+        return None;
+    }
+    match attributes.wheelchair.as_deref() {
+        Some("no") => return None,
+        // Surveyed as accessible: trust it over surface guesses
+        Some("yes" | "designated") => return Some(0.0),
+        _ => {}
+    }
+
+    let kerb = match attributes.kerb {
+        Some(Kerb::Raised | Kerb::Yes) => return None,
+        Some(Kerb::Rolled) => 0.3,
+        _ => 0.0,
+    };
+    let smoothness = match attributes.smoothness {
+        Some(smoothness) if smoothness >= Smoothness::VeryBad => return None,
+        Some(Smoothness::Bad) => 1.0,
+        Some(Smoothness::Intermediate) => 0.3,
+        _ => 0.0,
+    };
+    let surface = match attributes.surface.as_deref() {
+        Some("asphalt" | "concrete" | "concrete:plates" | "paving_stones" | "paved" | "wood" | "metal") => 0.0,
+        Some("compacted" | "fine_gravel") => 0.5,
+        Some("sett" | "cobblestone" | "unhewn_cobblestone") => 1.0,
+        Some("gravel" | "pebblestone" | "sand" | "grass" | "mud" | "dirt" | "earth" | "ground" | "rock") => {
+            return None
+        }
+        // Untagged or unusual values: fall back on the coarse surface class
+        _ => match surface {
+            SurfaceType::Paved => 0.0,
+            SurfaceType::Trail => 1.0,
+            SurfaceType::Dirt => return None,
+        },
+    };
+    let limited = if attributes.wheelchair.as_deref() == Some("limited") { 0.5 } else { 0.0 };
+
+    Some(kerb + smoothness + surface + limited)
+}
+
+/// Steepest known grade of an edge in percent, from its `incline` tag and
+/// the DEM.
+pub fn grade_percent(attributes: &WayAttributes, environment: &EdgeEnvironment) -> Option<f64> {
+    match (attributes.incline_percent.map(f64::abs), environment.max_grade_percent) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Kerb found on a node, from `kerb=*` or a bare `barrier=kerb`.
+pub fn kerb_from_tags<'a>(tags: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<Kerb> {
+    let mut barrier = false;
+    for (key, value) in tags {
+        match (key, value) {
+            ("kerb", kerb) => return Kerb::from_tag(kerb),
+            ("barrier", "kerb") => barrier = true,
+            _ => {}
+        }
+    }
+    barrier.then_some(Kerb::Yes)
+}
+
+/// Fill the DEM grade of every edge: the steepest grade between consecutive
+/// samples [`GRADE_SAMPLE_SPACING_M`] apart. `nodes` must be indexed by graph
+/// id - 1, as produced by the graph builder.
+pub fn annotate_grades(edges: &mut [EdgeRecord], nodes: &[NodeRecord], dem: &ArcAsciiDem) {
+    edges.par_iter_mut().for_each(|edge| {
+        let Some(polyline) = edge.polyline(nodes) else {
+            return;
+        };
+        let samples: Vec<_> = sample_polyline(&polyline, GRADE_SAMPLE_SPACING_M)
+            .into_iter()
+            .filter_map(|c| Some((c, dem.sample(c.lat, c.lon)?)))
+            .collect();
+        edge.environment.max_grade_percent = samples
+            .windows(2)
+            .filter_map(|pair| {
+                let run_m = haversine_km(pair[0].0, pair[1].0) * 1000.0;
+                (run_m > 1.0).then(|| 100.0 * (pair[1].1 - pair[0].1).abs() / run_m)
+            })
+            .reduce(f64::max);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(tags: &[(&str, &str)]) -> WayAttributes {
+        let tags: Vec<(String, String)> =
+            tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        WayAttributes::from_tags(&tags)
+    }
+
+    #[test]
+    fn test_wheelchair_penalty_rules_out_steps_kerbs_and_rough_surfaces() {
+        let paved = SurfaceType::Paved;
+        assert_eq!(wheelchair_penalty(&attributes(&[("highway", "footway")]), paved), Some(0.0));
+        assert_eq!(wheelchair_penalty(&attributes(&[("highway", "steps")]), paved), None);
+        assert_eq!(wheelchair_penalty(&attributes(&[("kerb", "raised")]), paved), None);
+        assert_eq!(wheelchair_penalty(&attributes(&[("surface", "gravel")]), paved), None);
+        assert_eq!(wheelchair_penalty(&attributes(&[("smoothness", "horrible")]), paved), None);
+        assert_eq!(wheelchair_penalty(&attributes(&[("wheelchair", "no")]), paved), None);
+
+        let rough = attributes(&[("surface", "sett"), ("smoothness", "intermediate")]);
+        assert_eq!(wheelchair_penalty(&rough, paved), Some(1.3));
+        assert_eq!(wheelchair_penalty(&attributes(&[]), SurfaceType::Trail), Some(1.0));
+        assert_eq!(
+            wheelchair_penalty(&attributes(&[("surface", "gravel"), ("wheelchair", "yes")]), paved),
+            Some(0.0),
+            "surveyed tag wins"
+        );
+    }
+
+    #[test]
+    fn test_grade_combines_incline_tag_and_dem() {
+        let env = |max_grade_percent| EdgeEnvironment { max_grade_percent, ..Default::default() };
+        let tagged = attributes(&[("incline", "-12%")]);
+        assert_eq!(grade_percent(&tagged, &env(None)), Some(12.0));
+        assert_eq!(grade_percent(&tagged, &env(Some(15.0))), Some(15.0));
+        assert_eq!(grade_percent(&attributes(&[("incline", "up")]), &env(None)), None);
+
+        assert_eq!(kerb_from_tags([("barrier", "kerb")]), Some(Kerb::Yes));
+        assert_eq!(kerb_from_tags([("barrier", "kerb"), ("kerb", "lowered")]), Some(Kerb::Lowered));
+        assert_eq!(kerb_from_tags([("highway", "crossing")]), None);
+    }
+}
@@ -1,16 +1,27 @@
 use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
 use backend::{
     database::Database,
     elevation::create_elevation_profile,
     engine::RouteEngine,
     graph::{BoundingBox, GraphBuilder, GraphBuilderConfig, GraphFile},
-    loops::{self, LoopGenerationError},
-    models::{Coordinate, LoopRouteRequest, LoopRouteResponse, RouteRequest},
+    itinerary, itinerary_error, loop_error,
+    loops,
+    map_match_error, map_matching,
+    models::{
+        ApiError, Coordinate, DetourRouteRequest, DetourRouteResponse, ItineraryRequest, ItineraryResponse,
+        LoopRouteRequest, LoopRouteResponse, MapMatchRequest, MapMatchResponse, NearestRequest,
+        NearestResponse, RouteRequest,
+    },
     partial_graph::PartialGraphConfig,
     poi,
-    routing::{estimate_time_minutes, haversine_km, rate_difficulty},
+    routing::haversine_km,
     saved_routes_handlers,
 };
 use shared::MultiPointRouteRequest;
@@ -246,25 +257,23 @@ async fn ign_roads_handler(
                 _ => vec![],
             };
 
-            for maybe_coords in coord_arrays {
-                if let Some(coords) = maybe_coords {
-                    let polyline: Vec<serde_json::Value> = coords
-                        .iter()
-                        .filter_map(|c| {
-                            let arr = c.as_array()?;
-                            let lon = arr.first()?.as_f64()?;
-                            let lat = arr.get(1)?.as_f64()?;
-                            let alt = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                            Some(serde_json::json!({"lat": lat, "lon": lon, "alt": alt}))
-                        })
-                        .collect();
-
-                    if polyline.len() >= 2 {
-                        roads.push(serde_json::json!({
-                            "nature": nature,
-                            "coords": polyline
-                        }));
-                    }
+            for coords in coord_arrays.into_iter().flatten() {
+                let polyline: Vec<serde_json::Value> = coords
+                    .iter()
+                    .filter_map(|c| {
+                        let arr = c.as_array()?;
+                        let lon = arr.first()?.as_f64()?;
+                        let lat = arr.get(1)?.as_f64()?;
+                        let alt = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                        Some(serde_json::json!({"lat": lat, "lon": lon, "alt": alt}))
+                    })
+                    .collect();
+
+                if polyline.len() >= 2 {
+                    roads.push(serde_json::json!({
+                        "nature": nature,
+                        "coords": polyline
+                    }));
                 }
             }
         }
@@ -336,25 +345,23 @@ async fn fetch_ign_polygons(
                 _ => vec![],
             };
 
-            for maybe_ring in rings {
-                if let Some(ring) = maybe_ring {
-                    let coords: Vec<serde_json::Value> = ring
-                        .iter()
-                        .filter_map(|c| {
-                            let arr = c.as_array()?;
-                            let lon = arr.first()?.as_f64()?;
-                            let lat = arr.get(1)?.as_f64()?;
-                            let alt = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                            Some(serde_json::json!({"lat": lat, "lon": lon, "alt": alt}))
-                        })
-                        .collect();
-
-                    if coords.len() >= 3 {
-                        results.push(serde_json::json!({
-                            "nature": nature,
-                            "coords": coords
-                        }));
-                    }
+            for ring in rings.into_iter().flatten() {
+                let coords: Vec<serde_json::Value> = ring
+                    .iter()
+                    .filter_map(|c| {
+                        let arr = c.as_array()?;
+                        let lon = arr.first()?.as_f64()?;
+                        let lat = arr.get(1)?.as_f64()?;
+                        let alt = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                        Some(serde_json::json!({"lat": lat, "lon": lon, "alt": alt}))
+                    })
+                    .collect();
+
+                if coords.len() >= 3 {
+                    results.push(serde_json::json!({
+                        "nature": nature,
+                        "coords": coords
+                    }));
                 }
             }
         }
@@ -440,26 +447,24 @@ async fn ign_buildings_handler(
                 _ => vec![],
             };
 
-            for maybe_ring in rings {
-                if let Some(ring) = maybe_ring {
-                    let coords: Vec<serde_json::Value> = ring
-                        .iter()
-                        .filter_map(|c| {
-                            let arr = c.as_array()?;
-                            let lon = arr.first()?.as_f64()?;
-                            let lat = arr.get(1)?.as_f64()?;
-                            let alt = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                            Some(serde_json::json!({"lat": lat, "lon": lon, "alt": alt}))
-                        })
-                        .collect();
-
-                    if coords.len() >= 3 {
-                        buildings.push(serde_json::json!({
-                            "nature": nature,
-                            "hauteur": hauteur,
-                            "coords": coords
-                        }));
-                    }
+            for ring in rings.into_iter().flatten() {
+                let coords: Vec<serde_json::Value> = ring
+                    .iter()
+                    .filter_map(|c| {
+                        let arr = c.as_array()?;
+                        let lon = arr.first()?.as_f64()?;
+                        let lat = arr.get(1)?.as_f64()?;
+                        let alt = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                        Some(serde_json::json!({"lat": lat, "lon": lon, "alt": alt}))
+                    })
+                    .collect();
+
+                if coords.len() >= 3 {
+                    buildings.push(serde_json::json!({
+                        "nature": nature,
+                        "hauteur": hauteur,
+                        "coords": coords
+                    }));
                 }
             }
         }
@@ -600,19 +605,21 @@ async fn route_handler(
     // Calculate bounding box with margin for the route
     let bbox = BoundingBox::from_route(req.start, req.end, 5.0);
 
+    backend::water::validate_max_km_without_water(req.options.max_km_without_water)
+        .map_err(|message| (StatusCode::BAD_REQUEST, message.to_string()))?;
+
     let engine = get_or_build_engine(&config, bbox).await?;
 
     let t_path = std::time::Instant::now();
-    match engine.find_path(&req) {
-        Some(path) => {
+    let routed = match req.options.max_km_without_water {
+        Some(max_km) => backend::water::route_with_water(&engine, &req, max_km)
+            .map(|(path, nodes, _)| (path, nodes)),
+        None => engine.find_path_returning_indices(&req),
+    };
+    match routed {
+        Some((path, nodes)) => {
             tracing::info!("PERF pathfinding: {:.0}ms ({} points)", t_path.elapsed().as_secs_f64() * 1000.0, path.len());
 
-            // Calculate distance
-            let distance_km: f64 = path
-                .windows(2)
-                .map(|pair| haversine_km(pair[0], pair[1]))
-                .sum();
-
             // Fetch elevation profile on-demand
             let t_elev = std::time::Instant::now();
             let elevation_profile = match create_elevation_profile(&path).await {
@@ -631,34 +638,12 @@ async fn route_handler(
                 }
             };
 
-            // For now, GPX base64 is empty - we can implement it later
-            let gpx_base64 = String::new();
-
-            // Compute analytics from elevation profile
-            let (estimated_time, difficulty) = match &elevation_profile {
-                Some(profile) => {
-                    let time = estimate_time_minutes(distance_km, profile.total_ascent);
-                    let diff = rate_difficulty(&profile.elevations, &path, profile.total_ascent);
-                    (Some(time), Some(diff))
-                }
-                None => (None, None),
-            };
-
-            let response = RouteResponse {
-                path,
-                distance_km,
-                gpx_base64,
-                metadata: None,
-                elevation_profile,
-                terrain: None,
-                snapped_waypoints: None,
-                estimated_time_minutes: estimated_time,
-                difficulty,
-                surface_breakdown: None,
-                segments: None,
-            };
+            let mut response = engine
+                .route_response(path, &nodes, elevation_profile, &req.options)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            response.debug = req.options.debug.then(|| engine.explain_route(&req, &nodes)).flatten();
 
-            tracing::info!("PERF TOTAL /api/route: {:.0}ms ({:.2}km)", t_total.elapsed().as_secs_f64() * 1000.0, distance_km);
+            tracing::info!("PERF TOTAL /api/route: {:.0}ms ({:.2}km)", t_total.elapsed().as_secs_f64() * 1000.0, response.distance_km);
             Ok(Json(response))
         }
         None => {
@@ -667,6 +652,12 @@ async fn route_handler(
                 req.start,
                 req.end
             );
+            if let Some(message) = engine
+                .disconnected_island_message(&req)
+                .or_else(|| engine.no_accessible_route_message(&req))
+            {
+                return Err((StatusCode::NOT_FOUND, message));
+            }
             Err((
                 StatusCode::NOT_FOUND,
                 "No route found - coordinates may be outside graph coverage or unreachable"
@@ -676,10 +667,45 @@ async fn route_handler(
     }
 }
 
+/// Bbox covering every waypoint a loop request may place.
+fn loop_bbox(req: &LoopRouteRequest) -> BoundingBox {
+    let radius = (req.target_distance_km / 2.0).max(2.0) * 1.4 + req.distance_tolerance_km.max(1.0);
+    bbox_from_center(req.start, radius)
+}
+
+/// `GET /api/loops/stream?request=<json>` - loop generation as Server-Sent Events
+async fn loop_stream_get_handler(
+    State(config): State<Arc<PartialGraphConfig>>,
+    Query(query): Query<backend::LoopStreamQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let req: LoopRouteRequest = serde_json::from_str(&query.request).map_err(|e| {
+        api_error((StatusCode::BAD_REQUEST, format!("Invalid loop request: {}", e)))
+    })?;
+    loop_stream(&config, req).await
+}
+
+/// `POST /api/loops/stream` - loop generation as Server-Sent Events
+async fn loop_stream_post_handler(
+    State(config): State<Arc<PartialGraphConfig>>,
+    Json(req): Json<LoopRouteRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    loop_stream(&config, req).await
+}
+
+async fn loop_stream(
+    config: &Arc<PartialGraphConfig>,
+    req: LoopRouteRequest,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    // Reject bad requests before paying for the graph build
+    loops::validate_request(&req).map_err(loop_error)?;
+    let engine = get_or_build_engine(config, loop_bbox(&req)).await.map_err(api_error)?;
+    backend::loop_event_stream(engine, req).map_err(loop_error)
+}
+
 async fn loop_route_handler(
     State(config): State<Arc<PartialGraphConfig>>,
     Json(req): Json<LoopRouteRequest>,
-) -> Result<Json<LoopRouteResponse>, (StatusCode, String)> {
+) -> Result<Json<LoopRouteResponse>, (StatusCode, Json<ApiError>)> {
     let t_total = std::time::Instant::now();
     tracing::info!(
         "Loop request from {:?} targeting {:.1} km",
@@ -687,10 +713,7 @@ async fn loop_route_handler(
         req.target_distance_km
     );
 
-    let radius = (req.target_distance_km / 2.0).max(2.0) * 1.4 + req.distance_tolerance_km.max(1.0);
-    let bbox = bbox_from_center(req.start, radius);
-
-    let engine = get_or_build_engine(&config, bbox).await?;
+    let engine = get_or_build_engine(&config, loop_bbox(&req)).await.map_err(api_error)?;
 
     let t_loops = std::time::Instant::now();
     match loops::generate_loops(&engine, &req).await {
@@ -699,19 +722,114 @@ async fn loop_route_handler(
             tracing::info!("PERF TOTAL /api/loops: {:.0}ms", t_total.elapsed().as_secs_f64() * 1000.0);
             Ok(Json(response))
         }
-        Err(err) => {
-            let status = match err {
-                LoopGenerationError::InvalidTargetDistance => StatusCode::BAD_REQUEST,
-                LoopGenerationError::NoLoopFound => StatusCode::NOT_FOUND,
-                LoopGenerationError::Gpx(_) | LoopGenerationError::Elevation(_) => {
-                    StatusCode::INTERNAL_SERVER_ERROR
-                }
-            };
-            Err((status, err.to_string()))
-        }
+        Err(err) => Err(loop_error(err)),
     }
 }
 
+/// Engine build errors in the [`ApiError`] shape of the loop endpoints.
+fn api_error((status, message): (StatusCode, String)) -> (StatusCode, Json<ApiError>) {
+    (status, Json(ApiError { message }))
+}
+
+/// Handler for /api/itinerary - split a trek into daily stages
+async fn itinerary_handler(
+    State(config): State<Arc<PartialGraphConfig>>,
+    Json(req): Json<ItineraryRequest>,
+) -> Result<Json<ItineraryResponse>, (StatusCode, Json<ApiError>)> {
+    let t_total = std::time::Instant::now();
+    // Reject bad requests before paying for the graph build
+    itinerary::validate_request(&req).map_err(itinerary_error)?;
+
+    let (min_lat, max_lat, min_lon, max_lon) = backend::geo_utils::compute_bounds(&req.waypoints);
+    let bbox = BoundingBox::from_route(
+        Coordinate { lat: min_lat, lon: min_lon },
+        Coordinate { lat: max_lat, lon: max_lon },
+        5.0,
+    );
+    bbox.validate()
+        .map_err(|err_msg| api_error((StatusCode::BAD_REQUEST, format!("Invalid request: {}", err_msg))))?;
+
+    let engine = get_or_build_engine(&config, bbox).await.map_err(api_error)?;
+    let plan = itinerary::plan_itinerary(&engine, &req).await.map_err(itinerary_error)?;
+
+    tracing::info!(
+        "PERF TOTAL /api/itinerary: {:.0}ms ({:.1}km, {} stages)",
+        t_total.elapsed().as_secs_f64() * 1000.0,
+        plan.total_distance_km,
+        plan.stages.len()
+    );
+    Ok(Json(plan))
+}
+
+/// Handler for /api/route/match - snap a recorded GPX track onto the graph
+async fn map_match_handler(
+    State(config): State<Arc<PartialGraphConfig>>,
+    Json(req): Json<MapMatchRequest>,
+) -> Result<Json<MapMatchResponse>, (StatusCode, Json<ApiError>)> {
+    let t_total = std::time::Instant::now();
+    let trace = map_matching::decode_trace(&req.gpx_base64).map_err(map_match_error)?;
+
+    let (min_lat, max_lat, min_lon, max_lon) = backend::geo_utils::compute_bounds(&trace);
+    let bbox = BoundingBox::from_route(
+        Coordinate { lat: min_lat, lon: min_lon },
+        Coordinate { lat: max_lat, lon: max_lon },
+        1.0,
+    );
+    bbox.validate()
+        .map_err(|err_msg| api_error((StatusCode::BAD_REQUEST, format!("Invalid request: {}", err_msg))))?;
+
+    let engine = get_or_build_engine(&config, bbox).await.map_err(api_error)?;
+    let matched = map_matching::map_match(&engine, trace).await.map_err(map_match_error)?;
+
+    tracing::info!(
+        "PERF TOTAL /api/route/match: {:.0}ms ({:.1}km, {} unmatched sections)",
+        t_total.elapsed().as_secs_f64() * 1000.0,
+        matched.route.distance_km,
+        matched.unmatched.len()
+    );
+    Ok(Json(matched))
+}
+
+/// Handler for /api/nearest - snap candidates around a click
+async fn nearest_handler(
+    State(config): State<Arc<PartialGraphConfig>>,
+    Json(req): Json<NearestRequest>,
+) -> Result<Json<NearestResponse>, (StatusCode, String)> {
+    let bbox = BoundingBox::from_route(req.point, req.point, 2.0);
+    let engine = get_or_build_engine(&config, bbox).await?;
+    Ok(Json(NearestResponse {
+        candidates: engine.nearest(req.point, req.count),
+    }))
+}
+
+/// Handler for /api/route/detour - A→B route stretched to a target length
+async fn detour_route_handler(
+    State(config): State<Arc<PartialGraphConfig>>,
+    Json(req): Json<DetourRouteRequest>,
+) -> Result<Json<DetourRouteResponse>, (StatusCode, Json<ApiError>)> {
+    let t_total = std::time::Instant::now();
+    tracing::info!(
+        "Detour request: {:?} -> {:?} targeting {:.1} km",
+        req.start,
+        req.end,
+        req.target_distance_km
+    );
+
+    // The widest ellipse reaches about half the extra length beyond the endpoints
+    let extra_km = (req.target_distance_km - haversine_km(req.start, req.end)).max(0.0);
+    let margin_km = (extra_km / 2.0 * 1.4 + req.distance_tolerance_km.max(1.0)).max(5.0);
+    let bbox = BoundingBox::from_route(req.start, req.end, margin_km);
+    let engine = get_or_build_engine(&config, bbox).await.map_err(api_error)?;
+
+    let response = loops::generate_detours(&engine, &req).await.map_err(loop_error)?;
+    tracing::info!(
+        "PERF TOTAL /api/route/detour: {:.0}ms ({} candidates)",
+        t_total.elapsed().as_secs_f64() * 1000.0,
+        response.candidates.len()
+    );
+    Ok(Json(response))
+}
+
 /// Handler for /api/route/multi - optimized multi-waypoint routing with single graph generation
 async fn multi_route_handler(
     State(config): State<Arc<PartialGraphConfig>>,
@@ -724,6 +842,8 @@ async fn multi_route_handler(
             "At least 2 waypoints required".to_string(),
         ));
     }
+    backend::water::validate_max_km_without_water(req.options.max_km_without_water)
+        .map_err(|message| (StatusCode::BAD_REQUEST, message.to_string()))?;
 
     tracing::info!(
         "Multi-point route request: {} waypoints, close_loop={}",
@@ -773,7 +893,7 @@ async fn multi_route_handler(
     let push_dedup = |coords: &mut Vec<Coordinate>, c: Coordinate| {
         if coords
             .last()
-            .map_or(true, |last| {
+            .is_none_or(|last| {
                 (last.lat - c.lat).abs() > 1e-7 || (last.lon - c.lon).abs() > 1e-7
             })
         {
@@ -786,6 +906,7 @@ async fn multi_route_handler(
     // We also collect the snapped waypoint positions (on-road projections) so the
     // frontend can place markers exactly on the route line.
     let mut all_coords: Vec<Coordinate> = Vec::new();
+    let mut all_nodes = Vec::new();
     let mut snapped_waypoints: Vec<Coordinate> = Vec::new();
     let mut total_distance = 0.0;
     // Track segment boundaries: (start_idx, end_idx) in all_coords
@@ -798,11 +919,12 @@ async fn multi_route_handler(
             end: points[i + 1],
             w_pop: req.w_pop,
             w_paved: req.w_paved,
+            options: req.options.clone(),
         };
 
         let t_seg = std::time::Instant::now();
-        match engine.find_path(&segment_req) {
-            Some(path) => {
+        match engine.find_path_returning_indices(&segment_req) {
+            Some((path, segment_nodes)) => {
                 tracing::info!(
                     "PERF segment {}/{}: {:.0}ms ({} pts)",
                     i + 1,
@@ -827,6 +949,7 @@ async fn multi_route_handler(
                 for &coord in &path {
                     push_dedup(&mut all_coords, coord);
                 }
+                RouteEngine::append_leg_nodes(&mut all_nodes, &segment_nodes);
 
                 let end_idx = all_coords.len() - 1;
                 segment_boundaries.push((if i == 0 { 0 } else { actual_start }, end_idx));
@@ -839,6 +962,12 @@ async fn multi_route_handler(
                 total_distance = segment_distance;
             }
             None => {
+                if let Some(message) = engine
+                    .disconnected_island_message(&segment_req)
+                    .or_else(|| engine.no_accessible_route_message(&segment_req))
+                {
+                    return Err((StatusCode::NOT_FOUND, format!("Segment {} -> {}: {}", i + 1, i + 2, message)));
+                }
                 return Err((
                     StatusCode::NOT_FOUND,
                     format!(
@@ -882,15 +1011,6 @@ async fn multi_route_handler(
         }
     };
 
-    let (estimated_time, difficulty) = match &elevation_profile {
-        Some(profile) => {
-            let time = estimate_time_minutes(total_distance, profile.total_ascent);
-            let diff = rate_difficulty(&profile.elevations, &all_coords, profile.total_ascent);
-            (Some(time), Some(diff))
-        }
-        None => (None, None),
-    };
-
     // Compute per-segment statistics
     let segments = if segment_boundaries.len() >= 2 {
         let seg_stats: Vec<shared::SegmentStats> = segment_boundaries
@@ -946,19 +1066,12 @@ async fn multi_route_handler(
         None
     };
 
-    let response = RouteResponse {
-        path: all_coords,
-        distance_km: total_distance,
-        gpx_base64: String::new(),
-        metadata: None,
-        elevation_profile,
-        terrain: None,
-        snapped_waypoints: Some(snapped_waypoints),
-        estimated_time_minutes: estimated_time,
-        difficulty,
-        surface_breakdown: None,
-        segments,
-    };
+    // Waypoints are the user's own: the water report flags gaps without re-routing
+    let mut response = engine
+        .route_response(all_coords, &all_nodes, elevation_profile, &req.options)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    response.snapped_waypoints = Some(snapped_waypoints);
+    response.segments = segments;
 
     tracing::info!("PERF TOTAL /api/route/multi: {:.0}ms ({} wps, {:.2}km)", t_total.elapsed().as_secs_f64() * 1000.0, req.waypoints.len(), total_distance);
     Ok(Json(response))
@@ -1052,8 +1165,16 @@ async fn main() {
             axum::routing::post(backend::partial_graph::partial_graph_handler),
         )
         .route("/api/loops", axum::routing::post(loop_route_handler))
+        .route(
+            "/api/loops/stream",
+            axum::routing::get(loop_stream_get_handler).post(loop_stream_post_handler),
+        )
         .route("/api/route", axum::routing::post(route_handler))
         .route("/api/route/multi", axum::routing::post(multi_route_handler))
+        .route("/api/route/detour", axum::routing::post(detour_route_handler))
+        .route("/api/route/match", axum::routing::post(map_match_handler))
+        .route("/api/nearest", axum::routing::post(nearest_handler))
+        .route("/api/itinerary", axum::routing::post(itinerary_handler))
         .route("/api/roads", axum::routing::post(roads_handler))
         .route("/api/ign-roads", axum::routing::post(ign_roads_handler))
         .route("/api/ign-vegetation", axum::routing::post(ign_vegetation_handler))
@@ -1071,6 +1192,8 @@ async fn main() {
         .route("/api/routes/:id", axum::routing::get(saved_routes_handlers::get_route))
         .route("/api/routes/:id", axum::routing::delete(saved_routes_handlers::delete_route))
         .route("/api/routes/:id/favorite", axum::routing::post(saved_routes_handlers::toggle_favorite))
+        .route("/api/route-groups", axum::routing::post(saved_routes_handlers::save_route_group))
+        .route("/api/route-groups/:id", axum::routing::get(saved_routes_handlers::get_route_group))
         .layer(cors)
         .with_state(db);
 
@@ -1079,7 +1202,12 @@ async fn main() {
     tracing::info!("API endpoints:");
     tracing::info!("  POST /api/route - Find route with on-demand graph generation");
     tracing::info!("  POST /api/route/multi - Multi-waypoint route with single graph generation");
+    tracing::info!("  POST /api/route/detour - A→B route close to a target length");
+    tracing::info!("  POST /api/route/match - Snap a recorded GPX track onto the graph");
+    tracing::info!("  POST /api/nearest - Snap candidates around a point");
+    tracing::info!("  POST /api/itinerary - Split a multi-day trek into daily stages");
     tracing::info!("  POST /api/loops - Generate loop candidates");
+    tracing::info!("  GET|POST /api/loops/stream - Stream loop candidates (Server-Sent Events)");
     tracing::info!("  POST /api/graph/partial - Generate partial graph");
     tracing::info!("  GET /api/click_mode - Get click mode");
     tracing::info!("Saved routes (PostgreSQL):");
@@ -1088,6 +1216,8 @@ async fn main() {
     tracing::info!("  GET /api/routes/:id - Get specific route");
     tracing::info!("  DELETE /api/routes/:id - Delete route");
     tracing::info!("  POST /api/routes/:id/favorite - Toggle favorite");
+    tracing::info!("  POST /api/route-groups - Save routes as a group (itinerary stages)");
+    tracing::info!("  GET /api/route-groups/:id - Get a route group");
     tracing::info!("Ready to generate graphs on-demand!");
 
     axum::serve(tokio::net::TcpListener::bind(addr).await.unwrap(), app)
@@ -1141,14 +1271,14 @@ fn prepare_graph_for_bbox(
 
     // Check cache first
     if cache_path.exists() {
-        let result = GraphFile::read_from_path(&cache_path).map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to load cache: {}", e),
-            )
-        });
-        tracing::info!("PERF prepare_graph CACHE HIT: {:.0}ms ({})", t0.elapsed().as_secs_f64() * 1000.0, cache_path.display());
-        return result;
+        match GraphFile::read_from_path(&cache_path) {
+            Ok(graph) => {
+                tracing::info!("PERF prepare_graph CACHE HIT: {:.0}ms ({})", t0.elapsed().as_secs_f64() * 1000.0, cache_path.display());
+                return Ok(graph);
+            }
+            // Outdated format or corrupt file: rebuild and overwrite it below
+            Err(e) => tracing::warn!("Ignoring unreadable cache {}: {}", cache_path.display(), e),
+        }
     }
 
     // Try to use tiles if available (FAST - <10s)
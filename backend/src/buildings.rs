@@ -9,6 +9,9 @@ use serde::Serialize;
 
 use crate::models::Coordinate;
 
+type NodeEntries = Vec<(i64, (f64, f64))>;
+type BuildingWays = Vec<(i64, Vec<i64>)>;
+
 #[derive(Debug, Serialize)]
 pub struct Building {
     pub polygon: Vec<Coordinate>,
@@ -28,7 +31,7 @@ pub fn extract_buildings(
         .map_err(|e| format!("Failed to open PBF: {}", e))?;
 
     // Pass 1: collect nodes in bbox and building ways
-    let (node_entries, building_ways): (Vec<(i64, (f64, f64))>, Vec<(i64, Vec<i64>)>) =
+    let (node_entries, building_ways): (NodeEntries, BuildingWays) =
         reader.par_map_reduce(
             |element| match element {
                 Element::Node(node) => {
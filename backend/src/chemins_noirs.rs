@@ -0,0 +1,122 @@
+//! The chemins noirs score.
+//!
+//! How "black" a route is, from 0 (busy paved roads through towns) to 100
+//! (quiet, waymarked tracks and paths away from traffic). Each component is
+//! the length-weighted mean of a per-edge value from 0 to 1, scaled to 100;
+//! the score is their weighted mean. The graph carries no population data,
+//! so busy areas only show through the road class and the quietness.
+
+use crate::{
+    graph::Highway,
+    models::{CheminsNoirsScore, SurfaceType},
+};
+
+/// Component weights, summing to 1: the kind of way matters most
+const ROAD_CLASS_WEIGHT: f64 = 0.375;
+const SURFACE_WEIGHT: f64 = 0.25;
+const QUIETNESS_WEIGHT: f64 = 0.25;
+const WAYMARKED_WEIGHT: f64 = 0.125;
+
+/// Values of one edge, each from 0 to 1.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EdgeValues {
+    pub road_class: f64,
+    pub surface: f64,
+    pub quietness: f64,
+    pub waymarked: f64,
+}
+
+/// How minor a `highway=*` class is, from 0 (trunk and primary roads) to 1
+/// (paths and tracks). Ways without a class are halfway.
+pub fn road_class_value(highway: Option<Highway>) -> f64 {
+    match highway {
+        Some(Highway::Path | Highway::Footway | Highway::Bridleway | Highway::Track | Highway::Steps) => 1.0,
+        Some(Highway::Cycleway) => 0.8,
+        Some(Highway::Unclassified | Highway::Service) => 0.6,
+        Some(Highway::Residential | Highway::LivingStreet | Highway::Pedestrian) => 0.4,
+        Some(Highway::Tertiary | Highway::TertiaryLink) => 0.3,
+        Some(Highway::Secondary | Highway::SecondaryLink) => 0.1,
+        Some(Highway::Primary | Highway::PrimaryLink | Highway::Trunk | Highway::TrunkLink) => 0.0,
+        None => 0.5,
+    }
+}
+
+/// How unpaved a surface is, the complement of the paved penalty of the
+/// routing cost.
+pub fn surface_value(surface: SurfaceType) -> f64 {
+    match surface {
+        SurfaceType::Paved => 0.0,
+        SurfaceType::Trail => 0.8,
+        SurfaceType::Dirt => 1.0,
+    }
+}
+
+/// Score of a route from the values and lengths of its edges, `None` when
+/// there is no length to weigh.
+pub fn score(edges: impl IntoIterator<Item = (EdgeValues, f64)>) -> Option<CheminsNoirsScore> {
+    let mut sums = EdgeValues::default();
+    let mut total_km = 0.0;
+    for (values, length_km) in edges {
+        sums.road_class += values.road_class * length_km;
+        sums.surface += values.surface * length_km;
+        sums.quietness += values.quietness * length_km;
+        sums.waymarked += values.waymarked * length_km;
+        total_km += length_km;
+    }
+    if total_km <= 0.0 {
+        return None;
+    }
+
+    let percent = |sum: f64| 100.0 * sum / total_km;
+    let (road_class, surface, quietness, waymarked) = (
+        percent(sums.road_class),
+        percent(sums.surface),
+        percent(sums.quietness),
+        percent(sums.waymarked),
+    );
+    Some(CheminsNoirsScore {
+        score: ROAD_CLASS_WEIGHT * road_class
+            + SURFACE_WEIGHT * surface
+            + QUIETNESS_WEIGHT * quietness
+            + WAYMARKED_WEIGHT * waymarked,
+        road_class,
+        surface,
+        quietness,
+        waymarked,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_weighs_components_by_length() {
+        let track = EdgeValues {
+            road_class: road_class_value(Some(Highway::Track)),
+            surface: surface_value(SurfaceType::Dirt),
+            quietness: 1.0,
+            waymarked: 1.0,
+        };
+        let road = EdgeValues {
+            road_class: road_class_value(Some(Highway::SecondaryLink)),
+            surface: surface_value(SurfaceType::Paved),
+            quietness: 0.0,
+            waymarked: 0.0,
+        };
+
+        let wild = score([(track, 2.0)]).expect("score");
+        assert!((wild.score - 100.0).abs() < 1e-9);
+
+        // Three quarters of the length on the track
+        let mixed = score([(track, 3.0), (road, 1.0)]).expect("score");
+        assert!((mixed.road_class - 77.5).abs() < 1e-9);
+        assert!((mixed.surface - 75.0).abs() < 1e-9);
+        assert!((mixed.quietness - 75.0).abs() < 1e-9);
+        assert!((mixed.score - (0.375 * 77.5 + 0.625 * 75.0)).abs() < 1e-9);
+        assert!(mixed.score < wild.score);
+
+        assert!(score([]).is_none());
+        assert_eq!(road_class_value(None), 0.5);
+    }
+}
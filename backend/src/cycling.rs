@@ -0,0 +1,129 @@
+//! Cycling profile.
+//!
+//! Ways where bicycles are forbidden (`bicycle=no`) are impassable. Steps,
+//! footways without bicycle permission, `bicycle=dismount` and MTB sections
+//! above the rider's `max_mtb_scale` are walked with the bike (hike-a-bike)
+//! at a heavy penalty. Cycleways and roads with a cycle track or lane cost
+//! less than plain roads.
+
+use crate::{
+    graph::{Highway, WayAttributes},
+    models::SurfaceType,
+};
+
+/// Highest `mtb:scale` ridden when the request does not set `max_mtb_scale`
+pub const DEFAULT_MAX_MTB_SCALE: u8 = 2;
+/// Extra cost per km when pushing or carrying the bike: walking pace is
+/// roughly a quarter of riding pace
+pub const PUSH_PENALTY: f64 = 3.0;
+/// Extra cost per km for each `mtb:scale` level
+const MTB_SCALE_PENALTY: f64 = 0.2;
+
+/// How a bike gets along an edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CyclingEdge {
+    /// The bike has to be walked whatever the rider's level
+    pub must_push: bool,
+    pub mtb_scale: Option<u8>,
+    /// Extra cost per km, relative to the length, from the cycling
+    /// infrastructure (or lack of it)
+    pub penalty: f64,
+}
+
+impl CyclingEdge {
+    /// Whether the bike is walked on this edge by a rider comfortable up to
+    /// `max_mtb_scale`.
+    pub fn is_hike_a_bike(&self, max_mtb_scale: u8) -> bool {
+        self.must_push || self.mtb_scale.is_some_and(|scale| scale > max_mtb_scale)
+    }
+
+    /// Extra cost per km for a rider comfortable up to `max_mtb_scale`.
+    pub fn cost_penalty(&self, max_mtb_scale: u8) -> f64 {
+        if self.is_hike_a_bike(max_mtb_scale) {
+            PUSH_PENALTY
+        } else {
+            self.penalty + MTB_SCALE_PENALTY * f64::from(self.mtb_scale.unwrap_or(0))
+        }
+    }
+}
+
+/// Cycling semantics of a way, or `None` if bicycles are forbidden on it.
+pub fn cycling_edge(attributes: &WayAttributes, surface: SurfaceType) -> Option<CyclingEdge> {
+    let bicycle = attributes.bicycle.as_deref();
+    if matches!(bicycle, Some("no" | "private" | "use_sidepath")) {
+        return None;
+    }
+    let highway = attributes.highway;
+    let allowed = matches!(bicycle, Some("yes" | "designated" | "permissive"));
+
+    let must_push = highway == Some(Highway::Steps)
+        || bicycle == Some("dismount")
+        || (matches!(highway, Some(Highway::Footway | Highway::Pedestrian)) && !allowed);
+
+    let infrastructure = if highway == Some(Highway::Cycleway) || bicycle == Some("designated") {
+        0.0
+    } else {
+        match attributes.cycleway.as_deref() {
+            Some("track" | "opposite_track") => 0.0,
+            Some("lane" | "opposite_lane") => 0.2,
+            _ => match highway {
+                Some(Highway::Trunk | Highway::TrunkLink | Highway::Primary | Highway::PrimaryLink) => 1.0,
+                Some(Highway::Secondary | Highway::SecondaryLink) => 0.6,
+                Some(Highway::Tertiary | Highway::TertiaryLink) => 0.4,
+                _ => 0.3,
+            },
+        }
+    };
+    // Unpaved ways are slower on a bike even when rideable
+    let roughness = match surface {
+        SurfaceType::Paved => 0.0,
+        SurfaceType::Trail => 0.2,
+        SurfaceType::Dirt => 0.4,
+    };
+
+    Some(CyclingEdge {
+        must_push,
+        mtb_scale: attributes.mtb_scale,
+        penalty: infrastructure + roughness,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(tags: &[(&str, &str)]) -> WayAttributes {
+        let tags: Vec<(String, String)> =
+            tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        WayAttributes::from_tags(&tags)
+    }
+
+    #[test]
+    fn test_cycling_edge_honours_bicycle_tags() {
+        let paved = SurfaceType::Paved;
+        assert!(cycling_edge(&attributes(&[("highway", "path"), ("bicycle", "no")]), paved).is_none());
+
+        let footway = cycling_edge(&attributes(&[("highway", "footway")]), paved).unwrap();
+        assert!(footway.must_push);
+        let shared = cycling_edge(&attributes(&[("highway", "footway"), ("bicycle", "yes")]), paved).unwrap();
+        assert!(!shared.must_push);
+        assert!(cycling_edge(&attributes(&[("highway", "steps")]), paved).unwrap().must_push);
+
+        let track = cycling_edge(&attributes(&[("highway", "secondary"), ("cycleway:right", "track")]), paved);
+        let lane = cycling_edge(&attributes(&[("highway", "secondary"), ("cycleway", "lane")]), paved);
+        let bare = cycling_edge(&attributes(&[("highway", "secondary")]), paved);
+        assert_eq!(track.unwrap().penalty, 0.0);
+        assert!(track.unwrap().penalty < lane.unwrap().penalty);
+        assert!(lane.unwrap().penalty < bare.unwrap().penalty);
+    }
+
+    #[test]
+    fn test_mtb_scale_above_rider_level_is_hike_a_bike() {
+        let edge = cycling_edge(&attributes(&[("highway", "path"), ("mtb:scale", "3+")]), SurfaceType::Dirt).unwrap();
+        assert_eq!(edge.mtb_scale, Some(3));
+        assert!(edge.is_hike_a_bike(2));
+        assert!(!edge.is_hike_a_bike(3));
+        assert_eq!(edge.cost_penalty(2), PUSH_PENALTY);
+        assert!((edge.cost_penalty(4) - (0.3 + 0.4 + 0.6)).abs() < 1e-9);
+    }
+}
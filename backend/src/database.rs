@@ -17,6 +17,9 @@ pub enum DatabaseError {
     #[error("Route not found: {0}")]
     NotFound(i32),
 
+    #[error("Route group not found: {0}")]
+    GroupNotFound(i32),
+
     #[error("Invalid route data: {0}")]
     InvalidData(String),
 
@@ -42,6 +45,41 @@ pub struct SavedRoute {
     /// Original waypoints for multi-point routes (optional for backward compatibility)
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub original_waypoints: Option<sqlx::types::JsonValue>,
+    /// Route group this route belongs to (e.g. a multi-day itinerary)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub group_id: Option<i32>,
+    /// Position within the group (0 = first stage)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub group_position: Option<i32>,
+    /// Distance per surface, copied from the route
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub surface_breakdown: Option<sqlx::types::JsonValue>,
+    /// Distance per highway class, copied from the route
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub highway_breakdown: Option<sqlx::types::JsonValue>,
+    /// Chemins noirs score, copied from the route
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub chemins_noirs_score: Option<f32>,
+}
+
+/// Order of the saved routes list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteSort {
+    /// Most recently saved first
+    #[default]
+    Newest,
+    /// Highest chemins noirs score first, unscored routes last
+    CheminsNoirs,
+}
+
+impl RouteSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            RouteSort::Newest => "created_at DESC",
+            RouteSort::CheminsNoirs => "chemins_noirs_score DESC NULLS LAST, created_at DESC",
+        }
+    }
 }
 
 /// Request to save a new route
@@ -56,6 +94,50 @@ pub struct SaveRouteRequest {
     pub original_waypoints: Option<Vec<shared::Coordinate>>,
 }
 
+/// Ordered collection of saved routes, e.g. the stages of a multi-day trek
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedRouteGroup {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Member routes in group order
+    pub routes: Vec<SavedRoute>,
+}
+
+/// Request to save several routes as one group
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveRouteGroupRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Routes in order; each is saved as "<name> - day <n>/<count>"
+    pub routes: Vec<RouteResponse>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(FromRow)]
+struct RouteGroupRow {
+    id: i32,
+    name: String,
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl RouteGroupRow {
+    fn with_routes(self, routes: Vec<SavedRoute>) -> SavedRouteGroup {
+        SavedRouteGroup {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            routes,
+        }
+    }
+}
+
 /// Database connection pool
 pub struct Database {
     pool: PgPool,
@@ -92,12 +174,19 @@ impl Database {
         // SQLx query() cannot handle multiple statements, so we use a raw connection
         let mut conn = self.pool.acquire().await?;
 
-        let migration_sql = include_str!("../migrations/20250128_create_saved_routes.sql");
+        let migrations = [
+            include_str!("../migrations/20250128_create_saved_routes.sql"),
+            include_str!("../migrations/20261018_create_route_groups.sql"),
+            include_str!("../migrations/20261018_add_route_breakdowns.sql"),
+            include_str!("../migrations/20261018_add_chemins_noirs_score.sql"),
+        ];
 
         // Execute using raw SQL (supports multiple statements)
-        sqlx::raw_sql(migration_sql)
-            .execute(&mut *conn)
-            .await?;
+        for migration_sql in migrations {
+            sqlx::raw_sql(migration_sql)
+                .execute(&mut *conn)
+                .await?;
+        }
 
         tracing::info!("Database migrations completed");
         Ok(())
@@ -111,6 +200,17 @@ impl Database {
     /// # Returns
     /// The saved route with generated ID
     pub async fn save_route(&self, req: SaveRouteRequest) -> Result<SavedRoute, DatabaseError> {
+        let route = Self::insert_route(&self.pool, req, None).await?;
+        tracing::info!("Route saved: {} (ID: {})", route.name, route.id);
+        Ok(route)
+    }
+
+    /// Insert one route, optionally as member `(group_id, position)` of a group
+    async fn insert_route<'e, E: sqlx::PgExecutor<'e>>(
+        executor: E,
+        req: SaveRouteRequest,
+        group: Option<(i32, i32)>,
+    ) -> Result<SavedRoute, DatabaseError> {
         let route_json = serde_json::to_value(&req.route)
             .map_err(|e| DatabaseError::InvalidData(e.to_string()))?;
 
@@ -124,13 +224,20 @@ impl Database {
         let original_waypoints_json = req.original_waypoints
             .and_then(|wp| serde_json::to_value(wp).ok());
 
+        let surface_breakdown_json = req.route.surface_breakdown.as_ref()
+            .and_then(|breakdown| serde_json::to_value(breakdown).ok());
+        let highway_breakdown_json = req.route.highway_breakdown.as_ref()
+            .and_then(|breakdown| serde_json::to_value(breakdown).ok());
+        let chemins_noirs_score = req.route.chemins_noirs.map(|score| score.score as f32);
+
         let route = sqlx::query_as::<_, SavedRoute>(
             r#"
             INSERT INTO saved_routes (
                 name, description, distance_km, total_ascent_m, total_descent_m,
-                route_data, gpx_data, tags, original_waypoints
+                route_data, gpx_data, tags, original_waypoints, group_id, group_position,
+                surface_breakdown, highway_breakdown, chemins_noirs_score
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
             RETURNING *
             "#,
         )
@@ -143,18 +250,80 @@ impl Database {
         .bind(&req.route.gpx_base64)
         .bind(req.tags.unwrap_or_default())
         .bind(original_waypoints_json)
-        .fetch_one(&self.pool)
+        .bind(group.map(|(group_id, _)| group_id))
+        .bind(group.map(|(_, position)| position))
+        .bind(surface_breakdown_json)
+        .bind(highway_breakdown_json)
+        .bind(chemins_noirs_score)
+        .fetch_one(executor)
         .await?;
 
-        tracing::info!("Route saved: {} (ID: {})", route.name, route.id);
         Ok(route)
     }
 
-    /// Get all saved routes (summary only)
-    pub async fn list_routes(&self) -> Result<Vec<SavedRoute>, DatabaseError> {
+    /// Save routes as one ordered group, atomically
+    ///
+    /// # Errors
+    /// Returns InvalidData if `routes` is empty
+    pub async fn save_route_group(
+        &self,
+        req: SaveRouteGroupRequest,
+    ) -> Result<SavedRouteGroup, DatabaseError> {
+        if req.routes.is_empty() {
+            return Err(DatabaseError::InvalidData("route group has no routes".to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let group = sqlx::query_as::<_, RouteGroupRow>(
+            "INSERT INTO route_groups (name, description) VALUES ($1, $2) RETURNING *",
+        )
+        .bind(&req.name)
+        .bind(&req.description)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let count = req.routes.len();
+        let mut routes = Vec::with_capacity(count);
+        for (position, route) in (0..).zip(req.routes) {
+            let member = SaveRouteRequest {
+                name: format!("{} - day {}/{}", req.name, position + 1, count),
+                description: req.description.clone(),
+                route,
+                tags: req.tags.clone(),
+                original_waypoints: None,
+            };
+            routes.push(Self::insert_route(&mut *tx, member, Some((group.id, position))).await?);
+        }
+        tx.commit().await?;
+
+        tracing::info!("Route group saved: {} (ID: {}, {} routes)", group.name, group.id, count);
+        Ok(group.with_routes(routes))
+    }
+
+    /// Get a route group with its routes in order
+    pub async fn get_route_group(&self, id: i32) -> Result<SavedRouteGroup, DatabaseError> {
+        let group = sqlx::query_as::<_, RouteGroupRow>("SELECT * FROM route_groups WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(DatabaseError::GroupNotFound(id))?;
+
         let routes = sqlx::query_as::<_, SavedRoute>(
-            "SELECT * FROM saved_routes ORDER BY created_at DESC"
+            "SELECT * FROM saved_routes WHERE group_id = $1 ORDER BY group_position",
         )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(group.with_routes(routes))
+    }
+
+    /// Get all saved routes (summary only)
+    pub async fn list_routes(&self, sort: RouteSort) -> Result<Vec<SavedRoute>, DatabaseError> {
+        let routes = sqlx::query_as::<_, SavedRoute>(&format!(
+            "SELECT * FROM saved_routes ORDER BY {}",
+            sort.order_by()
+        ))
         .fetch_all(&self.pool)
         .await?;
 
@@ -287,8 +456,22 @@ mod tests {
             snapped_waypoints: None,
             estimated_time_minutes: None,
             difficulty: None,
+            hardest_section: None,
             surface_breakdown: None,
+            highway_breakdown: None,
             segments: None,
+            water: None,
+            waymarked: None,
+            quietness_score: None,
+            shade_percent: None,
+            lit_percent: None,
+            cycling: None,
+            off_trail: None,
+            debug: None,
+            maneuvers: None,
+            legs: None,
+            chemins_noirs: None,
+            unresolved_snap_choices: vec![],
         };
 
         SaveRouteRequest {
@@ -351,6 +534,22 @@ mod tests {
         assert_eq!(route_response.distance_km, 2.5);
     }
 
+    #[tokio::test]
+    async fn test_save_route_persists_breakdowns() {
+        let (db, _container) = setup_test_db().await;
+        let mut request = create_test_route_request("Breakdown Test");
+        request.route.surface_breakdown = Some(vec![("trail".to_string(), 1.5), ("paved".to_string(), 1.0)]);
+        request.route.highway_breakdown = Some(vec![("path".to_string(), 1.5), ("residential".to_string(), 1.0)]);
+
+        let saved = db.save_route(request).await.expect("Failed to save route");
+        assert_eq!(saved.surface_breakdown, Some(serde_json::json!([["trail", 1.5], ["paved", 1.0]])));
+
+        let retrieved = db.get_route(saved.id).await.expect("Failed to retrieve route");
+        assert_eq!(retrieved.highway_breakdown, Some(serde_json::json!([["path", 1.5], ["residential", 1.0]])));
+        let route = Database::to_route_response(&retrieved).expect("Failed to convert to RouteResponse");
+        assert_eq!(route.surface_breakdown.map(|breakdown| breakdown.len()), Some(2));
+    }
+
     #[tokio::test]
     async fn test_list_routes() {
         let (db, _container) = setup_test_db().await;
@@ -367,7 +566,7 @@ mod tests {
             .expect("Failed to save route 3");
 
         // List all routes
-        let routes = db.list_routes().await.expect("Failed to list routes");
+        let routes = db.list_routes(RouteSort::Newest).await.expect("Failed to list routes");
 
         assert_eq!(routes.len(), 3);
         // Routes are ordered by created_at DESC, so most recent first
@@ -376,6 +575,30 @@ mod tests {
         assert_eq!(routes[2].name, "Route 1");
     }
 
+    #[tokio::test]
+    async fn test_list_routes_by_chemins_noirs_score() {
+        let (db, _container) = setup_test_db().await;
+        let scored = |name: &str, score: Option<f64>| {
+            let mut request = create_test_route_request(name);
+            request.route.chemins_noirs = score.map(|score| shared::CheminsNoirsScore {
+                score,
+                road_class: score,
+                surface: score,
+                quietness: score,
+                waymarked: score,
+            });
+            request
+        };
+        db.save_route(scored("Towns", Some(20.0))).await.expect("Failed to save route");
+        db.save_route(scored("Unscored", None)).await.expect("Failed to save route");
+        db.save_route(scored("Backroads", Some(85.0))).await.expect("Failed to save route");
+
+        let routes = db.list_routes(RouteSort::CheminsNoirs).await.expect("Failed to list routes");
+        let names: Vec<&str> = routes.iter().map(|route| route.name.as_str()).collect();
+        assert_eq!(names, ["Backroads", "Towns", "Unscored"]);
+        assert_eq!(routes[0].chemins_noirs_score, Some(85.0));
+    }
+
     #[tokio::test]
     async fn test_delete_route() {
         let (db, _container) = setup_test_db().await;
@@ -400,6 +623,47 @@ mod tests {
         assert!(matches!(result, Err(DatabaseError::NotFound(_))));
     }
 
+    #[tokio::test]
+    async fn test_save_and_retrieve_route_group() {
+        let (db, _container) = setup_test_db().await;
+        let stages = vec![
+            create_test_route_request("Stage 1").route,
+            create_test_route_request("Stage 2").route,
+        ];
+
+        let saved = db
+            .save_route_group(SaveRouteGroupRequest {
+                name: "GR 5".to_string(),
+                description: None,
+                routes: stages,
+                tags: Some(vec!["trek".to_string()]),
+            })
+            .await
+            .expect("Failed to save route group");
+        assert_eq!(saved.routes.len(), 2);
+        assert_eq!(saved.routes[1].name, "GR 5 - day 2/2");
+        assert_eq!(saved.routes[1].group_id, Some(saved.id));
+
+        let retrieved = db.get_route_group(saved.id).await.expect("Failed to get group");
+        assert_eq!(retrieved.name, "GR 5");
+        let positions: Vec<_> = retrieved.routes.iter().map(|r| r.group_position).collect();
+        assert_eq!(positions, vec![Some(0), Some(1)]);
+
+        assert!(matches!(
+            db.get_route_group(9999).await,
+            Err(DatabaseError::GroupNotFound(9999))
+        ));
+        assert!(db
+            .save_route_group(SaveRouteGroupRequest {
+                name: "Empty".to_string(),
+                description: None,
+                routes: vec![],
+                tags: None,
+            })
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_delete_nonexistent_route() {
         let (db, _container) = setup_test_db().await;
@@ -458,8 +722,22 @@ mod tests {
             snapped_waypoints: None,
             estimated_time_minutes: None,
             difficulty: None,
+            hardest_section: None,
             surface_breakdown: None,
+            highway_breakdown: None,
             segments: None,
+            water: None,
+            waymarked: None,
+            quietness_score: None,
+            shade_percent: None,
+            lit_percent: None,
+            cycling: None,
+            off_trail: None,
+            debug: None,
+            maneuvers: None,
+            legs: None,
+            chemins_noirs: None,
+            unresolved_snap_choices: vec![],
         };
 
         let request = SaveRouteRequest {
@@ -492,7 +770,7 @@ mod tests {
     async fn test_list_routes_empty() {
         let (db, _container) = setup_test_db().await;
 
-        let routes = db.list_routes().await.expect("Failed to list routes");
+        let routes = db.list_routes(RouteSort::Newest).await.expect("Failed to list routes");
         assert_eq!(routes.len(), 0);
     }
 }
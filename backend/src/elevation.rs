@@ -18,6 +18,11 @@ pub enum ElevationError {
 
 /// Get elevation data for a batch of coordinates from local DEM
 pub async fn get_elevations(coords: Vec<(f64, f64)>) -> Result<Vec<f64>, ElevationError> {
+    sample_elevations(&coords)
+}
+
+/// Blocking counterpart of [`get_elevations`] (the DEM is sampled in memory).
+fn sample_elevations(coords: &[(f64, f64)]) -> Result<Vec<f64>, ElevationError> {
     if coords.is_empty() {
         return Ok(Vec::new());
     }
@@ -27,7 +32,7 @@ pub async fn get_elevations(coords: Vec<(f64, f64)>) -> Result<Vec<f64>, Elevati
     let mut values = Vec::with_capacity(coords.len());
     let mut missing_count = 0usize;
 
-    for &(lat, lon) in &coords {
+    for &(lat, lon) in coords {
         match grid.sample(lat, lon) {
             Some(val) => values.push(val),
             None => {
@@ -136,6 +141,11 @@ fn smooth_elevation_profile(path: &[Coordinate], raw: &[Option<f64>]) -> Vec<Opt
 pub async fn create_elevation_profile(
     path: &[Coordinate],
 ) -> Result<ElevationProfile, ElevationError> {
+    compute_elevation_profile(path)
+}
+
+/// Blocking counterpart of [`create_elevation_profile`], for use from worker threads.
+pub fn compute_elevation_profile(path: &[Coordinate]) -> Result<ElevationProfile, ElevationError> {
     if path.is_empty() {
         return Ok(ElevationProfile {
             elevations: Vec::new(),
@@ -150,7 +160,7 @@ pub async fn create_elevation_profile(
     let coords: Vec<(f64, f64)> = path.iter().map(|c| (c.lat, c.lon)).collect();
 
     // Get elevations from local DEM
-    let elevations_vec = get_elevations(coords).await?;
+    let elevations_vec = sample_elevations(&coords)?;
     let raw_elevations: Vec<Option<f64>> = elevations_vec.into_iter().map(Some).collect();
     let elevations = smooth_elevation_profile(path, &raw_elevations);
 
@@ -301,7 +311,7 @@ mod tests {
         // All values should be in reasonable range
         for val in &smoothed {
             let v = val.unwrap();
-            assert!(v >= 95.0 && v <= 115.0);
+            assert!((95.0..=115.0).contains(&v));
         }
     }
 
@@ -354,7 +364,7 @@ mod tests {
         for val in &smoothed {
             assert!(val.is_some());
             let v = val.unwrap();
-            assert!(v >= 95.0 && v <= 135.0);
+            assert!((95.0..=135.0).contains(&v));
         }
         // First and last values should show overall ascent trend
         assert!(smoothed.last().unwrap().unwrap() > smoothed.first().unwrap().unwrap());
@@ -1,20 +1,41 @@
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Ordering,
+    collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet},
     fs::File,
     io::{self, Read},
     path::Path,
 };
 
 use crate::{
-    geo_utils::fast_distance_km,
-    graph::GraphFile,
-    models::{Coordinate, RouteRequest, SurfaceType},
+    error::RouteError,
+    geo_utils::{approximate_distance_km, fast_distance_km, haversine_km, initial_bearing_deg},
+    gpx_export::encode_route_as_gpx,
+    graph::{GraphFile, Highway},
+    instructions::{self, Way},
+    models::{
+        CheminsNoirsScore, Coordinate, CyclingReport, EdgeCost, ElevationProfile,
+        GeoJsonMultiPoint, GradeClass, HardestSection, Language, Maneuver, ManeuverType,
+        OffTrailSection, RouteDebug, RouteLeg, RouteOptions, RouteRequest, RouteResponse,
+        RoutingProfile, SnapCandidate, SnapChoice, SurfaceType, WaymarkedSection,
+    },
+    poi::Poi,
+    accessibility,
+    chemins_noirs::{self, EdgeValues},
+    cycling::{self, CyclingEdge},
+    off_trail,
+    quietness,
+    routing::{estimate_time_minutes, rate_difficulty},
+    sac_scale::{self, TrailVisibility},
+    scenery,
+    water::water_report,
+    waymarked::WaymarkedRoute,
 };
 use kdtree::KdTree;
 use kdtree::distance::squared_euclidean;
 use petgraph::{
     algo::astar,
     graph::{NodeIndex, UnGraph},
+    unionfind::UnionFind,
     visit::EdgeRef,
 };
 
@@ -81,6 +102,10 @@ struct RoadPoint {
     edge_idx: Option<petgraph::graph::EdgeIndex>,
 }
 
+/// A segment of a path as `(from_km, to_km)` along it and the graph edge
+/// whose geometry contains it, if any; see [`RouteEngine::path_edges`].
+pub type PathEdge = (f64, f64, Option<petgraph::graph::EdgeIndex>);
+
 /// Result of snapping a coordinate to the nearest road.
 /// Contains both the graph node for A* routing AND the road polyline
 /// from the projected point to that node ("road prefix").
@@ -91,6 +116,42 @@ struct RoadSnap {
     /// Polyline from the projected point on the road to the snap node,
     /// following the road geometry. Empty if target is right at a node.
     road_prefix: Vec<Coordinate>,
+    /// Edge the target was projected onto; `None` when snapped straight to
+    /// a node
+    edge: Option<petgraph::graph::EdgeIndex>,
+}
+
+/// A recorded trace point projected onto a nearby edge, as a map matching
+/// candidate.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceCandidate {
+    edge: petgraph::graph::EdgeIndex,
+    /// Index of the polyline segment holding the projection
+    segment: usize,
+    /// Position along the edge from its source node, 0 to 1
+    fraction: f64,
+    /// Projected point on the edge
+    pub coord: Coordinate,
+    /// Distance from the recorded point to `coord`
+    pub distance_m: f64,
+}
+
+/// Bounded Dijkstra entry, ordered for a min-heap on distance.
+#[derive(PartialEq)]
+struct MinKm(f64, NodeIndex);
+
+impl Eq for MinKm {}
+
+impl Ord for MinKm {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.partial_cmp(&self.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for MinKm {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 #[derive(Clone)]
@@ -104,6 +165,14 @@ pub struct RouteEngine {
     road_points: Vec<RoadPoint>,
     /// Pre-built edge index for O(1) edge lookup by (source, target) node indices
     edge_map: HashMap<(usize, usize), petgraph::graph::EdgeIndex>,
+    /// Points of interest carried by the graph file (water, huts, peaks...)
+    pois: Vec<Poi>,
+    /// Waymarked route relations referenced by `EdgeData::routes`
+    routes: Vec<WaymarkedRoute>,
+    /// Way names referenced by `EdgeData::name`
+    names: Vec<String>,
+    /// Connected component of each node, 0 being the largest
+    components: Vec<u32>,
 }
 
 impl PathFinder for RouteEngine {
@@ -148,14 +217,81 @@ struct EdgeData {
     mean_population_density: f64,
     /// Intermediate waypoints for this edge (OSM geometry)
     waypoints: Vec<Coordinate>,
+    /// Indices into `RouteEngine::routes`
+    routes: Vec<u32>,
+    /// Best `network_score` of the walking routes on this edge, 0 if none
+    waymarked_score: f64,
+    /// Noise from nearby major roads and railways, 0 to 1
+    noise: f64,
+    /// Forest, water and viewpoint surroundings, 0 to 1
+    scenery: f64,
+    /// Share of the edge under forest cover, 0 to 1
+    shade: f64,
+    /// Tagged `lit=yes` (or another lit value) in OSM
+    lit: bool,
+    /// Wheelchair penalty, `None` when impassable (see `accessibility`)
+    wheelchair_penalty: Option<f64>,
+    /// Steepest known grade in percent
+    grade_percent: Option<f64>,
+    /// `None` when bicycles are forbidden (see `cycling`)
+    cycling: Option<CyclingEdge>,
+    /// SAC level, raised for poor trail visibility (see `sac_scale`)
+    sac_scale: Option<u8>,
+    trail_visibility: Option<TrailVisibility>,
+    highway: Option<Highway>,
+    /// Index into `RouteEngine::names`
+    name: Option<u32>,
 }
 
 #[derive(Clone, Copy)]
 pub struct WeightConfig {
     pub population: f64,
     pub paved: f64,
+    pub waymarked: f64,
+    pub quiet: f64,
+    pub scenery: f64,
+    pub shade: f64,
+    pub lit: f64,
 }
 
+impl WeightConfig {
+    fn from_request(req: &RouteRequest) -> Self {
+        Self {
+            population: req.w_pop,
+            paved: req.w_paved,
+            waymarked: req.options.w_waymarked,
+            quiet: req.options.w_quiet,
+            scenery: req.options.w_scenery,
+            shade: req.options.w_shade,
+            lit: req.options.w_lit,
+        }
+    }
+}
+
+/// Penalties of an edge per km of its length, one per preference weight;
+/// see [`RouteEngine::edge_cost`].
+#[derive(Clone, Copy)]
+struct CostFactors {
+    population: f64,
+    paved: f64,
+    waymarked: f64,
+    quiet: f64,
+    scenery: f64,
+    shade: f64,
+    lit: f64,
+}
+
+/// Consecutive path segments on the same waymarked route are merged across
+/// gaps up to this length (junctions the path crosses off any edge).
+const WAYMARKED_GAP_KM: f64 = 0.05;
+/// Shorter stretches along a waymarked route are not reported
+const MIN_WAYMARKED_SECTION_KM: f64 = 0.1;
+/// `nearest` only considers edges this close to the click
+const NEAREST_RADIUS_M: f64 = 2000.0;
+/// A waypoint snapped onto an island moves to a way of the main network
+/// this close to it
+const ISLAND_SNAP_RADIUS_M: f64 = 500.0;
+
 impl RouteEngine {
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, EngineError> {
         let file = File::open(path)?;
@@ -201,11 +337,33 @@ impl RouteEngine {
                 let b = graph[to].population_density;
                 (a + b) / 2.0
             };
+            let waymarked_score = edge
+                .routes
+                .iter()
+                .filter_map(|&i| graph_file.routes.get(i as usize))
+                .filter(|route| route.is_walking())
+                .map(WaymarkedRoute::network_score)
+                .fold(0.0, f64::max);
+            let noise = quietness::noise_level(&edge.environment);
+            let scenery = scenery::scenery_level(&edge.environment);
             let data = EdgeData {
                 length_km,
                 surface: edge.surface,
                 mean_population_density,
                 waypoints: edge.waypoints,
+                routes: edge.routes,
+                waymarked_score,
+                noise,
+                scenery,
+                shade: edge.environment.forest_fraction.clamp(0.0, 1.0),
+                lit: edge.attributes.lit == Some(true),
+                wheelchair_penalty: accessibility::wheelchair_penalty(&edge.attributes, edge.surface),
+                grade_percent: accessibility::grade_percent(&edge.attributes, &edge.environment),
+                cycling: cycling::cycling_edge(&edge.attributes, edge.surface),
+                sac_scale: sac_scale::effective_sac_scale(&edge.attributes),
+                trail_visibility: edge.attributes.trail_visibility,
+                highway: edge.attributes.highway,
+                name: edge.attributes.name,
             };
             graph.update_edge(from, to, data);
         }
@@ -215,8 +373,414 @@ impl RouteEngine {
 
         // Build edge lookup map for O(1) edge access
         let edge_map = Self::build_edge_map(&graph);
+        let components = component_labels(&graph);
+
+        Ok(Self {
+            graph,
+            nodes,
+            road_point_index,
+            road_points,
+            edge_map,
+            components,
+            pois: graph_file.pois,
+            routes: graph_file.routes,
+            names: graph_file.names,
+        })
+    }
+
+    /// Points of interest loaded with the graph.
+    pub fn pois(&self) -> &[Poi] {
+        &self.pois
+    }
+
+    fn way_name(&self, edge: &EdgeData) -> Option<String> {
+        edge.name.and_then(|name| self.names.get(name as usize).cloned())
+    }
+
+    /// Waymarked routes followed by a path, given by its [`Self::path_edges`],
+    /// in order of their first point along it, or `None` when it follows none.
+    ///
+    /// Works on coordinates so that any path assembled from engine legs
+    /// (loops, detours, multi-point routes) can be reported: each segment
+    /// is matched to the graph edge whose geometry contains it.
+    pub fn waymarked_sections(&self, edges: &[PathEdge]) -> Option<Vec<WaymarkedSection>> {
+        if self.routes.is_empty() || edges.is_empty() {
+            return None;
+        }
 
-        Ok(Self { graph, nodes, road_point_index, road_points, edge_map })
+        // route index -> (from_km, to_km) of the section being extended
+        let mut open: HashMap<u32, (f64, f64)> = HashMap::new();
+        let mut closed: Vec<(u32, f64, f64)> = Vec::new();
+
+        for &(start_km, end_km, edge) in edges {
+            for &route in edge.map(|e| self.graph[e].routes.as_slice()).unwrap_or_default() {
+                match open.get_mut(&route) {
+                    Some(section) if start_km - section.1 <= WAYMARKED_GAP_KM => section.1 = end_km,
+                    _ => {
+                        if let Some((from, to)) = open.insert(route, (start_km, end_km)) {
+                            closed.push((route, from, to));
+                        }
+                    }
+                }
+            }
+        }
+        closed.extend(open.into_iter().map(|(route, (from, to))| (route, from, to)));
+
+        let mut sections: Vec<WaymarkedSection> = closed
+            .into_iter()
+            .filter(|(_, from, to)| to - from >= MIN_WAYMARKED_SECTION_KM)
+            .filter_map(|(route, from_km, to_km)| {
+                let route = self.routes.get(route as usize)?;
+                Some(WaymarkedSection {
+                    osm_id: route.osm_id,
+                    route: route.route.clone(),
+                    network: route.network.clone(),
+                    name: route.name.clone(),
+                    reference: route.reference.clone(),
+                    colour: route.colour.clone(),
+                    osmc_symbol: route.osmc_symbol.clone(),
+                    from_km,
+                    to_km,
+                })
+            })
+            .collect();
+        sections.sort_by(|a, b| {
+            a.from_km
+                .partial_cmp(&b.from_km)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.osm_id.cmp(&b.osm_id))
+        });
+        (!sections.is_empty()).then_some(sections)
+    }
+
+    /// Route-level quietness from 0 (along major roads or railways) to 100,
+    /// from the length-weighted mean noise of the edges followed by a path,
+    /// given by its [`Self::path_edges`]. Segments off the graph (snapping
+    /// legs) count as quiet.
+    pub fn quietness_score(&self, edges: &[PathEdge]) -> Option<f64> {
+        self.length_weighted_mean(edges, |edge| edge.noise)
+            .map(|noise| 100.0 * (1.0 - noise))
+    }
+
+    /// Percentage of the length of a path under forest cover. Segments off
+    /// the graph count as unshaded.
+    pub fn shade_percent(&self, edges: &[PathEdge]) -> Option<f64> {
+        self.length_weighted_mean(edges, |edge| edge.shade)
+            .map(|shade| 100.0 * shade)
+    }
+
+    /// Percentage of the length of a path on ways tagged as lit. Segments
+    /// off the graph count as unlit.
+    pub fn lit_percent(&self, edges: &[PathEdge]) -> Option<f64> {
+        self.length_weighted_mean(edges, |edge| if edge.lit { 1.0 } else { 0.0 })
+            .map(|lit| 100.0 * lit)
+    }
+
+    /// The chemins noirs score of a path, over the edges it follows;
+    /// segments off the graph are left out. `None` when it follows none.
+    pub fn chemins_noirs_score(&self, edges: &[PathEdge]) -> Option<CheminsNoirsScore> {
+        chemins_noirs::score(edges.iter().filter_map(|&(from_km, to_km, edge)| {
+            let edge = &self.graph[edge?];
+            let values = EdgeValues {
+                road_class: chemins_noirs::road_class_value(edge.highway),
+                surface: chemins_noirs::surface_value(edge.surface),
+                quietness: 1.0 - edge.noise,
+                waymarked: if edge.waymarked_score > 0.0 { 1.0 } else { 0.0 },
+            };
+            Some((values, to_km - from_km))
+        }))
+    }
+
+    /// `path` as a [`RouteResponse`] with its GPX export and every report
+    /// the engine derives from a route. `nodes` are the graph nodes it goes
+    /// through in order, empty when unknown; `options` select the water,
+    /// cycling and off-trail reports and the language of the maneuvers.
+    /// Without `elevation` the time is estimated on the flat and there is
+    /// no difficulty. Fields that depend on the request (debug, segments,
+    /// snapped waypoints) are left for the caller to fill.
+    pub fn route_response(
+        &self,
+        path: Vec<Coordinate>,
+        nodes: &[NodeIndex],
+        elevation: Option<ElevationProfile>,
+        options: &RouteOptions,
+    ) -> Result<RouteResponse, RouteError> {
+        let distance_km = approximate_distance_km(&path);
+        let total_ascent = elevation.as_ref().map_or(0.0, |profile| profile.total_ascent);
+        let difficulty = elevation
+            .as_ref()
+            .map(|profile| rate_difficulty(&profile.elevations, &path, profile.total_ascent));
+        // Every report below walks the same edges
+        let edges = self.path_edges(&path);
+        let legs = self.route_legs(&edges, elevation.as_ref().map(|profile| profile.elevations.as_slice()));
+        Ok(RouteResponse {
+            gpx_base64: encode_route_as_gpx(&path)?,
+            metadata: Some(crate::build_metadata(&path)),
+            elevation_profile: elevation,
+            terrain: None,
+            snapped_waypoints: None,
+            estimated_time_minutes: Some(estimate_time_minutes(distance_km, total_ascent)),
+            difficulty,
+            hardest_section: self.hardest_section(&edges),
+            surface_breakdown: self.surface_breakdown(&edges),
+            highway_breakdown: self.highway_breakdown(&edges),
+            segments: None,
+            water: options
+                .max_km_without_water
+                .map(|max_km| water_report(self.pois(), &path, max_km)),
+            waymarked: self.waymarked_sections(&edges),
+            quietness_score: self.quietness_score(&edges),
+            shade_percent: self.shade_percent(&edges),
+            lit_percent: self.lit_percent(&edges),
+            cycling: self.cycling_report(&edges, options),
+            off_trail: self.off_trail_sections(&edges, options),
+            debug: None,
+            maneuvers: self.maneuvers(&path, nodes, &edges, options.language),
+            legs,
+            chemins_noirs: self.chemins_noirs_score(&edges),
+            unresolved_snap_choices: options
+                .snap_choices
+                .iter()
+                .filter(|choice| self.resolve_snap_choice(choice).is_none())
+                .map(|choice| choice.point)
+                .collect(),
+            distance_km,
+            path,
+        })
+    }
+
+    /// Rideable and hike-a-bike distances along a path for a cycling
+    /// request, `None` for other profiles. Segments off the graph count as
+    /// rideable.
+    pub fn cycling_report(&self, edges: &[PathEdge], options: &RouteOptions) -> Option<CyclingReport> {
+        if options.profile != RoutingProfile::Cycling {
+            return None;
+        }
+        let max_mtb_scale = options.max_mtb_scale.unwrap_or(cycling::DEFAULT_MAX_MTB_SCALE);
+        let mut report = CyclingReport { rideable_km: 0.0, hike_a_bike_km: 0.0, max_mtb_scale: None };
+        for &(from_km, to_km, edge) in edges {
+            let cycling = edge.and_then(|e| self.graph[e].cycling);
+            if cycling.is_some_and(|c| c.is_hike_a_bike(max_mtb_scale)) {
+                report.hike_a_bike_km += to_km - from_km;
+            } else {
+                report.rideable_km += to_km - from_km;
+            }
+            if let Some(scale) = cycling.and_then(|c| c.mtb_scale) {
+                report.max_mtb_scale = Some(report.max_mtb_scale.map_or(scale, |m| m.max(scale)));
+            }
+        }
+        Some(report)
+    }
+
+    /// First stretch of a path at its highest SAC grade, `None` when no
+    /// edge along it is graded.
+    pub fn hardest_section(&self, edges: &[PathEdge]) -> Option<HardestSection> {
+        let graded: Vec<_> = edges
+            .iter()
+            .map(|&(from_km, to_km, edge)| (from_km, to_km, edge.map(|e| &self.graph[e])))
+            .collect();
+        let hardest = graded.iter().filter_map(|(_, _, edge)| edge.and_then(|e| e.sac_scale)).max()?;
+
+        let mut section: Option<HardestSection> = None;
+        for (from_km, to_km, edge) in graded {
+            let edge = edge.filter(|e| e.sac_scale == Some(hardest));
+            match (edge, &mut section) {
+                (Some(edge), None) => {
+                    section = Some(HardestSection {
+                        sac_scale: sac_scale::label(hardest),
+                        trail_visibility: edge.trail_visibility.map(|v| v.as_str().to_string()),
+                        from_km,
+                        to_km,
+                    })
+                }
+                (Some(edge), Some(section)) => {
+                    section.to_km = to_km;
+                    let worst = section.trail_visibility.as_deref().and_then(TrailVisibility::from_tag);
+                    if edge.trail_visibility > worst {
+                        section.trail_visibility = edge.trail_visibility.map(|v| v.as_str().to_string());
+                    }
+                }
+                (None, Some(_)) => break,
+                (None, None) => {}
+            }
+        }
+        section
+    }
+
+    /// Stretches of a path off the graph, when the request allowed off-trail
+    /// segments and some were used.
+    pub fn off_trail_sections(&self, edges: &[PathEdge], options: &RouteOptions) -> Option<Vec<OffTrailSection>> {
+        options.off_trail_beyond_m?;
+        let mut sections: Vec<OffTrailSection> = Vec::new();
+        for &(from_km, to_km, edge) in edges {
+            if edge.is_some() {
+                continue;
+            }
+            match sections.last_mut() {
+                Some(section) if section.to_km == from_km => section.to_km = to_km,
+                _ => sections.push(OffTrailSection { from_km, to_km }),
+            }
+        }
+        (!sections.is_empty()).then_some(sections)
+    }
+
+    /// Mean of `value` over the edges followed by a path, weighted by
+    /// length; segments off the graph count as 0.
+    fn length_weighted_mean(&self, edges: &[PathEdge], value: impl Fn(&EdgeData) -> f64) -> Option<f64> {
+        let (weighted, total_km) = edges.iter().fold(
+            (0.0, 0.0),
+            |(weighted, total), &(from_km, to_km, edge)| {
+                let v = edge.map_or(0.0, |e| value(&self.graph[e]));
+                (weighted + v * (to_km - from_km), total + (to_km - from_km))
+            },
+        );
+        (total_km > 0.0).then(|| weighted / total_km)
+    }
+
+    /// Distance per surface along a path, longest first. Segments off the
+    /// graph count as `unknown`.
+    pub fn surface_breakdown(&self, edges: &[PathEdge]) -> Option<Vec<(String, f64)>> {
+        self.breakdown(edges, |edge| edge.surface.as_str())
+    }
+
+    /// Distance per `highway=*` class along a path, longest first. Untagged
+    /// ways and segments off the graph count as `unknown`.
+    pub fn highway_breakdown(&self, edges: &[PathEdge]) -> Option<Vec<(String, f64)>> {
+        self.breakdown(edges, |edge| edge.highway.map_or("unknown", Highway::as_str))
+    }
+
+    /// Length of the segments of a path summed per `key` of their edge, so
+    /// that the totals add up to the length of the path, road prefixes and
+    /// segments off the graph included.
+    fn breakdown(&self, edges: &[PathEdge], key: impl Fn(&EdgeData) -> &'static str) -> Option<Vec<(String, f64)>> {
+        let mut totals: HashMap<&str, f64> = HashMap::new();
+        for &(from_km, to_km, edge) in edges {
+            let key = edge.map_or("unknown", |edge| key(&self.graph[edge]));
+            *totals.entry(key).or_default() += to_km - from_km;
+        }
+        let mut breakdown: Vec<(String, f64)> = totals.into_iter().map(|(key, km)| (key.to_string(), km)).collect();
+        breakdown.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+        (!breakdown.is_empty()).then_some(breakdown)
+    }
+
+    /// Append the nodes of a route leg to those of the previous legs,
+    /// without repeating the node they share.
+    pub fn append_leg_nodes(nodes: &mut Vec<NodeIndex>, leg: &[NodeIndex]) {
+        let shared = usize::from(!nodes.is_empty() && nodes.last() == leg.first());
+        nodes.extend_from_slice(&leg[shared..]);
+    }
+
+    /// A path, given by its [`Self::path_edges`], split into legs of
+    /// consecutive edges with the same surface, way, waymarked route and
+    /// grade class. `elevations`, one per point of the path as in
+    /// [`crate::models::ElevationProfile`], gives their ascent.
+    pub fn route_legs(&self, edges: &[PathEdge], elevations: Option<&[Option<f64>]>) -> Option<Vec<RouteLeg>> {
+        if edges.is_empty() {
+            return None;
+        }
+        let mut legs: Vec<RouteLeg> = Vec::new();
+        for (i, &(start_km, end_km, edge)) in edges.iter().enumerate() {
+            let climb = elevations
+                .and_then(|elevations| Some((elevations.get(i).copied()??, elevations.get(i + 1).copied()??)))
+                .map_or(0.0, |(from, to)| (to - from).max(0.0));
+            let leg = match edge.map(|edge| &self.graph[edge]) {
+                Some(edge) => RouteLeg {
+                    from_index: i,
+                    to_index: i + 1,
+                    distance_km: end_km - start_km,
+                    ascent_m: climb,
+                    surface: Some(edge.surface),
+                    highway: edge.highway.map(|h| h.as_str().to_string()),
+                    name: self.way_name(edge),
+                    waymarked: edge
+                        .routes
+                        .iter()
+                        .filter_map(|&route| self.routes.get(route as usize))
+                        .max_by(|a, b| a.network_score().partial_cmp(&b.network_score()).unwrap_or(Ordering::Equal))
+                        .and_then(|route| route.reference.clone().or_else(|| route.name.clone())),
+                    grade_class: edge.grade_percent.map(grade_class),
+                },
+                None => RouteLeg {
+                    from_index: i,
+                    to_index: i + 1,
+                    distance_km: end_km - start_km,
+                    ascent_m: climb,
+                    surface: None,
+                    highway: None,
+                    name: None,
+                    waymarked: None,
+                    grade_class: None,
+                },
+            };
+            match legs.last_mut() {
+                Some(last)
+                    if last.surface == leg.surface
+                        && last.highway == leg.highway
+                        && last.name == leg.name
+                        && last.waymarked == leg.waymarked
+                        && last.grade_class == leg.grade_class =>
+                {
+                    last.to_index = leg.to_index;
+                    last.distance_km += leg.distance_km;
+                    last.ascent_m += leg.ascent_m;
+                }
+                _ => legs.push(leg),
+            }
+        }
+        Some(legs)
+    }
+
+    /// Each segment of `path` as `(from_km, to_km)` along the path and the
+    /// graph edge whose geometry contains it, if any. The route reports take
+    /// these rather than the path, so that a response matches them once.
+    pub fn path_edges(&self, path: &[Coordinate]) -> Vec<PathEdge> {
+        let Some(&first) = path.first() else {
+            return Vec::new();
+        };
+        let mut matched = Vec::with_capacity(path.len() - 1);
+        let mut along_km = 0.0;
+        let mut candidates = (self.edges_at(first), Vec::new());
+
+        for segment in path.windows(2) {
+            candidates.1 = self.edges_at(segment[1]);
+            let start_km = along_km;
+            along_km += haversine_km(segment[0], segment[1]);
+
+            let midpoint = segment[0].interpolate(segment[1], 0.5);
+            let on_edge = |e| self.project_to_edge(midpoint, e) < 1e-7;
+            // Segments between two points inside an edge, as map matching
+            // yields, have no vertex to look the edge up from
+            let edge = candidates.0.iter().chain(&candidates.1).copied().find(|&e| on_edge(e)).or_else(|| {
+                self.trace_candidates(midpoint, 1.0, 4)
+                    .into_iter()
+                    .map(|candidate| candidate.edge)
+                    .find(|&e| on_edge(e))
+            });
+            matched.push((start_km, along_km, edge));
+            candidates.0 = std::mem::take(&mut candidates.1);
+        }
+        matched
+    }
+
+    /// Edges whose geometry has a vertex exactly at `coord`: the edge of an
+    /// intermediate waypoint, or every edge of a graph node.
+    fn edges_at(&self, coord: Coordinate) -> Vec<petgraph::graph::EdgeIndex> {
+        let mut edges = Vec::new();
+        let nearest = self
+            .road_point_index
+            .nearest(&[coord.lon, coord.lat], 4, &squared_euclidean)
+            .unwrap_or_default();
+        for (dist_sq, &point_id) in nearest {
+            if dist_sq > 1e-14 {
+                break;
+            }
+            let rp = &self.road_points[point_id];
+            match rp.edge_idx {
+                Some(edge) => edges.push(edge),
+                None => edges.extend(self.graph.edges(NodeIndex::new(rp.node_idx)).map(|e| e.id())),
+            }
+        }
+        edges
     }
 
     /// Build road-point spatial index with edge metadata for projection-based snapping.
@@ -299,12 +863,20 @@ impl RouteEngine {
     ///
     /// ## Edge Weight Calculation
     /// ```text
-    /// weight = base_cost * (1.0 + population_penalty + surface_penalty)
+    /// weight = base_cost * (1.0 + population_penalty + surface_penalty
+    ///                        + waymarked_penalty + noise_penalty + scenery_penalty
+    ///                        + shade_penalty + unlit_penalty)
     ///
     /// where:
     ///   base_cost = edge_length_km
     ///   population_penalty = population_density * w_pop
     ///   surface_penalty = if paved { 0.0 } else { w_paved }
+    ///   waymarked_penalty = w_waymarked * (1 - network_score)   (w_waymarked >= 0)
+    ///                     = -w_waymarked * network_score        (w_waymarked < 0)
+    ///   noise_penalty = w_quiet * noise   (0 to 1, from nearby major roads and railways)
+    ///   scenery_penalty = w_scenery * (1 - scenery)   (0 to 1, forest, water, viewpoints)
+    ///   shade_penalty = w_shade * (1 - forest_fraction)
+    ///   unlit_penalty = if lit { 0.0 } else { w_lit }
     /// ```
     ///
     /// ## Optimizations
@@ -323,7 +895,7 @@ impl RouteEngine {
         req: &RouteRequest,
         excluded_edges: &HashSet<(NodeIndex, NodeIndex)>,
     ) -> Option<Vec<Coordinate>> {
-        let (coords, _indices) = self.find_path_core(req, excluded_edges)?;
+        let (coords, _indices) = self.find_path_core(req, excluded_edges, None)?;
         Some(coords)
     }
 
@@ -331,7 +903,7 @@ impl RouteEngine {
         &self,
         req: &RouteRequest,
     ) -> Option<(Vec<Coordinate>, Vec<NodeIndex>)> {
-        self.find_path_core(req, &HashSet::new())
+        self.find_path_core(req, &HashSet::new(), None)
     }
 
     pub fn find_path_with_excluded_edges_returning_indices(
@@ -339,7 +911,159 @@ impl RouteEngine {
         req: &RouteRequest,
         excluded_edges: &HashSet<(NodeIndex, NodeIndex)>,
     ) -> Option<(Vec<Coordinate>, Vec<NodeIndex>)> {
-        self.find_path_core(req, excluded_edges)
+        self.find_path_core(req, excluded_edges, None)
+    }
+
+    /// Turn-by-turn instructions along a route from
+    /// [`Self::find_path_returning_indices`]: its `path`, the graph nodes it
+    /// goes through and its [`Self::path_edges`]. A maneuver is emitted where
+    /// the way changes and at junctions where the route turns.
+    pub fn maneuvers(
+        &self,
+        path: &[Coordinate],
+        nodes: &[NodeIndex],
+        edges: &[PathEdge],
+        language: Language,
+    ) -> Option<Vec<Maneuver>> {
+        if path.len() < 2 || nodes.is_empty() {
+            return None;
+        }
+        let mut along_km = vec![0.0];
+        for pair in path.windows(2) {
+            along_km.push(along_km[along_km.len() - 1] + haversine_km(pair[0], pair[1]));
+        }
+
+        // Index of each node in `path`, which holds them in order
+        let mut positions = Vec::with_capacity(nodes.len());
+        let mut from = 0;
+        for &node in nodes {
+            let coord = self.nodes[node.index()].coord;
+            from += path[from..]
+                .iter()
+                .position(|c| (c.lat - coord.lat).abs() < 1e-7 && (c.lon - coord.lon).abs() < 1e-7)?;
+            positions.push(from);
+        }
+        // The edge whose geometry the path follows out of each node, rather
+        // than any of its parallel edges. Nodes of consecutive legs are not
+        // joined by an edge: the way followed up to the seam goes on across it
+        let mut ways: Vec<Way> = Vec::with_capacity(nodes.len());
+        for (pair, &index) in nodes.windows(2).zip(&positions) {
+            let taken = edges
+                .get(index)
+                .and_then(|&(_, _, edge)| edge)
+                .filter(|&edge| self.graph.edge_endpoints(edge).is_some_and(|(a, b)| [a, b] == *pair || [b, a] == *pair));
+            let way = match taken {
+                Some(edge) => Way { name: self.way_name(&self.graph[edge]), highway: self.graph[edge].highway },
+                None => ways.last().cloned().unwrap_or_default(),
+            };
+            ways.push(way);
+        }
+
+        // (type, bearing change, index in path, way taken)
+        let mut steps = vec![(ManeuverType::Depart, 0.0, 0, ways.first())];
+        for (k, &index) in positions.iter().enumerate().take(nodes.len().saturating_sub(1)).skip(1) {
+            if index == 0 || index + 1 >= path.len() {
+                continue;
+            }
+            let change = instructions::bearing_change(
+                initial_bearing_deg(path[index - 1], path[index]),
+                initial_bearing_deg(path[index], path[index + 1]),
+            );
+            let kind = instructions::classify(change);
+            let junction = self.graph.edges(nodes[k]).count() > 2;
+            if ways[k - 1].differs_from(&ways[k]) || (junction && kind != ManeuverType::Continue) {
+                steps.push((kind, change, index, Some(&ways[k])));
+            }
+        }
+        steps.push((ManeuverType::Arrive, 0.0, path.len() - 1, None));
+
+        let maneuvers = steps
+            .iter()
+            .enumerate()
+            .map(|(i, &(kind, change, index, way))| {
+                let previous = if i == 0 { index } else { steps[i - 1].2 };
+                let next = steps.get(i + 1).map_or(index, |step| step.2);
+                Maneuver {
+                    kind,
+                    bearing_change_deg: change,
+                    distance_to_next_km: along_km[next] - along_km[index],
+                    name: way.and_then(|way| way.label(language)),
+                    path_index: index,
+                    instruction: instructions::phrase(kind, way, along_km[index] - along_km[previous], language),
+                }
+            })
+            .collect();
+        Some(maneuvers)
+    }
+
+    /// Cost of each edge of the route through `route`, the nodes the handler
+    /// returned, and with `debug_search_space` the nodes A* settled on the
+    /// way. The search space is left out when the search alone does not give
+    /// that route, as after a water detour.
+    pub fn explain_route(&self, req: &RouteRequest, route: &[NodeIndex]) -> Option<RouteDebug> {
+        let &start = route.first()?;
+        let edges: Vec<EdgeCost> = route
+            .windows(2)
+            .filter_map(|pair| {
+                // The cheapest of parallel edges, as the search picked
+                let edge = self.graph.edges_connecting(pair[0], pair[1]).min_by(|a, b| {
+                    let cost = |e| self.search_edge_cost(e, req, &HashSet::new(), start);
+                    cost(*a).partial_cmp(&cost(*b)).unwrap_or(Ordering::Equal)
+                })?;
+                Some(self.edge_cost_breakdown(edge, req, start))
+            })
+            .collect();
+
+        let settled_nodes = req.options.debug_search_space.then(|| {
+            let mut settled = Vec::new();
+            let searched = self.find_path_core(req, &HashSet::new(), Some(&mut settled));
+            searched.is_some_and(|(_, nodes)| nodes == route).then_some(settled)
+        });
+        let settled_nodes = settled_nodes.flatten().map(|settled| {
+            let mut seen = HashSet::new();
+            GeoJsonMultiPoint {
+                kind: "MultiPoint".to_string(),
+                coordinates: settled
+                    .into_iter()
+                    .filter(|node| seen.insert(*node))
+                    .map(|node| {
+                        let coord = self.nodes[node.index()].coord;
+                        [coord.lon, coord.lat]
+                    })
+                    .collect(),
+            }
+        });
+        Some(RouteDebug { total_cost: edges.iter().map(|edge| edge.total).sum(), edges, settled_nodes })
+    }
+
+    /// Terms of the search cost of `edge`, in km.
+    fn edge_cost_breakdown(
+        &self,
+        edge: petgraph::graph::EdgeReference<EdgeData>,
+        req: &RouteRequest,
+        start: NodeIndex,
+    ) -> EdgeCost {
+        let data = edge.weight();
+        let weights = WeightConfig::from_request(req);
+        let factors = self.cost_factors(data, weights);
+        let hiking = self.edge_cost(data, weights);
+        let profiled = self.profile_edge_cost(data, req, weights);
+        let total = self.search_edge_cost(edge, req, &HashSet::new(), start);
+        EdgeCost {
+            from: self.nodes[edge.source().index()].coord,
+            to: self.nodes[edge.target().index()].coord,
+            length_km: data.length_km,
+            population: data.length_km * factors.population,
+            paved: data.length_km * factors.paved,
+            waymarked: data.length_km * factors.waymarked,
+            quiet: data.length_km * factors.quiet,
+            scenery: data.length_km * factors.scenery,
+            shade: data.length_km * factors.shade,
+            lit: data.length_km * factors.lit,
+            profile: profiled - hiking,
+            exclusion: total - profiled,
+            total,
+        }
     }
 
     /// Core A* pathfinding with road-snap prefixes.
@@ -352,9 +1076,9 @@ impl RouteEngine {
         &self,
         req: &RouteRequest,
         excluded_edges: &HashSet<(NodeIndex, NodeIndex)>,
+        settled: Option<&mut Vec<NodeIndex>>,
     ) -> Option<(Vec<Coordinate>, Vec<NodeIndex>)> {
-        let start_snap = self.snap_to_road(req.start)?;
-        let end_snap = self.snap_to_road(req.end)?;
+        let (start_snap, end_snap) = self.snap_endpoints(req)?;
 
         let start = start_snap.node;
         let end = end_snap.node;
@@ -402,7 +1126,7 @@ impl RouteEngine {
         };
 
         // Run A* between snap nodes (may be same node → single point)
-        let (astar_coords, route) = self.run_astar(start, end, req, excluded_edges)?;
+        let (astar_coords, route) = self.run_astar(start, end, req, excluded_edges, settled)?;
 
         // Build full path: start_prefix + A* path + reversed end_prefix
         let mut full_coords = Vec::new();
@@ -412,7 +1136,7 @@ impl RouteEngine {
 
         // A* path (may overlap with last point of prefix — dedup)
         for &coord in &astar_coords {
-            if full_coords.last().map_or(true, |last: &Coordinate| {
+            if full_coords.last().is_none_or(|last: &Coordinate| {
                 (last.lat - coord.lat).abs() > 1e-7 || (last.lon - coord.lon).abs() > 1e-7
             }) {
                 full_coords.push(coord);
@@ -423,7 +1147,7 @@ impl RouteEngine {
         let mut end_suffix: Vec<Coordinate> = end_snap.road_prefix;
         end_suffix.reverse();
         for &coord in &end_suffix {
-            if full_coords.last().map_or(true, |last: &Coordinate| {
+            if full_coords.last().is_none_or(|last: &Coordinate| {
                 (last.lat - coord.lat).abs() > 1e-7 || (last.lon - coord.lon).abs() > 1e-7
             }) {
                 full_coords.push(coord);
@@ -445,16 +1169,12 @@ impl RouteEngine {
         end: NodeIndex,
         req: &RouteRequest,
         excluded_edges: &HashSet<(NodeIndex, NodeIndex)>,
+        mut settled: Option<&mut Vec<NodeIndex>>,
     ) -> Option<(Vec<Coordinate>, Vec<NodeIndex>)> {
         if start == end {
             return Some((vec![self.nodes[start.index()].coord], vec![start]));
         }
 
-        let weights = WeightConfig {
-            population: req.w_pop,
-            paved: req.w_paved,
-        };
-
         let heuristic = |idx: NodeIndex| {
             if idx == end {
                 0.0
@@ -463,33 +1183,68 @@ impl RouteEngine {
             }
         };
 
-        let edge_cost = |edge: petgraph::graph::EdgeReference<EdgeData>| {
-            let base_cost = self.edge_cost(edge.weight(), weights);
-            let from = edge.source();
-            let to = edge.target();
-
-            let is_excluded = excluded_edges.contains(&(from, to)) || excluded_edges.contains(&(to, from));
-            let is_final_return = to == start || from == start;
-
-            if is_excluded && !is_final_return {
-                base_cost * 10.0
-            } else {
-                base_cost
+        let edge_cost = |edge| self.search_edge_cost(edge, req, excluded_edges, start);
+        let is_goal = |node| {
+            if let Some(settled) = settled.as_deref_mut() {
+                settled.push(node);
             }
+            node == end
         };
 
-        let (_cost, route) = astar(
-            &self.graph,
-            start,
-            |finish| finish == end,
-            edge_cost,
-            heuristic,
-        )?;
+        let (cost, route) = astar(&self.graph, start, is_goal, edge_cost, heuristic)?;
+        // Only reachable through edges the profile forbids
+        if !cost.is_finite() {
+            return None;
+        }
 
         let coords = expand_path_with_waypoints(&route, &self.graph, &self.nodes, &self.edge_map);
         Some((coords, route))
     }
 
+    /// Cost of an edge for the search from `start`: the profile cost, ten
+    /// times higher on `excluded_edges` except for edges back to `start`.
+    fn search_edge_cost(
+        &self,
+        edge: petgraph::graph::EdgeReference<EdgeData>,
+        req: &RouteRequest,
+        excluded_edges: &HashSet<(NodeIndex, NodeIndex)>,
+        start: NodeIndex,
+    ) -> f64 {
+        let base_cost = self.profile_edge_cost(edge.weight(), req, WeightConfig::from_request(req));
+        let from = edge.source();
+        let to = edge.target();
+
+        let is_excluded = excluded_edges.contains(&(from, to)) || excluded_edges.contains(&(to, from));
+        let is_final_return = to == start || from == start;
+
+        if is_excluded && !is_final_return {
+            base_cost * 10.0
+        } else {
+            base_cost
+        }
+    }
+
+    /// Cost of an edge under the request's profile, infinite on ways
+    /// harder than `max_sac_scale`.
+    fn profile_edge_cost(&self, edge: &EdgeData, req: &RouteRequest, weights: WeightConfig) -> f64 {
+        let too_hard = edge.sac_scale.is_some_and(|scale| req.options.max_sac_scale.is_some_and(|max| scale > max));
+        if too_hard {
+            return f64::INFINITY;
+        }
+        match req.options.profile {
+            RoutingProfile::Hiking => self.edge_cost(edge, weights),
+            RoutingProfile::Wheelchair => {
+                let max_grade_percent =
+                    req.options.max_grade_percent.unwrap_or(accessibility::DEFAULT_MAX_GRADE_PERCENT);
+                self.wheelchair_edge_cost(edge, weights, max_grade_percent)
+            }
+            RoutingProfile::Cycling => {
+                let max_mtb_scale = req.options.max_mtb_scale.unwrap_or(cycling::DEFAULT_MAX_MTB_SCALE);
+                self.cycling_edge_cost(edge, weights, max_mtb_scale)
+            }
+        }
+    }
+
     /// Find closest graph node using road-point spatial index.
     /// Snaps to the nearest point on any road (including mid-segment waypoints),
     /// then returns that road's closest intersection node.
@@ -522,10 +1277,10 @@ impl RouteEngine {
 
             if let Some(edge_idx) = rp.edge_idx {
                 // Only project onto each edge once
-                if !edge_projections.contains_key(&edge_idx) {
+                if let Entry::Vacant(slot) = edge_projections.entry(edge_idx) {
                     let proj_dist = self.project_to_edge(target, edge_idx);
                     if proj_dist * 111.0 < MAX_DISTANCE_KM {
-                        edge_projections.insert(edge_idx, proj_dist);
+                        slot.insert(proj_dist);
                     }
                 }
             } else {
@@ -619,6 +1374,170 @@ impl RouteEngine {
         min_dist
     }
 
+    /// [`Self::snap_to_road`], or the edge picked for this waypoint in
+    /// `snap_choices`, or an off-trail path across the DEM when the
+    /// request allows it and the nearest way is further than
+    /// `off_trail_beyond_m`.
+    fn snap_waypoint(&self, target: Coordinate, options: &RouteOptions) -> Option<RoadSnap> {
+        if let Some(edge) = self.chosen_edge(target, options) {
+            return Some(self.snap_along_edge(target, edge));
+        }
+        let snap = self.snap_to_road(target);
+        let Some(beyond_m) = options.off_trail_beyond_m else {
+            return snap;
+        };
+        let road_m = snap.as_ref().map(|snap| {
+            let on_road = snap.road_prefix.first().copied().unwrap_or(self.nodes[snap.node.index()].coord);
+            haversine_km(target, on_road) * 1000.0
+        });
+        if road_m.is_some_and(|road_m| road_m <= beyond_m) {
+            return snap;
+        }
+        let Some(dem) = crate::elevation::local_dem_grid() else {
+            return snap;
+        };
+        self.off_trail_snap(target, |c| dem.sample(c.lat, c.lon)).or(snap)
+    }
+
+    /// Edge picked for this waypoint in `snap_choices`, if found.
+    fn chosen_edge(&self, target: Coordinate, options: &RouteOptions) -> Option<petgraph::graph::EdgeIndex> {
+        options
+            .snap_choices
+            .iter()
+            .find(|choice| haversine_km(choice.point, target) * 1000.0 < 1.0)
+            .and_then(|choice| self.resolve_snap_choice(choice))
+    }
+
+    /// The edge of a snap choice: the nearest one within a metre of its
+    /// projected point, of the same `highway` class and name when given.
+    fn resolve_snap_choice(&self, choice: &SnapChoice) -> Option<petgraph::graph::EdgeIndex> {
+        self.trace_candidates(choice.projected, 1.0, 8)
+            .into_iter()
+            .map(|candidate| candidate.edge)
+            .find(|&edge| {
+                let data = &self.graph[edge];
+                choice.highway.as_deref().is_none_or(|highway| data.highway.map(Highway::as_str) == Some(highway))
+                    && choice.name.as_deref().is_none_or(|name| self.way_name(data).as_deref() == Some(name))
+            })
+    }
+
+    /// Snap both ends of a leg. When they land in different connected
+    /// components, ends off the largest component move to a nearby way of
+    /// it, or else the end on the smaller fragment joins the other end's
+    /// component. Waypoints with an explicit snap choice are left alone.
+    fn snap_endpoints(&self, req: &RouteRequest) -> Option<(RoadSnap, RoadSnap)> {
+        let start = self.snap_waypoint(req.start, &req.options)?;
+        let end = self.snap_waypoint(req.end, &req.options)?;
+        let (start_component, end_component) = (self.component_of(&start), self.component_of(&end));
+        if start_component == end_component {
+            return Some((start, end));
+        }
+
+        let resnap = |snap: &RoadSnap, target: Coordinate, component: u32| {
+            if self.component_of(snap) == component {
+                Some(snap.clone())
+            } else if self.chosen_edge(target, &req.options).is_some() {
+                None
+            } else {
+                self.snap_in_component(target, component)
+            }
+        };
+        for component in [0, start_component.min(end_component)] {
+            if let (Some(s), Some(e)) = (resnap(&start, req.start, component), resnap(&end, req.end, component)) {
+                tracing::debug!("island snap: moved both ends to component {}", component);
+                return Some((s, e));
+            }
+        }
+        Some((start, end))
+    }
+
+    fn component_of(&self, snap: &RoadSnap) -> u32 {
+        self.components[snap.node.index()]
+    }
+
+    /// Snap onto the nearest edge of `component` within
+    /// [`ISLAND_SNAP_RADIUS_M`].
+    fn snap_in_component(&self, target: Coordinate, component: u32) -> Option<RoadSnap> {
+        const CANDIDATE_EDGES: usize = 20;
+        self.trace_candidates(target, ISLAND_SNAP_RADIUS_M, CANDIDATE_EDGES)
+            .into_iter()
+            .find(|candidate| {
+                let (source, _) = self.graph.edge_endpoints(candidate.edge).unwrap();
+                self.components[source.index()] == component
+            })
+            .map(|candidate| self.snap_along_edge(target, candidate.edge))
+    }
+
+    /// Why no route joins the ends of `req` when they snap onto different
+    /// connected components, e.g. a private driveway cut off by the
+    /// bounding box of a partial graph.
+    pub fn disconnected_island_message(&self, req: &RouteRequest) -> Option<String> {
+        let (start, end) = self.snap_endpoints(req)?;
+        let (start_component, end_component) = (self.component_of(&start), self.component_of(&end));
+        let (side, island) = match start_component.cmp(&end_component) {
+            std::cmp::Ordering::Equal => return None,
+            std::cmp::Ordering::Greater => ("start", start_component),
+            std::cmp::Ordering::Less => ("end", end_component),
+        };
+        let nodes = self.components.iter().filter(|&&c| c == island).count();
+        Some(format!(
+            "No route found: {side} is on a disconnected island ({nodes} nodes cut off from the rest of the network)"
+        ))
+    }
+
+    /// Error message for a request without a path when the request itself, not
+    /// the graph, is the cause: the route exists above `max_sac_scale`, or on
+    /// foot for a wheelchair or cycling request.
+    pub fn no_accessible_route_message(&self, req: &RouteRequest) -> Option<String> {
+        if let Some(max_sac_scale) = req.options.max_sac_scale {
+            let mut unlimited = req.clone();
+            unlimited.options.max_sac_scale = None;
+            if self.find_path(&unlimited).is_some() {
+                return Some(format!(
+                    "No route up to {}: every path between these points uses harder trails",
+                    sac_scale::label(max_sac_scale)
+                ));
+            }
+        }
+        let message = match req.options.profile {
+            RoutingProfile::Hiking => return None,
+            RoutingProfile::Wheelchair => format!(
+                "No accessible route: every path between these points uses steps, raised kerbs, \
+                 rough surfaces or grades above {}%",
+                req.options.max_grade_percent.unwrap_or(accessibility::DEFAULT_MAX_GRADE_PERCENT)
+            ),
+            RoutingProfile::Cycling => {
+                "No cyclable route: every path between these points uses ways closed to bicycles".to_string()
+            }
+        };
+        let mut on_foot = req.clone();
+        on_foot.options.profile = RoutingProfile::Hiking;
+        on_foot.options.max_sac_scale = None;
+        self.find_path(&on_foot)?;
+        Some(message)
+    }
+
+    /// Join `target` to one of its closest graph nodes by the quickest walk
+    /// over the terrain. The prefix runs from `target` to the node, like a
+    /// road prefix.
+    fn off_trail_snap(&self, target: Coordinate, elevation: impl Fn(Coordinate) -> Option<f64>) -> Option<RoadSnap> {
+        const CANDIDATE_NODES: usize = 8;
+        let candidates: Vec<NodeIndex> = self
+            .closest_nodes(target, CANDIDATE_NODES)
+            .into_iter()
+            .filter(|&n| haversine_km(target, self.nodes[n.index()].coord) <= off_trail::OFF_TRAIL_MAX_KM)
+            .collect();
+        let coords: Vec<Coordinate> = candidates.iter().map(|&n| self.nodes[n.index()].coord).collect();
+        let (reached, path) = off_trail::least_cost_path(elevation, target, &coords)?;
+        tracing::debug!(
+            "off-trail: target=({:.7},{:.7}) → node {} across {} cells",
+            target.lat, target.lon,
+            candidates[reached].index(),
+            path.len()
+        );
+        Some(RoadSnap { node: candidates[reached], road_prefix: path, edge: None })
+    }
+
     /// Snap a coordinate to the nearest road, returning the graph node AND
     /// the road polyline from the projected point to that node.
     ///
@@ -641,10 +1560,10 @@ impl RouteEngine {
         for (dist_sq, &point_id) in &nearest {
             let rp = &self.road_points[point_id];
             if let Some(edge_idx) = rp.edge_idx {
-                if !edge_projections.contains_key(&edge_idx) {
+                if let Entry::Vacant(slot) = edge_projections.entry(edge_idx) {
                     let proj_dist = self.project_to_edge(target, edge_idx);
                     if proj_dist * 111.0 < MAX_DISTANCE_KM {
-                        edge_projections.insert(edge_idx, proj_dist);
+                        slot.insert(proj_dist);
                     }
                 }
             } else {
@@ -661,15 +1580,21 @@ impl RouteEngine {
 
         // If best pure node is closer than best edge, use node directly
         if let Some((node_idx, node_dist)) = best_pure_node {
-            if best_edge.map_or(true, |(_, &edge_dist)| node_dist < edge_dist) {
+            if best_edge.is_none_or(|(_, &edge_dist)| node_dist < edge_dist) {
                 return Some(RoadSnap {
                     node: NodeIndex::new(node_idx),
                     road_prefix: vec![],
+                    edge: None,
                 });
             }
         }
 
         let (&best_edge_idx, _) = best_edge?;
+        Some(self.snap_along_edge(target, best_edge_idx))
+    }
+
+    /// Project `target` onto an edge and follow it to its closest end.
+    fn snap_along_edge(&self, target: Coordinate, best_edge_idx: petgraph::graph::EdgeIndex) -> RoadSnap {
         let (from, to) = self.graph.edge_endpoints(best_edge_idx).unwrap();
         let edge_data = &self.graph[best_edge_idx];
 
@@ -718,9 +1643,7 @@ impl RouteEngine {
         } else {
             // Snap to 'to' — prefix goes forward along polyline: proj → seg_idx+1 → ... → end
             let mut prefix = vec![proj_point];
-            for i in (min_seg_idx + 1)..polyline.len() {
-                prefix.push(polyline[i]);
-            }
+            prefix.extend_from_slice(&polyline[(min_seg_idx + 1)..]);
             (to, prefix)
         };
 
@@ -734,7 +1657,211 @@ impl RouteEngine {
             road_prefix.len()
         );
 
-        Some(RoadSnap { node: snap_node, road_prefix })
+        RoadSnap { node: snap_node, road_prefix, edge: Some(best_edge_idx) }
+    }
+
+    /// Up to `count` edges (at most 20) around a clicked point, nearest
+    /// first, with the one routing snaps onto by default flagged.
+    pub fn nearest(&self, point: Coordinate, count: usize) -> Vec<SnapCandidate> {
+        const MAX_COUNT: usize = 20;
+        let count = count.min(MAX_COUNT);
+        let snap = self.snap_to_road(point);
+        let selected = |edge: petgraph::graph::EdgeIndex| match &snap {
+            Some(RoadSnap { edge: Some(snapped), .. }) => *snapped == edge,
+            Some(RoadSnap { node, edge: None, .. }) => {
+                self.graph.edge_endpoints(edge).is_some_and(|(a, b)| a == *node || b == *node)
+            }
+            None => false,
+        };
+        self.trace_candidates(point, NEAREST_RADIUS_M, count)
+            .into_iter()
+            .map(|candidate| {
+                let edge = &self.graph[candidate.edge];
+                let (source, _) = self.graph.edge_endpoints(candidate.edge).unwrap();
+                SnapCandidate {
+                    projected: candidate.coord,
+                    distance_from_click_m: candidate.distance_m,
+                    surface: edge.surface,
+                    highway: edge.highway.map(|h| h.as_str().to_string()),
+                    name: self.way_name(edge),
+                    component: self.components[source.index()],
+                    selected: selected(candidate.edge),
+                }
+            })
+            .collect()
+    }
+
+    /// Edges within `radius_m` of a recorded point, nearest first, at most
+    /// `max` of them.
+    pub fn trace_candidates(&self, point: Coordinate, radius_m: f64, max: usize) -> Vec<TraceCandidate> {
+        let nearest = self
+            .road_point_index
+            .nearest(&[point.lon, point.lat], (max * 6).max(30), &squared_euclidean)
+            .unwrap_or_default();
+        let mut edges = HashSet::new();
+        for (_, &point_id) in nearest {
+            let rp = &self.road_points[point_id];
+            match rp.edge_idx {
+                Some(edge) => {
+                    edges.insert(edge);
+                }
+                None => edges.extend(self.graph.edges(NodeIndex::new(rp.node_idx)).map(|e| e.id())),
+            }
+        }
+
+        let mut candidates: Vec<TraceCandidate> = edges
+            .into_iter()
+            .filter_map(|edge| {
+                let candidate = self.project_onto_edge(point, edge);
+                (candidate.distance_m <= radius_m).then_some(candidate)
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.distance_m.partial_cmp(&b.distance_m).unwrap_or(Ordering::Equal));
+        candidates.truncate(max);
+        candidates
+    }
+
+    /// Network distance in km from `from` to each of `to`, `None` beyond
+    /// `max_km`.
+    pub fn candidate_distances(&self, from: &TraceCandidate, to: &[TraceCandidate], max_km: f64) -> Vec<Option<f64>> {
+        let reached = self.dijkstra_from_candidate(from, max_km);
+        to.iter()
+            .map(|target| {
+                self.candidate_route(from, target, &reached)
+                    .map(|(km, _)| km)
+                    .filter(|&km| km <= max_km)
+            })
+            .collect()
+    }
+
+    /// Geometry of the shortest network path from `from` to `to`, if within
+    /// `max_km`.
+    pub fn candidate_path(&self, from: &TraceCandidate, to: &TraceCandidate, max_km: f64) -> Option<Vec<Coordinate>> {
+        let reached = self.dijkstra_from_candidate(from, max_km);
+        let (km, end_node) = self.candidate_route(from, to, &reached)?;
+        if km > max_km {
+            return None;
+        }
+        let polyline = |edge| {
+            let (a, b) = self.graph.edge_endpoints(edge).unwrap();
+            let mut polyline = vec![self.nodes[a.index()].coord];
+            polyline.extend_from_slice(&self.graph[edge].waypoints);
+            polyline.push(self.nodes[b.index()].coord);
+            (a, polyline)
+        };
+
+        let (source, from_line) = polyline(from.edge);
+        let mut path = vec![from.coord];
+        let Some(end_node) = end_node else {
+            // Both on the same edge
+            if from.segment < to.segment {
+                path.extend_from_slice(&from_line[from.segment + 1..=to.segment]);
+            } else {
+                path.extend(from_line[to.segment + 1..=from.segment].iter().rev());
+            }
+            path.push(to.coord);
+            return Some(path);
+        };
+
+        let mut route = vec![end_node];
+        while let Some(&(_, Some(previous))) = reached.get(route.last().unwrap()) {
+            route.push(previous);
+        }
+        route.reverse();
+        // Leave the first edge through the node the search started from
+        if route[0] == source {
+            path.extend(from_line[..=from.segment].iter().rev());
+        } else {
+            path.extend_from_slice(&from_line[from.segment + 1..]);
+        }
+        path.extend(expand_path_with_waypoints(&route, &self.graph, &self.nodes, &self.edge_map).into_iter().skip(1));
+        let (source, to_line) = polyline(to.edge);
+        if end_node == source {
+            path.extend_from_slice(&to_line[1..=to.segment]);
+        } else {
+            path.extend(to_line[to.segment + 1..to_line.len() - 1].iter().rev());
+        }
+        path.push(to.coord);
+        path.dedup_by(|a, b| (a.lat - b.lat).abs() < 1e-9 && (a.lon - b.lon).abs() < 1e-9);
+        Some(path)
+    }
+
+    /// Network distance from `from` to `to` given a search from `from`,
+    /// with the node where the path enters the edge of `to` (`None` when
+    /// both are on the same edge).
+    fn candidate_route(
+        &self,
+        from: &TraceCandidate,
+        to: &TraceCandidate,
+        reached: &HashMap<NodeIndex, (f64, Option<NodeIndex>)>,
+    ) -> Option<(f64, Option<NodeIndex>)> {
+        let length_km = self.graph[to.edge].length_km;
+        if from.edge == to.edge {
+            return Some(((to.fraction - from.fraction).abs() * length_km, None));
+        }
+        let (source, target) = self.graph.edge_endpoints(to.edge)?;
+        [(source, to.fraction), (target, 1.0 - to.fraction)]
+            .into_iter()
+            .filter_map(|(node, share)| Some((reached.get(&node)?.0 + share * length_km, Some(node))))
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+    }
+
+    /// Distances and predecessors of the nodes within `max_km` of a
+    /// candidate, leaving its edge through either end.
+    fn dijkstra_from_candidate(&self, from: &TraceCandidate, max_km: f64) -> HashMap<NodeIndex, (f64, Option<NodeIndex>)> {
+        let mut reached: HashMap<NodeIndex, (f64, Option<NodeIndex>)> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        if let Some((source, target)) = self.graph.edge_endpoints(from.edge) {
+            let length_km = self.graph[from.edge].length_km;
+            for (node, km) in [(source, from.fraction * length_km), (target, (1.0 - from.fraction) * length_km)] {
+                if reached.get(&node).is_none_or(|&(best, _)| km < best) {
+                    reached.insert(node, (km, None));
+                    queue.push(MinKm(km, node));
+                }
+            }
+        }
+        while let Some(MinKm(km, node)) = queue.pop() {
+            if reached.get(&node).is_some_and(|&(best, _)| km > best) {
+                continue;
+            }
+            for edge in self.graph.edges(node) {
+                let next = if edge.source() == node { edge.target() } else { edge.source() };
+                let next_km = km + edge.weight().length_km;
+                if next_km <= max_km && reached.get(&next).is_none_or(|&(best, _)| next_km < best) {
+                    reached.insert(next, (next_km, Some(node)));
+                    queue.push(MinKm(next_km, next));
+                }
+            }
+        }
+        reached
+    }
+
+    /// Closest point of an edge's geometry to `point`.
+    fn project_onto_edge(&self, point: Coordinate, edge: petgraph::graph::EdgeIndex) -> TraceCandidate {
+        let (from, to) = self.graph.edge_endpoints(edge).unwrap();
+        let mut polyline = vec![self.nodes[from.index()].coord];
+        polyline.extend_from_slice(&self.graph[edge].waypoints);
+        polyline.push(self.nodes[to.index()].coord);
+
+        let (segment, t) = polyline
+            .windows(2)
+            .map(|seg| point_to_segment_distance(point, seg[0], seg[1]))
+            .enumerate()
+            .min_by(|a, b| a.1 .0.partial_cmp(&b.1 .0).unwrap_or(Ordering::Equal))
+            .map(|(i, (_, t))| (i, t))
+            .unwrap_or((0, 0.0));
+        let coord = polyline[segment].interpolate(polyline[segment + 1], t);
+
+        let lengths: Vec<f64> = polyline.windows(2).map(|seg| haversine_km(seg[0], seg[1])).collect();
+        let total: f64 = lengths.iter().sum();
+        let along: f64 = lengths[..segment].iter().sum::<f64>() + t * lengths[segment];
+        TraceCandidate {
+            edge,
+            segment,
+            fraction: if total > 0.0 { along / total } else { 0.0 },
+            coord,
+            distance_m: haversine_km(point, coord) * 1000.0,
+        }
     }
 
     /// Extract all road polylines within a bounding box
@@ -751,7 +1878,7 @@ impl RouteEngine {
                 c.lat >= min_lat && c.lat <= max_lat && c.lon >= min_lon && c.lon <= max_lon
             };
 
-            if in_bbox(&from_coord) || in_bbox(&to_coord) || edge_data.waypoints.iter().any(|w| in_bbox(w)) {
+            if in_bbox(&from_coord) || in_bbox(&to_coord) || edge_data.waypoints.iter().any(in_bbox) {
                 let mut polyline = Vec::with_capacity(2 + edge_data.waypoints.len());
                 polyline.push(from_coord);
                 polyline.extend_from_slice(&edge_data.waypoints);
@@ -763,21 +1890,105 @@ impl RouteEngine {
     }
 
     fn edge_cost(&self, edge: &EdgeData, weights: WeightConfig) -> f64 {
+        let factors = self.cost_factors(edge, weights);
+        edge.length_km
+            * (1.0
+                + factors.population
+                + factors.paved
+                + factors.waymarked
+                + factors.quiet
+                + factors.scenery
+                + factors.shade
+                + factors.lit)
+    }
+
+    fn cost_factors(&self, edge: &EdgeData, weights: WeightConfig) -> CostFactors {
         let paved_penalty = match edge.surface {
             SurfaceType::Paved => 1.0,
             SurfaceType::Trail => 0.2,
             SurfaceType::Dirt => 0.0,
         };
 
-        edge.length_km
-            * (1.0
-                + weights.population * edge.mean_population_density
-                + weights.paved * paved_penalty)
+        // Penalties only, so that the cost never drops below the length and the
+        // straight-line heuristic stays admissible: a preference for marked
+        // trails penalizes unmarked edges, an aversion penalizes marked ones.
+        let waymarked_penalty = if weights.waymarked >= 0.0 {
+            weights.waymarked * (1.0 - edge.waymarked_score)
+        } else {
+            -weights.waymarked * edge.waymarked_score
+        };
+
+        // Untagged ways count as unlit: outside towns they almost always are
+        let unlit_penalty = if edge.lit { 0.0 } else { weights.lit.max(0.0) };
+
+        CostFactors {
+            population: weights.population * edge.mean_population_density,
+            paved: weights.paved * paved_penalty,
+            waymarked: waymarked_penalty,
+            quiet: weights.quiet.max(0.0) * edge.noise,
+            scenery: weights.scenery.max(0.0) * (1.0 - edge.scenery),
+            shade: weights.shade.max(0.0) * (1.0 - edge.shade),
+            lit: unlit_penalty,
+        }
+    }
+
+    /// [`Self::edge_cost`] for the wheelchair profile: infinite on steps,
+    /// raised kerbs, rough surfaces and grades above `max_grade_percent`,
+    /// plus a penalty for roughness and slope otherwise.
+    fn wheelchair_edge_cost(&self, edge: &EdgeData, weights: WeightConfig, max_grade_percent: f64) -> f64 {
+        let Some(penalty) = edge.wheelchair_penalty else {
+            return f64::INFINITY;
+        };
+        let grade = edge.grade_percent.unwrap_or(0.0);
+        if grade > max_grade_percent {
+            return f64::INFINITY;
+        }
+        let slope_penalty = if max_grade_percent > 0.0 { grade / max_grade_percent } else { 0.0 };
+
+        self.edge_cost(edge, weights) + edge.length_km * (penalty + slope_penalty)
+    }
+
+    /// [`Self::edge_cost`] for the cycling profile: infinite where bicycles
+    /// are forbidden, plus the infrastructure, MTB or hike-a-bike penalty.
+    fn cycling_edge_cost(&self, edge: &EdgeData, weights: WeightConfig, max_mtb_scale: u8) -> f64 {
+        match edge.cycling {
+            Some(cycling) => {
+                self.edge_cost(edge, weights) + edge.length_km * cycling.cost_penalty(max_mtb_scale)
+            }
+            None => f64::INFINITY,
+        }
     }
 }
 
 // Removed: squared_distance (replaced by KD-Tree spatial index)
 
+fn grade_class(grade_percent: f64) -> GradeClass {
+    match grade_percent.abs() {
+        g if g < 5.0 => GradeClass::Flat,
+        g if g < 10.0 => GradeClass::Moderate,
+        g if g <= 15.0 => GradeClass::Steep,
+        _ => GradeClass::VerySteep,
+    }
+}
+
+/// Connected component of every node, numbered by decreasing size.
+fn component_labels(graph: &UnGraph<NodeData, EdgeData>) -> Vec<u32> {
+    let mut union_find = UnionFind::new(graph.node_count());
+    for edge in graph.edge_references() {
+        union_find.union(edge.source().index(), edge.target().index());
+    }
+    let roots = union_find.into_labeling();
+
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    for &root in &roots {
+        *sizes.entry(root).or_default() += 1;
+    }
+    let mut by_size: Vec<(usize, usize)> = sizes.into_iter().collect();
+    by_size.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let rank: HashMap<usize, u32> = by_size.iter().enumerate().map(|(i, &(root, _))| (root, i as u32)).collect();
+    roots.iter().map(|root| rank[root]).collect()
+}
+
 fn straight_line_km(a: Coordinate, b: Coordinate) -> f64 {
     fast_distance_km(a, b)
 }
@@ -872,6 +2083,7 @@ mod tests {
     use petgraph::visit::EdgeRef;
 
     use super::*;
+    use crate::graph::test_graph::{self, detour_test_graph, edge, node};
 
     const SAMPLE: &str = include_str!("../data/sample_graph.json");
 
@@ -893,6 +2105,7 @@ mod tests {
             },
             w_pop: 0.0,
             w_paved: 5.0,
+            options: Default::default(),
         };
         let path = engine.find_path(&base_req).expect("path");
         assert!(path.len() > 3, "should take longer scenic path");
@@ -912,6 +2125,7 @@ mod tests {
             },
             w_pop: 0.0,
             w_paved: 0.0,
+            options: Default::default(),
         };
         let path = engine.find_path(&base_req).expect("path");
         // Note: With OSM waypoints + interpolation, paths may have more points
@@ -949,6 +2163,7 @@ mod tests {
             },
             w_pop: 1.0,
             w_paved: 1.0,
+            options: Default::default(),
         };
 
         let path = engine.find_path(&far_req);
@@ -993,6 +2208,7 @@ mod tests {
             },
             w_pop: 1.0,
             w_paved: 1.0,
+            options: Default::default(),
         };
 
         // Should either return a single-point path or None
@@ -1034,34 +2250,35 @@ mod tests {
     fn snap_test_graph() -> GraphFile {
         use crate::graph::{EdgeRecord, NodeRecord};
 
-        GraphFile {
-            nodes: vec![
-                NodeRecord { id: 1, lat: 45.015, lon: 5.005, elevation: None, population_density: 0.1 }, // intersection
-                NodeRecord { id: 2, lat: 45.015, lon: 5.000, elevation: None, population_density: 0.1 }, // paved W
-                NodeRecord { id: 3, lat: 45.030, lon: 5.000, elevation: None, population_density: 0.0 }, // trail end N
-                NodeRecord { id: 4, lat: 45.015, lon: 5.015, elevation: None, population_density: 0.1 }, // paved E
-                NodeRecord { id: 5, lat: 45.010, lon: 5.005, elevation: None, population_density: 0.1 }, // paved S
+        let populated = |id, lat, lon| NodeRecord { population_density: 0.1, ..node(id, lat, lon) };
+        let paved = |from, to, length_m| EdgeRecord { surface: SurfaceType::Paved, ..edge(from, to, length_m) };
+        test_graph::graph(
+            vec![
+                populated(1, 45.015, 5.005), // intersection
+                populated(2, 45.015, 5.000), // paved W
+                node(3, 45.030, 5.000),      // trail end N
+                populated(4, 45.015, 5.015), // paved E
+                populated(5, 45.010, 5.005), // paved S
             ],
-            edges: vec![
+            vec![
                 // Trail N1→N3 with intermediate waypoints (the "Combefort" road)
                 EdgeRecord {
-                    from: 1, to: 3,
                     surface: SurfaceType::Trail,
-                    length_m: 1800.0,
                     waypoints: vec![
                         Coordinate { lat: 45.018, lon: 5.003 },  // wp0
                         Coordinate { lat: 45.020, lon: 5.002 },  // wp1 ← target area
                         Coordinate { lat: 45.025, lon: 5.001 },  // wp2
                     ],
+                    ..edge(1, 3, 1800.0)
                 },
                 // Paved roads at intersection
-                EdgeRecord { from: 1, to: 2, surface: SurfaceType::Paved, length_m: 400.0, waypoints: vec![] },
-                EdgeRecord { from: 1, to: 4, surface: SurfaceType::Paved, length_m: 800.0, waypoints: vec![] },
-                EdgeRecord { from: 1, to: 5, surface: SurfaceType::Paved, length_m: 550.0, waypoints: vec![] },
+                paved(1, 2, 400.0),
+                paved(1, 4, 800.0),
+                paved(1, 5, 550.0),
                 // Connect N2→N5 for routing alternatives
-                EdgeRecord { from: 2, to: 5, surface: SurfaceType::Paved, length_m: 700.0, waypoints: vec![] },
+                paved(2, 5, 700.0),
             ],
-        }
+        )
     }
 
     fn snap_test_engine() -> RouteEngine {
@@ -1240,6 +2457,7 @@ mod tests {
             end: Coordinate { lat: 45.015, lon: 5.005 },   // at intersection N1
             w_pop: 1.0,
             w_paved: 1.0,
+            options: Default::default(),
         };
 
         let path = engine.find_path(&req).expect("should find path");
@@ -1272,6 +2490,7 @@ mod tests {
             end: Coordinate { lat: 45.015, lon: 5.015 },   // N4
             w_pop: 1.0,
             w_paved: 1.0,
+            options: Default::default(),
         };
 
         let path = engine.find_path(&req).expect("should find path");
@@ -1311,6 +2530,7 @@ mod tests {
             end: Coordinate { lat: 45.015, lon: 5.000 },   // N2
             w_pop: 0.0,
             w_paved: 0.0,
+            options: Default::default(),
         };
 
         let path = engine.find_path(&req).expect("should find path");
@@ -1343,6 +2563,7 @@ mod tests {
             end: Coordinate { lat: 45.015, lon: 5.015 },   // N4
             w_pop: 0.0,
             w_paved: 0.0,
+            options: Default::default(),
         };
 
         let path = engine.find_path(&req).expect("should find path");
@@ -1394,4 +2615,681 @@ mod tests {
             connectivity_ratio * 100.0
         );
     }
+
+    /// 1 → 2 straight (800 m, unmarked) or 1 → 3 → 2 along a GR (1000 m).
+    fn waymarked_test_engine() -> RouteEngine {
+        use crate::graph::EdgeRecord;
+
+        let gr = WaymarkedRoute::from_tags(
+            1234,
+            [("route", "hiking"), ("network", "nwn"), ("name", "GR 9"), ("ref", "GR 9")],
+        )
+        .unwrap();
+
+        RouteEngine::from_graph_file(GraphFile {
+            routes: vec![gr],
+            ..test_graph::graph(
+                vec![node(1, 45.0, 5.0), node(2, 45.0, 5.01), node(3, 45.003, 5.005)],
+                vec![
+                    edge(1, 2, 800.0),
+                    EdgeRecord {
+                        waypoints: vec![Coordinate { lat: 45.0015, lon: 5.0025 }],
+                        routes: vec![0],
+                        ..edge(1, 3, 500.0)
+                    },
+                    EdgeRecord { routes: vec![0], ..edge(3, 2, 500.0) },
+                ],
+            )
+        })
+        .expect("waymarked test graph")
+    }
+
+    #[test]
+    fn test_waymarked_weight_favours_or_avoids_marked_trails() {
+        let engine = waymarked_test_engine();
+        let follows_gr = |w_waymarked: f64| {
+            engine
+                .find_path(&RouteRequest {
+                    start: Coordinate { lat: 45.0, lon: 5.0 },
+                    end: Coordinate { lat: 45.0, lon: 5.01 },
+                    w_pop: 0.0,
+                    w_paved: 0.0,
+                    options: crate::models::RouteOptions { w_waymarked, ..Default::default() },
+                })
+                .expect("path")
+                .iter()
+                .any(|c| (c.lat - 45.003).abs() < 1e-9)
+        };
+
+        assert!(!follows_gr(0.0), "shortest path ignores the GR");
+        assert!(follows_gr(1.0), "bonus takes the GR via node 3");
+        assert!(!follows_gr(-1.0), "penalty keeps off the GR");
+    }
+
+    #[test]
+    fn test_waymarked_sections_report_followed_routes() {
+        let engine = waymarked_test_engine();
+        let marked = vec![
+            Coordinate { lat: 45.0, lon: 5.0 },
+            Coordinate { lat: 45.0015, lon: 5.0025 },
+            Coordinate { lat: 45.003, lon: 5.005 },
+            Coordinate { lat: 45.0, lon: 5.01 },
+        ];
+
+        let sections = engine.waymarked_sections(&engine.path_edges(&marked)).expect("sections");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].osm_id, 1234);
+        assert_eq!(sections[0].reference.as_deref(), Some("GR 9"));
+        assert_eq!(sections[0].from_km, 0.0);
+        let total = crate::geo_utils::approximate_distance_km(&marked);
+        assert!((sections[0].to_km - total).abs() < 1e-9);
+
+        let direct = vec![marked[0], marked[3]];
+        assert!(engine.waymarked_sections(&engine.path_edges(&direct)).is_none());
+    }
+
+    /// [`detour_test_graph`] with the given surroundings on each way.
+    fn environment_test_engine(
+        direct: crate::graph::EdgeEnvironment,
+        detour: crate::graph::EdgeEnvironment,
+    ) -> RouteEngine {
+        let mut graph = detour_test_graph();
+        graph.edges[0].environment = direct;
+        for edge in &mut graph.edges[1..] {
+            edge.environment = detour.clone();
+        }
+        RouteEngine::from_graph_file(graph).expect("environment test graph")
+    }
+
+    fn route_with_options(engine: &RouteEngine, options: crate::models::RouteOptions) -> Vec<Coordinate> {
+        engine
+            .find_path(&RouteRequest {
+                start: Coordinate { lat: 45.0, lon: 5.0 },
+                end: Coordinate { lat: 45.0, lon: 5.01 },
+                w_pop: 0.0,
+                w_paved: 0.0,
+                options,
+            })
+            .expect("path")
+    }
+
+    #[test]
+    fn test_quiet_weight_avoids_noisy_edges() {
+        use crate::graph::EdgeEnvironment;
+        use crate::models::RouteOptions;
+
+        // The straight edge runs along a trunk road
+        let engine = environment_test_engine(
+            EdgeEnvironment { major_road_distance_m: Some(0.0), ..Default::default() },
+            EdgeEnvironment::default(),
+        );
+
+        let noisy = route_with_options(&engine, RouteOptions::default());
+        let quiet = route_with_options(&engine, RouteOptions { w_quiet: 1.0, ..Default::default() });
+        assert_eq!(noisy.len(), 2, "shortest path follows the road");
+        assert!(quiet.iter().any(|c| (c.lat - 45.003).abs() < 1e-9), "detours via node 3");
+
+        assert_eq!(engine.quietness_score(&engine.path_edges(&noisy)), Some(0.0));
+        assert_eq!(engine.quietness_score(&engine.path_edges(&quiet)), Some(100.0));
+        assert_eq!(engine.quietness_score(&engine.path_edges(&noisy[..1])), None);
+    }
+
+    #[test]
+    fn test_scenery_weight_prefers_forest() {
+        use crate::graph::EdgeEnvironment;
+        use crate::models::RouteOptions;
+
+        // The detour runs through forest
+        let engine = environment_test_engine(
+            EdgeEnvironment::default(),
+            EdgeEnvironment { forest_fraction: 1.0, ..Default::default() },
+        );
+
+        let plain = route_with_options(&engine, RouteOptions::default());
+        let scenic = route_with_options(&engine, RouteOptions { w_scenery: 1.0, ..Default::default() });
+        assert_eq!(plain.len(), 2);
+        assert!(scenic.iter().any(|c| (c.lat - 45.003).abs() < 1e-9), "detours through the forest");
+    }
+
+    #[test]
+    fn test_shade_weight_prefers_forest_cover() {
+        use crate::graph::EdgeEnvironment;
+        use crate::models::RouteOptions;
+
+        // Half of the detour is under trees
+        let engine = environment_test_engine(
+            EdgeEnvironment::default(),
+            EdgeEnvironment { forest_fraction: 0.5, ..Default::default() },
+        );
+
+        let sunny = route_with_options(&engine, RouteOptions { w_shade: 0.1, ..Default::default() });
+        let shaded = route_with_options(&engine, RouteOptions { w_shade: 2.0, ..Default::default() });
+        assert_eq!(sunny.len(), 2, "a mild preference keeps the shortest path");
+        assert!(shaded.iter().any(|c| (c.lat - 45.003).abs() < 1e-9), "detours under the trees");
+
+        assert_eq!(engine.shade_percent(&engine.path_edges(&sunny)), Some(0.0));
+        assert_eq!(engine.shade_percent(&engine.path_edges(&shaded)), Some(50.0));
+    }
+
+    #[test]
+    fn test_lit_weight_prefers_lit_ways() {
+        use crate::models::RouteOptions;
+
+        // Only the detour is lit
+        let mut graph = detour_test_graph();
+        for edge in &mut graph.edges[1..] {
+            edge.attributes.lit = Some(true);
+        }
+        graph.edges[0].attributes.lit = Some(false);
+        let engine = RouteEngine::from_graph_file(graph).expect("lit test graph");
+
+        let day = route_with_options(&engine, RouteOptions::default());
+        let night = route_with_options(&engine, RouteOptions { w_lit: 1.0, ..Default::default() });
+        assert_eq!(day.len(), 2);
+        assert!(night.iter().any(|c| (c.lat - 45.003).abs() < 1e-9), "detours along lit ways");
+
+        assert_eq!(engine.lit_percent(&engine.path_edges(&day)), Some(0.0));
+        assert_eq!(engine.lit_percent(&engine.path_edges(&night)), Some(100.0));
+    }
+
+    #[test]
+    fn test_wheelchair_profile_avoids_steps_and_steep_grades() {
+        use crate::models::{RouteOptions, RoutingProfile};
+
+        let wheelchair = |max_grade_percent| RouteOptions {
+            profile: RoutingProfile::Wheelchair,
+            max_grade_percent,
+            ..Default::default()
+        };
+        let via_detour = |path: &[Coordinate]| path.iter().any(|c| (c.lat - 45.003).abs() < 1e-9);
+
+        // The short way is a flight of steps, the detour a 10% paved ramp
+        let mut graph = detour_test_graph();
+        graph.edges[0].attributes.highway = Some(Highway::Steps);
+        for edge in &mut graph.edges {
+            edge.surface = SurfaceType::Paved;
+        }
+        for edge in &mut graph.edges[1..] {
+            edge.environment.max_grade_percent = Some(10.0);
+        }
+        let engine = RouteEngine::from_graph_file(graph).expect("wheelchair test graph");
+
+        assert_eq!(route_with_options(&engine, RouteOptions::default()).len(), 2, "walkers take the steps");
+        assert!(via_detour(&route_with_options(&engine, wheelchair(Some(12.0)))));
+
+        let too_steep = RouteRequest {
+            start: Coordinate { lat: 45.0, lon: 5.0 },
+            end: Coordinate { lat: 45.0, lon: 5.01 },
+            w_pop: 0.0,
+            w_paved: 0.0,
+            options: wheelchair(None),
+        };
+        assert!(engine.find_path(&too_steep).is_none(), "10% is above the default limit");
+        let message = engine.no_accessible_route_message(&too_steep).expect("message");
+        assert!(message.starts_with("No accessible route"), "got {message}");
+    }
+
+    #[test]
+    fn test_cycling_profile_rides_cycleways_and_reports_hike_a_bike() {
+        use crate::models::{RouteOptions, RoutingProfile};
+
+        let cycling = RouteOptions { profile: RoutingProfile::Cycling, ..Default::default() };
+        let via_detour = |path: &[Coordinate]| path.iter().any(|c| (c.lat - 45.003).abs() < 1e-9);
+
+        // The short way is a footway, the detour a cycleway
+        let mut graph = detour_test_graph();
+        for edge in &mut graph.edges {
+            edge.surface = SurfaceType::Paved;
+        }
+        graph.edges[0].attributes.highway = Some(Highway::Footway);
+        for edge in &mut graph.edges[1..] {
+            edge.attributes.highway = Some(Highway::Cycleway);
+        }
+        let engine = RouteEngine::from_graph_file(graph.clone()).expect("cycling test graph");
+
+        let ride = route_with_options(&engine, cycling.clone());
+        assert!(via_detour(&ride));
+        let report = engine.cycling_report(&engine.path_edges(&ride), &cycling).expect("report");
+        assert_eq!(report.hike_a_bike_km, 0.0);
+        assert!(report.rideable_km > 0.9);
+        assert!(engine.cycling_report(&engine.path_edges(&ride), &RouteOptions::default()).is_none());
+
+        // Pushing along the footway is still reported when it is the only way
+        let footway = vec![Coordinate { lat: 45.0, lon: 5.0 }, Coordinate { lat: 45.0, lon: 5.01 }];
+        let pushed = engine.cycling_report(&engine.path_edges(&footway), &cycling).expect("report");
+        assert!(pushed.hike_a_bike_km > 0.7 && pushed.rideable_km == 0.0);
+
+        // Bikes forbidden everywhere
+        for edge in &mut graph.edges {
+            edge.attributes.bicycle = Some("no".to_string());
+        }
+        let engine = RouteEngine::from_graph_file(graph).expect("cycling test graph");
+        let req = RouteRequest {
+            start: Coordinate { lat: 45.0, lon: 5.0 },
+            end: Coordinate { lat: 45.0, lon: 5.01 },
+            w_pop: 0.0,
+            w_paved: 0.0,
+            options: cycling,
+        };
+        assert!(engine.find_path(&req).is_none());
+        let message = engine.no_accessible_route_message(&req).expect("message");
+        assert!(message.starts_with("No cyclable route"), "got {message}");
+    }
+
+    #[test]
+    fn test_max_sac_scale_avoids_alpine_ridge() {
+        use crate::models::RouteOptions;
+
+        // The short way is a faint T5 ridge, the detour a T2 mountain path
+        let mut graph = detour_test_graph();
+        graph.edges[0].attributes.sac_scale = Some(5);
+        graph.edges[0].attributes.trail_visibility = Some(TrailVisibility::Bad);
+        for edge in &mut graph.edges[1..] {
+            edge.attributes.sac_scale = Some(2);
+        }
+        let engine = RouteEngine::from_graph_file(graph).expect("sac test graph");
+        let limited = |max_sac_scale| RouteOptions { max_sac_scale, ..Default::default() };
+
+        let ridge = route_with_options(&engine, limited(None));
+        assert_eq!(ridge.len(), 2);
+        let hardest = engine.hardest_section(&engine.path_edges(&ridge)).expect("graded route");
+        assert_eq!(hardest.sac_scale, "T5");
+        assert_eq!(hardest.trail_visibility.as_deref(), Some("bad"));
+        assert_eq!(hardest.from_km, 0.0);
+        assert!((hardest.to_km - 0.79).abs() < 0.01, "got {}", hardest.to_km);
+
+        let family = route_with_options(&engine, limited(Some(3)));
+        assert!(family.iter().any(|c| (c.lat - 45.003).abs() < 1e-9));
+        let hardest = engine.hardest_section(&engine.path_edges(&family)).expect("graded route");
+        assert_eq!(hardest.sac_scale, "T2");
+        assert!(hardest.to_km > 1.0, "both detour edges merged, got {}", hardest.to_km);
+
+        let req = RouteRequest {
+            start: Coordinate { lat: 45.0, lon: 5.0 },
+            end: Coordinate { lat: 45.0, lon: 5.01 },
+            w_pop: 0.0,
+            w_paved: 0.0,
+            options: limited(Some(1)),
+        };
+        assert!(engine.find_path(&req).is_none());
+        let message = engine.no_accessible_route_message(&req).expect("message");
+        assert!(message.starts_with("No route up to T1"), "got {message}");
+    }
+
+    #[test]
+    fn test_off_trail_snap_crosses_terrain_to_the_network() {
+        use crate::models::RouteOptions;
+
+        let engine = RouteEngine::from_graph_file(detour_test_graph()).expect("off-trail test graph");
+        // A summit ~1.9 km north of the detour node, on flat terrain
+        let summit = Coordinate { lat: 45.02, lon: 5.005 };
+        let snap = engine.off_trail_snap(summit, |_| Some(1000.0)).expect("off-trail snap");
+        let node = engine.nodes[snap.node.index()].coord;
+        assert!((node.lat - 45.003).abs() < 1e-9, "nearest node reached");
+        assert_eq!((snap.road_prefix[0].lat, snap.road_prefix[0].lon), (summit.lat, summit.lon));
+        assert!(engine.off_trail_snap(summit, |_| None).is_none(), "no DEM");
+
+        let mut path = snap.road_prefix.clone();
+        path.push(Coordinate { lat: 45.0, lon: 5.01 });
+        let options = RouteOptions { off_trail_beyond_m: Some(200.0), ..Default::default() };
+        let sections = engine.off_trail_sections(&engine.path_edges(&path), &options).expect("off-trail section");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].from_km, 0.0);
+        assert!((sections[0].to_km - 1.89).abs() < 0.05, "got {}", sections[0].to_km);
+        assert!(engine.off_trail_sections(&engine.path_edges(&path), &RouteOptions::default()).is_none());
+    }
+
+    #[test]
+    fn test_nearest_lists_snap_candidates_and_choices_override_snapping() {
+        use crate::graph::EdgeRecord;
+        use crate::models::{RouteOptions, SnapChoice};
+
+        let mut graph = detour_test_graph();
+        graph.edges[0].attributes.name = Some(0);
+        graph.names = vec!["Route du col".to_string()];
+        graph.edges[0].waypoints = vec![Coordinate { lat: 45.0, lon: 5.004 }];
+        // An island away from the rest of the network
+        graph.nodes.extend([node(4, 45.0, 5.02), node(5, 45.0, 5.021)]);
+        graph.edges.push(EdgeRecord {
+            surface: SurfaceType::Trail,
+            attributes: crate::graph::WayAttributes { highway: Some(Highway::Path), ..Default::default() },
+            ..edge(4, 5, 79.0)
+        });
+        let engine = RouteEngine::from_graph_file(graph.clone()).expect("nearest test graph");
+
+        let click = Coordinate { lat: 45.0002, lon: 5.004 };
+        let candidates = engine.nearest(click, 5);
+        assert_eq!(candidates.len(), 4);
+        assert!(candidates.windows(2).all(|w| w[0].distance_from_click_m <= w[1].distance_from_click_m));
+        let first = &candidates[0];
+        assert!((first.distance_from_click_m - 22.2).abs() < 1.0, "got {}", first.distance_from_click_m);
+        assert_eq!(first.name.as_deref(), Some("Route du col"));
+        assert!(first.selected);
+        assert_eq!(candidates.iter().filter(|c| c.selected).count(), 1);
+        let island = candidates.iter().find(|c| c.highway.as_deref() == Some("path")).expect("island edge");
+        assert_eq!((first.component, island.component), (0, 1));
+
+        // Start on the detour instead of the nearest road, on a graph built
+        // again with its edges in another order
+        let detour_edge = candidates
+            .iter()
+            .find(|c| c.projected.lat > 45.0005 && c.projected.lon > 5.005)
+            .expect("second detour edge");
+        let options = RouteOptions {
+            snap_choices: vec![SnapChoice {
+                point: click,
+                projected: detour_edge.projected,
+                highway: detour_edge.highway.clone(),
+                name: detour_edge.name.clone(),
+            }],
+            ..Default::default()
+        };
+        graph.edges.reverse();
+        let rebuilt = RouteEngine::from_graph_file(graph).expect("rebuilt test graph");
+        let route_from_click = |options: &RouteOptions| {
+            rebuilt
+                .find_path(&RouteRequest {
+                    start: click,
+                    end: Coordinate { lat: 45.0, lon: 5.01 },
+                    w_pop: 0.0,
+                    w_paved: 0.0,
+                    options: options.clone(),
+                })
+                .expect("path")
+        };
+        let path = route_from_click(&options);
+        assert!((path[0].lat - detour_edge.projected.lat).abs() < 1e-9);
+        let response = rebuilt.route_response(path, &[], None, &options).expect("response");
+        assert!(response.unresolved_snap_choices.is_empty());
+
+        // No edge by that name there: the default snap, reported
+        let mut unknown = options;
+        unknown.snap_choices[0].name = Some("Route du col".to_string());
+        let path = route_from_click(&unknown);
+        assert!((path[0].lat - first.projected.lat).abs() < 1e-9);
+        let response = rebuilt.route_response(path, &[], None, &unknown).expect("response");
+        assert_eq!(response.unresolved_snap_choices.len(), 1);
+        assert!((response.unresolved_snap_choices[0].lat - click.lat).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_island_snapping_and_disconnected_island_message() {
+        use crate::graph::EdgeRecord;
+
+        let mut graph = detour_test_graph();
+        let road = |from, to| EdgeRecord { surface: SurfaceType::Paved, ..edge(from, to, 90.0) };
+        // A driveway cut off just north of node 2, and a fragment far east
+        graph.nodes.extend([node(4, 45.0012, 5.0100), node(5, 45.0020, 5.0100)]);
+        graph.nodes.extend([node(6, 45.0, 5.0300), node(7, 45.0, 5.0312)]);
+        graph.edges.extend([road(4, 5), road(6, 7)]);
+        let engine = RouteEngine::from_graph_file(graph).expect("island test graph");
+
+        let request = |start, end| RouteRequest { start, end, w_pop: 0.0, w_paved: 0.0, options: Default::default() };
+        let start = Coordinate { lat: 45.0, lon: 5.0 };
+
+        // The click lands on the driveway, but the main network is close
+        let driveway = request(start, Coordinate { lat: 45.0013, lon: 5.0101 });
+        let path = engine.find_path(&driveway).expect("snapped onto the main network");
+        assert!(path.iter().all(|c| c.lat < 45.0012 || c.lon < 5.0099), "never enters the driveway");
+        assert_eq!(engine.disconnected_island_message(&driveway), None);
+
+        let far_island = Coordinate { lat: 45.0, lon: 5.0305 };
+        let to_island = request(start, far_island);
+        assert!(engine.find_path(&to_island).is_none());
+        let message = engine.disconnected_island_message(&to_island).expect("island message");
+        assert!(message.contains("end is on a disconnected island (2 nodes"), "{message}");
+        let message = engine.disconnected_island_message(&request(far_island, start)).expect("island message");
+        assert!(message.contains("start is on a disconnected island"), "{message}");
+
+        // Both ends on the same fragment route along it
+        let within = request(far_island, Coordinate { lat: 45.0, lon: 5.0311 });
+        assert!(engine.find_path(&within).is_some());
+        assert_eq!(engine.disconnected_island_message(&within), None);
+    }
+
+    #[test]
+    fn test_explain_route_breaks_down_edge_costs() {
+        use crate::models::RouteOptions;
+
+        let mut graph = detour_test_graph();
+        graph.edges[0].surface = SurfaceType::Paved;
+        for edge in &mut graph.edges[1..] {
+            edge.surface = SurfaceType::Trail;
+        }
+        let engine = RouteEngine::from_graph_file(graph).expect("explain test graph");
+        let request = |options| RouteRequest {
+            start: Coordinate { lat: 45.0, lon: 5.0 },
+            end: Coordinate { lat: 45.0, lon: 5.01 },
+            w_pop: 0.0,
+            w_paved: 1.0,
+            options,
+        };
+
+        // 0.8 km paved costs 1.6, twice 0.5 km of trail 1.2
+        let req = request(RouteOptions { debug: true, ..Default::default() });
+        let (_, nodes) = engine.find_path_returning_indices(&req).expect("path");
+        let debug = engine.explain_route(&req, &nodes).expect("debug");
+        assert_eq!(debug.edges.len(), 2);
+        for edge in &debug.edges {
+            assert!((edge.length_km - 0.5).abs() < 1e-9);
+            assert!((edge.paved - 0.1).abs() < 1e-9, "paved term {}", edge.paved);
+            assert_eq!((edge.population, edge.profile, edge.exclusion), (0.0, 0.0, 0.0));
+            assert!((edge.total - 0.6).abs() < 1e-9);
+        }
+        assert!((debug.total_cost - 1.2).abs() < 1e-9);
+        assert_eq!((debug.edges[0].to.lat, debug.edges[0].to.lon), (45.003, 5.005));
+        assert!(debug.settled_nodes.is_none());
+
+        let req = request(RouteOptions { debug: true, debug_search_space: true, ..Default::default() });
+        let debug = engine.explain_route(&req, &nodes).expect("debug");
+        let settled = debug.settled_nodes.expect("search space");
+        assert_eq!(settled.kind, "MultiPoint");
+        assert!(settled.coordinates.contains(&[5.0, 45.0]));
+        assert!(settled.coordinates.contains(&[5.01, 45.0]));
+
+        // A route the search did not pick, as through a water point, is
+        // explained as returned, without the search space
+        let direct = [NodeIndex::new(0), NodeIndex::new(1)];
+        let debug = engine.explain_route(&req, &direct).expect("debug");
+        assert_eq!(debug.edges.len(), 1);
+        assert!((debug.total_cost - 1.6).abs() < 1e-9, "total cost {}", debug.total_cost);
+        assert!(debug.settled_nodes.is_none());
+    }
+
+    #[test]
+    fn test_maneuvers_at_junctions_and_name_changes() {
+        use crate::graph::{EdgeRecord, WayAttributes};
+        use crate::models::{Language, ManeuverType};
+
+        let road = |from, to, name| EdgeRecord {
+            surface: SurfaceType::Paved,
+            attributes: WayAttributes {
+                highway: Some(Highway::Unclassified),
+                name: Some(name),
+                ..Default::default()
+            },
+            ..edge(from, to, 1000.0)
+        };
+        // Route du Col runs east through a T junction; Chemin de la Croix
+        // leaves it northwards and bends north-east without a junction
+        let engine = RouteEngine::from_graph_file(GraphFile {
+            names: vec!["Route du Col".to_string(), "Chemin de la Croix".to_string()],
+            ..test_graph::graph(
+                vec![
+                    node(1, 45.0, 5.0),
+                    node(2, 45.0, 5.01),
+                    node(3, 45.0, 5.02),
+                    node(4, 45.01, 5.01),
+                    node(5, 45.015, 5.017),
+                ],
+                vec![road(1, 2, 0), road(2, 3, 0), road(2, 4, 1), road(4, 5, 1)],
+            )
+        })
+        .expect("maneuver test graph");
+
+        let (path, nodes) = engine
+            .find_path_returning_indices(&RouteRequest {
+                start: Coordinate { lat: 45.0, lon: 5.0 },
+                end: Coordinate { lat: 45.015, lon: 5.017 },
+                w_pop: 0.0,
+                w_paved: 0.0,
+                options: Default::default(),
+            })
+            .expect("path");
+        let maneuvers = engine.maneuvers(&path, &nodes, &engine.path_edges(&path), Language::Fr).expect("maneuvers");
+        let kinds: Vec<ManeuverType> = maneuvers.iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, [ManeuverType::Depart, ManeuverType::Left, ManeuverType::Arrive]);
+
+        assert_eq!(maneuvers[0].instruction, "Partez sur Route du Col");
+        let left = &maneuvers[1];
+        assert_eq!((left.path_index, left.name.as_deref()), (1, Some("Chemin de la Croix")));
+        assert!((left.bearing_change_deg + 90.0).abs() < 1.0, "got {}", left.bearing_change_deg);
+        assert_eq!(left.instruction, "Tournez à gauche sur Chemin de la Croix après 790 m");
+        assert!((maneuvers[0].distance_to_next_km - 0.786).abs() < 0.01);
+        let total: f64 = maneuvers.iter().map(|m| m.distance_to_next_km).sum();
+        assert!((total - crate::geo_utils::approximate_distance_km(&path)).abs() < 0.01);
+        assert_eq!(maneuvers[2].path_index, path.len() - 1);
+
+        let english = engine.maneuvers(&path, &nodes, &engine.path_edges(&path), Language::En).expect("maneuvers");
+        assert_eq!(english[1].instruction, "Turn left onto Chemin de la Croix after 790 m");
+    }
+
+    #[test]
+    fn test_route_legs_group_edges_with_the_same_attributes() {
+        use crate::graph::{EdgeRecord, WayAttributes};
+        use crate::models::GradeClass;
+        use crate::waymarked::WaymarkedRoute;
+
+        let node = |id, lon| node(id, 45.0, lon);
+        let trail = |from, to| EdgeRecord {
+            surface: SurfaceType::Trail,
+            routes: vec![0],
+            attributes: WayAttributes {
+                highway: Some(Highway::Path),
+                incline_percent: Some(18.0),
+                ..Default::default()
+            },
+            ..edge(from, to, 394.0)
+        };
+        let road = EdgeRecord {
+            surface: SurfaceType::Paved,
+            routes: vec![],
+            attributes: WayAttributes {
+                highway: Some(Highway::Unclassified),
+                name: Some(0),
+                ..Default::default()
+            },
+            ..trail(3, 4)
+        };
+        let engine = RouteEngine::from_graph_file(GraphFile {
+            routes: vec![WaymarkedRoute {
+                osm_id: 1,
+                route: "hiking".to_string(),
+                network: "nwn".to_string(),
+                name: Some("Sentier de grande randonnée 5".to_string()),
+                reference: Some("GR 5".to_string()),
+                colour: None,
+                osmc_symbol: None,
+            }],
+            names: vec!["Route du Col".to_string()],
+            ..test_graph::graph(
+                vec![node(1, 5.0), node(2, 5.005), node(3, 5.01), node(4, 5.015)],
+                vec![trail(1, 2), trail(2, 3), road],
+            )
+        })
+        .expect("legs test graph");
+
+        // Snapped from a click just west of the first node
+        let path: Vec<Coordinate> = [4.999, 5.0, 5.005, 5.01, 5.015]
+            .iter()
+            .map(|&lon| Coordinate { lat: 45.0, lon })
+            .collect();
+        let elevations = [Some(1000.0), Some(1000.0), Some(1060.0), Some(1130.0), Some(1120.0)];
+        let legs = engine.route_legs(&engine.path_edges(&path), Some(&elevations)).expect("legs");
+
+        assert_eq!(legs.len(), 3);
+        assert_eq!((legs[0].from_index, legs[0].to_index, legs[0].surface), (0, 1, None));
+        let gr = &legs[1];
+        assert_eq!((gr.from_index, gr.to_index), (1, 3));
+        assert_eq!(gr.surface, Some(SurfaceType::Trail));
+        assert_eq!(gr.highway.as_deref(), Some("path"));
+        assert_eq!(gr.waymarked.as_deref(), Some("GR 5"));
+        assert_eq!(gr.grade_class, Some(GradeClass::VerySteep));
+        assert!((gr.distance_km - 0.788).abs() < 0.01, "got {}", gr.distance_km);
+        assert!((gr.ascent_m - 130.0).abs() < 1e-9);
+        let road = &legs[2];
+        assert_eq!((road.name.as_deref(), road.grade_class, road.ascent_m), (Some("Route du Col"), None, 0.0));
+
+        let without_profile = engine.route_legs(&engine.path_edges(&path), None).expect("legs");
+        assert!(without_profile.iter().all(|leg| leg.ascent_m == 0.0));
+    }
+
+    #[test]
+    fn test_surface_and_highway_breakdown_along_route_path() {
+        let mut graph = detour_test_graph();
+        graph.edges[1].surface = SurfaceType::Trail;
+        graph.edges[1].attributes.highway = Some(Highway::Track);
+        graph.edges[2].surface = SurfaceType::Paved;
+        let engine = RouteEngine::from_graph_file(graph).expect("breakdown test graph");
+
+        // Two legs meeting at the detour node
+        let mut nodes = vec![NodeIndex::new(0), NodeIndex::new(2)];
+        RouteEngine::append_leg_nodes(&mut nodes, &[NodeIndex::new(2), NodeIndex::new(1)]);
+        assert_eq!(nodes, [NodeIndex::new(0), NodeIndex::new(2), NodeIndex::new(1)]);
+
+        // Along the detour, then halfway back along the direct road and off
+        // the graph
+        let detour = haversine_km(Coordinate { lat: 45.0, lon: 5.0 }, Coordinate { lat: 45.003, lon: 5.005 });
+        let path: Vec<Coordinate> = nodes
+            .iter()
+            .map(|node| engine.nodes[node.index()].coord)
+            .chain([Coordinate { lat: 45.0, lon: 5.005 }, Coordinate { lat: 45.001, lon: 5.005 }])
+            .collect();
+        let road = haversine_km(path[2], path[3]);
+        let off_graph = haversine_km(path[3], path[4]);
+
+        let surfaces = engine.surface_breakdown(&engine.path_edges(&path)).expect("surfaces");
+        let labels: Vec<&str> = surfaces.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["paved", "trail", "dirt", "unknown"]);
+        for ((_, km), expected) in surfaces.iter().zip([detour, detour, road, off_graph]) {
+            assert!((km - expected).abs() < 1e-9, "got {km}, expected {expected}");
+        }
+        let total: f64 = surfaces.iter().map(|(_, km)| km).sum();
+        assert!((total - approximate_distance_km(&path)).abs() < 1e-9);
+
+        let highways = engine.highway_breakdown(&engine.path_edges(&path)).expect("highways");
+        assert_eq!(highways[0].0, "unknown");
+        assert!((highways[0].1 - (detour + road + off_graph)).abs() < 1e-9);
+        assert_eq!(highways[1].0, "track");
+        assert!(engine.surface_breakdown(&engine.path_edges(&path[..1])).is_none());
+    }
+
+    #[test]
+    fn test_chemins_noirs_score_favours_quiet_tracks() {
+        use crate::graph::EdgeEnvironment;
+        use crate::models::RouteOptions;
+
+        // The straight edge is a busy paved road
+        let mut graph = detour_test_graph();
+        graph.edges[0].surface = SurfaceType::Paved;
+        graph.edges[0].attributes.highway = Some(Highway::Secondary);
+        graph.edges[0].environment = EdgeEnvironment { major_road_distance_m: Some(0.0), ..Default::default() };
+        for edge in &mut graph.edges[1..] {
+            edge.attributes.highway = Some(Highway::Track);
+        }
+        let engine = RouteEngine::from_graph_file(graph).expect("score test graph");
+
+        let road = route_with_options(&engine, RouteOptions::default());
+        let score = engine.chemins_noirs_score(&engine.path_edges(&road)).expect("road score");
+        assert!((score.road_class - 10.0).abs() < 1e-9);
+        assert_eq!((score.surface, score.quietness), (0.0, 0.0));
+        assert!((score.score - 3.75).abs() < 1e-9);
+
+        let track = route_with_options(&engine, RouteOptions { w_quiet: 1.0, ..Default::default() });
+        let score = engine.chemins_noirs_score(&engine.path_edges(&track)).expect("track score");
+        assert_eq!((score.road_class, score.surface, score.quietness), (100.0, 100.0, 100.0));
+        assert_eq!(score.waymarked, 0.0);
+        assert!((score.score - 87.5).abs() < 1e-9, "got {}", score.score);
+        assert!(engine.chemins_noirs_score(&engine.path_edges(&track[..1])).is_none());
+    }
 }
@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use crate::models::Coordinate;
 
 pub const EARTH_RADIUS_KM: f64 = 6_371.0;
-const EARTH_RADIUS_M: f64 = 6_371_000.0;
+pub const EARTH_RADIUS_M: f64 = 6_371_000.0;
 
 pub fn haversine_km(a: Coordinate, b: Coordinate) -> f64 {
     let lat1 = a.lat.to_radians();
@@ -45,6 +47,101 @@ pub fn approximate_distance_km(path: &[Coordinate]) -> f64 {
     path.windows(2).map(|w| haversine_km(w[0], w[1])).sum()
 }
 
+/// Initial great-circle bearing from `from` to `to`, in degrees clockwise from north [0, 360).
+pub fn initial_bearing_deg(from: Coordinate, to: Coordinate) -> f64 {
+    let lat1 = from.lat.to_radians();
+    let lat2 = to.lat.to_radians();
+    let dlon = (to.lon - from.lon).to_radians();
+
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Even-odd ray casting test in lat/lon space (fine for areas of a few tens of km).
+/// The polygon may be given open or closed.
+pub fn point_in_polygon(point: Coordinate, polygon: &[Coordinate]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a.lat > point.lat) != (b.lat > point.lat) {
+            let lon_at_lat = a.lon + (point.lat - a.lat) / (b.lat - a.lat) * (b.lon - a.lon);
+            if point.lon < lon_at_lat {
+                inside = !inside;
+            }
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Shortest distance in meters from `point` to the polyline `path`.
+/// Uses a local equirectangular projection centred on `point`, which is accurate
+/// to well under a percent at hiking scales. Returns `f64::INFINITY` for an empty path.
+pub fn distance_to_path_m(point: Coordinate, path: &[Coordinate]) -> f64 {
+    closest_point_on_path(point, path).map_or(f64::INFINITY, |(distance_m, _, _)| distance_m)
+}
+
+/// Closest point of the polyline `path` to `point`, with the projection of
+/// [`distance_to_path_m`]: its distance in meters, the index of the segment
+/// it lies on and its position along that segment, from 0 to 1. A single
+/// point is segment 0 at 0; an empty path gives `None`.
+pub fn closest_point_on_path(point: Coordinate, path: &[Coordinate]) -> Option<(f64, usize, f64)> {
+    let cos_lat = point.lat.to_radians().cos();
+    let project = |c: Coordinate| {
+        (
+            (c.lon - point.lon).to_radians() * cos_lat * EARTH_RADIUS_M,
+            (c.lat - point.lat).to_radians() * EARTH_RADIUS_M,
+        )
+    };
+
+    if path.len() == 1 {
+        let (x, y) = project(path[0]);
+        return Some((x.hypot(y), 0, 0.0));
+    }
+
+    let mut best: Option<(f64, usize, f64)> = None;
+    for (i, w) in path.windows(2).enumerate() {
+        let (ax, ay) = project(w[0]);
+        let (bx, by) = project(w[1]);
+        let (dx, dy) = (bx - ax, by - ay);
+        let len_sq = dx * dx + dy * dy;
+        let t = if len_sq > 0.0 {
+            (-(ax * dx + ay * dy) / len_sq).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let distance = (ax + t * dx).hypot(ay + t * dy);
+        if best.is_none_or(|(closest, _, _)| distance < closest) {
+            best = Some((distance, i, t));
+        }
+    }
+    best
+}
+
+/// Whether a closed path runs clockwise on the map (shoelace formula on
+/// longitude/latitude, longitude scaled by the cosine of the mean latitude).
+/// Degenerate paths with no enclosed area count as counter-clockwise.
+pub fn is_clockwise(path: &[Coordinate]) -> bool {
+    if path.len() < 3 {
+        return false;
+    }
+    let cos_lat = (path.iter().map(|c| c.lat).sum::<f64>() / path.len() as f64)
+        .to_radians()
+        .cos();
+    let twice_area: f64 = path
+        .iter()
+        .zip(path.iter().cycle().skip(1))
+        .map(|(a, b)| a.lon * cos_lat * b.lat - b.lon * cos_lat * a.lat)
+        .sum();
+    twice_area < 0.0
+}
+
 pub fn compute_bounds(path: &[Coordinate]) -> (f64, f64, f64, f64) {
     let mut min_lat = f64::MAX;
     let mut max_lat = f64::MIN;
@@ -61,6 +158,80 @@ pub fn compute_bounds(path: &[Coordinate]) -> (f64, f64, f64, f64) {
     (min_lat, max_lat, min_lon, max_lon)
 }
 
+/// Polyline vertices plus intermediate points at most `spacing_m` apart.
+pub fn sample_polyline(polyline: &[Coordinate], spacing_m: f64) -> Vec<Coordinate> {
+    let Some(&first) = polyline.first() else {
+        return Vec::new();
+    };
+    let mut samples = vec![first];
+    for pair in polyline.windows(2) {
+        let steps = (haversine_km(pair[0], pair[1]) * 1000.0 / spacing_m).ceil().max(1.0) as usize;
+        samples.extend((1..=steps).map(|i| pair[0].interpolate(pair[1], i as f64 / steps as f64)));
+    }
+    samples
+}
+
+pub type GridCell = (i64, i64);
+
+/// Segments bucketed on a regular lat/lon grid, for nearest-segment queries
+/// within a bounded radius. `K` tells apart the kinds of features indexed.
+pub struct SegmentGrid<K> {
+    cell_deg: f64,
+    cells: HashMap<GridCell, Vec<(K, Coordinate, Coordinate)>>,
+}
+
+impl<K: Copy + PartialEq> SegmentGrid<K> {
+    pub fn new(cell_deg: f64) -> Self {
+        Self { cell_deg, cells: HashMap::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Index the segment `a → b`; pass `a == b` for a point feature.
+    pub fn insert(&mut self, kind: K, a: Coordinate, b: Coordinate) {
+        let (ay, ax) = self.cell_of(a);
+        let (by, bx) = self.cell_of(b);
+        for y in ay.min(by)..=ay.max(by) {
+            for x in ax.min(bx)..=ax.max(bx) {
+                self.cells.entry((y, x)).or_default().push((kind, a, b));
+            }
+        }
+    }
+
+    /// Distance in meters from `point` to the nearest segment of the given
+    /// kind, if within `radius_m`.
+    pub fn nearest_m(&self, point: Coordinate, kind: K, radius_m: f64) -> Option<f64> {
+        let radius_lat_deg = radius_m / 111_000.0;
+        let radius_lon_deg = radius_lat_deg / point.lat.to_radians().cos().max(0.01);
+        let reach_lat = (radius_lat_deg / self.cell_deg).ceil() as i64;
+        let reach_lon = (radius_lon_deg / self.cell_deg).ceil() as i64;
+        let (cy, cx) = self.cell_of(point);
+
+        let mut nearest: Option<f64> = None;
+        for y in cy - reach_lat..=cy + reach_lat {
+            for x in cx - reach_lon..=cx + reach_lon {
+                for &(k, a, b) in self.cells.get(&(y, x)).into_iter().flatten() {
+                    if k != kind {
+                        continue;
+                    }
+                    let distance = distance_to_path_m(point, &[a, b]);
+                    if distance <= radius_m && nearest.is_none_or(|n| distance < n) {
+                        nearest = Some(distance);
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
+    /// Grid cell containing `c`.
+    pub fn cell_of(&self, c: Coordinate) -> GridCell {
+        ((c.lat / self.cell_deg).floor() as i64, (c.lon / self.cell_deg).floor() as i64)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,4 +309,66 @@ mod tests {
         }];
         assert_eq!(approximate_distance_km(&path), 0.0);
     }
+
+    #[test]
+    fn test_initial_bearing_cardinal_directions() {
+        let origin = Coordinate { lat: 45.0, lon: 5.0 };
+        let north = Coordinate { lat: 45.1, lon: 5.0 };
+        let east = Coordinate { lat: 45.0, lon: 5.1 };
+        let south = Coordinate { lat: 44.9, lon: 5.0 };
+        let west = Coordinate { lat: 45.0, lon: 4.9 };
+
+        assert!(initial_bearing_deg(origin, north).abs() < 0.01);
+        assert!((initial_bearing_deg(origin, east) - 90.0).abs() < 0.1);
+        assert!((initial_bearing_deg(origin, south) - 180.0).abs() < 0.01);
+        assert!((initial_bearing_deg(origin, west) - 270.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_point_in_polygon() {
+        let square = vec![
+            Coordinate { lat: 45.0, lon: 5.0 },
+            Coordinate { lat: 45.0, lon: 5.1 },
+            Coordinate { lat: 45.1, lon: 5.1 },
+            Coordinate { lat: 45.1, lon: 5.0 },
+        ];
+        assert!(point_in_polygon(Coordinate { lat: 45.05, lon: 5.05 }, &square));
+        assert!(!point_in_polygon(Coordinate { lat: 45.2, lon: 5.05 }, &square));
+        assert!(!point_in_polygon(Coordinate { lat: 45.05, lon: 4.95 }, &square));
+        assert!(!point_in_polygon(Coordinate { lat: 45.05, lon: 5.05 }, &square[..2]));
+    }
+
+    #[test]
+    fn test_distance_to_path_m() {
+        let path = vec![
+            Coordinate { lat: 45.0, lon: 5.0 },
+            Coordinate { lat: 45.0, lon: 5.01 },
+        ];
+        // ~0.001° north of the segment midpoint ≈ 111 m
+        let above = Coordinate { lat: 45.001, lon: 5.005 };
+        assert!((distance_to_path_m(above, &path) - 111.2).abs() < 1.0);
+
+        // Beyond the segment end, distance is to the endpoint
+        let beyond = Coordinate { lat: 45.0, lon: 5.02 };
+        let expected = haversine_m(45.0, 5.01, 45.0, 5.02);
+        assert!((distance_to_path_m(beyond, &path) - expected).abs() < 1.0);
+
+        assert_eq!(distance_to_path_m(above, &[]), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_is_clockwise() {
+        // north → east → south-west back to start
+        let clockwise = vec![
+            Coordinate { lat: 45.0, lon: 5.0 },
+            Coordinate { lat: 45.1, lon: 5.0 },
+            Coordinate { lat: 45.1, lon: 5.1 },
+            Coordinate { lat: 45.0, lon: 5.0 },
+        ];
+        assert!(is_clockwise(&clockwise));
+
+        let counter: Vec<_> = clockwise.iter().rev().copied().collect();
+        assert!(!is_clockwise(&counter));
+        assert!(!is_clockwise(&clockwise[..2]));
+    }
 }
@@ -9,17 +9,37 @@ use std::{
 
 use lru::LruCache;
 use once_cell::sync::Lazy;
-use osmpbf::{Element, ElementReader};
+use osmpbf::{Element, ElementReader, RelMemberType};
 use serde::{Deserialize, Serialize};
 
+use crate::accessibility::{annotate_grades, kerb_from_tags, Kerb, Smoothness};
 use crate::geo_utils::haversine_km;
 use crate::models::{Coordinate, SurfaceType};
+use crate::poi::Poi;
+use crate::quietness::{NoiseIndex, NoiseSource};
+use crate::sac_scale::{parse_sac_scale, TrailVisibility};
+use crate::scenery::{assemble_rings, LandCover, SceneryIndex};
+use crate::waymarked::WaymarkedRoute;
 
 /// Type aliases for complex OSM data structures
 type OsmTags = Vec<(String, String)>;
 type NodeIds = Vec<i64>;
 type OsmWay = (i64, NodeIds, OsmTags);
 type NodeCoordMap = HashMap<i64, (f64, f64, Option<f64>)>;
+type RouteRelation = (WaymarkedRoute, Vec<i64>);
+type NodeEntry = (i64, (f64, f64, Option<f64>));
+type NoiseWay = (NoiseSource, NodeIds);
+type LandCoverWay = (LandCover, NodeIds);
+/// Land cover and outer member way ids of a multipolygon relation
+type LandCoverRelation = (LandCover, Vec<i64>);
+
+/// Header written before the postcard payload of `.bin` graphs.
+/// Postcard is not self-describing, so graphs written by an older layout must be
+/// rejected instead of being decoded into garbage; bump the version whenever
+/// `GraphFile`, `NodeRecord` or `EdgeRecord` change.
+const GRAPH_BINARY_MAGIC: &[u8; 4] = b"CNGF";
+const GRAPH_BINARY_VERSION: u8 = 11;
+type NodeGrid = HashMap<(i64, i64), Vec<(u64, f64, f64)>>;
 
 /// Trait for graph caching abstraction (Dependency Inversion Principle)
 ///
@@ -95,6 +115,15 @@ impl GraphCache for NoOpCache {
 pub struct GraphFile {
     pub nodes: Vec<NodeRecord>,
     pub edges: Vec<EdgeRecord>,
+    /// Points of interest (water, huts, peaks...) found in the same PBF pass
+    #[serde(default)]
+    pub pois: Vec<Poi>,
+    /// Waymarked route relations referenced by `EdgeRecord::routes`
+    #[serde(default)]
+    pub routes: Vec<WaymarkedRoute>,
+    /// Way names referenced by `WayAttributes::name`
+    #[serde(default)]
+    pub names: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -118,6 +147,237 @@ pub struct EdgeRecord {
     /// This preserves the actual geometry of the road
     #[serde(default)]
     pub waypoints: Vec<Coordinate>,
+    /// Indices into `GraphFile::routes` of the waymarked routes using this edge
+    #[serde(default)]
+    pub routes: Vec<u32>,
+    #[serde(default)]
+    pub attributes: WayAttributes,
+    #[serde(default)]
+    pub environment: EdgeEnvironment,
+}
+
+/// Tags of the OSM way an edge comes from, beyond its surface.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WayAttributes {
+    /// `lit=*`: `None` when untagged
+    pub lit: Option<bool>,
+    pub highway: Option<Highway>,
+    /// `incline=*` as a signed grade in percent, when numeric
+    pub incline_percent: Option<f64>,
+    /// Raw `surface=*` value (the coarse class is `EdgeRecord::surface`)
+    pub surface: Option<String>,
+    pub smoothness: Option<Smoothness>,
+    /// Worst `kerb=*` of the way and of the kerb nodes along the edge
+    pub kerb: Option<Kerb>,
+    pub wheelchair: Option<String>,
+    pub bicycle: Option<String>,
+    /// Best cycle infrastructure of `cycleway=*` and its `:left`, `:right`
+    /// and `:both` variants
+    pub cycleway: Option<String>,
+    /// `mtb:scale=*` level, 0 to 6 (`+`/`-` suffixes dropped)
+    pub mtb_scale: Option<u8>,
+    /// `sac_scale=*` level, 1 (T1) to 6 (T6)
+    pub sac_scale: Option<u8>,
+    pub trail_visibility: Option<TrailVisibility>,
+    /// Index into `GraphFile::names`
+    pub name: Option<u32>,
+}
+
+impl WayAttributes {
+    /// Attributes from the tags of a way. `name` is left out: it is interned
+    /// in the names of the graph by the builder.
+    pub fn from_tags(tags: &[(String, String)]) -> Self {
+        let mut attributes = Self::default();
+        for (key, value) in tags {
+            match key.as_str() {
+                "lit" => {
+                    attributes.lit = match value.as_str() {
+                        "yes" | "24/7" | "automatic" | "limited" | "interval" => Some(true),
+                        "no" | "disused" => Some(false),
+                        _ => None,
+                    }
+                }
+                "highway" => attributes.highway = Highway::from_tag(value),
+                "incline" => attributes.incline_percent = parse_incline(value),
+                "surface" => attributes.surface = Some(value.clone()),
+                "smoothness" => attributes.smoothness = Smoothness::from_tag(value),
+                "kerb" => attributes.kerb = attributes.kerb.max(Kerb::from_tag(value)),
+                "wheelchair" => attributes.wheelchair = Some(value.clone()),
+                "bicycle" => attributes.bicycle = Some(value.clone()),
+                "cycleway" | "cycleway:left" | "cycleway:right" | "cycleway:both" => {
+                    attributes.add_cycleway(value)
+                }
+                "mtb:scale" => {
+                    attributes.mtb_scale = value.trim_end_matches(['+', '-']).parse().ok().filter(|&s| s <= 6)
+                }
+                "sac_scale" => attributes.sac_scale = parse_sac_scale(value),
+                "trail_visibility" => attributes.trail_visibility = TrailVisibility::from_tag(value),
+                _ => {}
+            }
+        }
+        attributes
+    }
+
+    /// Whether the way is a flight of steps.
+    pub fn is_steps(&self) -> bool {
+        self.highway == Some(Highway::Steps)
+    }
+
+    fn add_cycleway(&mut self, cycleway: &str) {
+        let rank = |cycleway: &str| match cycleway {
+            "track" | "opposite_track" => 3,
+            "lane" | "opposite_lane" => 2,
+            "shared_lane" | "share_busway" => 1,
+            _ => 0,
+        };
+        if self.cycleway.as_deref().is_none_or(|current| rank(cycleway) > rank(current)) {
+            self.cycleway = Some(cycleway.to_string());
+        }
+    }
+}
+
+/// `highway=*` values of the ways kept in the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Highway {
+    Path,
+    Footway,
+    Pedestrian,
+    Cycleway,
+    Bridleway,
+    Steps,
+    LivingStreet,
+    Secondary,
+    SecondaryLink,
+    Tertiary,
+    TertiaryLink,
+    Residential,
+    Track,
+    Service,
+    Unclassified,
+    Primary,
+    PrimaryLink,
+    Trunk,
+    TrunkLink,
+}
+
+impl Highway {
+    const ALL: [Self; 19] = [
+        Self::Path,
+        Self::Footway,
+        Self::Pedestrian,
+        Self::Cycleway,
+        Self::Bridleway,
+        Self::Steps,
+        Self::LivingStreet,
+        Self::Secondary,
+        Self::SecondaryLink,
+        Self::Tertiary,
+        Self::TertiaryLink,
+        Self::Residential,
+        Self::Track,
+        Self::Service,
+        Self::Unclassified,
+        Self::Primary,
+        Self::PrimaryLink,
+        Self::Trunk,
+        Self::TrunkLink,
+    ];
+
+    /// The highway class of a `highway=*` value, `None` for ways left out
+    /// of the graph.
+    pub fn from_tag(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|highway| highway.as_str() == value)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Footway => "footway",
+            Self::Pedestrian => "pedestrian",
+            Self::Cycleway => "cycleway",
+            Self::Bridleway => "bridleway",
+            Self::Steps => "steps",
+            Self::LivingStreet => "living_street",
+            Self::Secondary => "secondary",
+            Self::SecondaryLink => "secondary_link",
+            Self::Tertiary => "tertiary",
+            Self::TertiaryLink => "tertiary_link",
+            Self::Residential => "residential",
+            Self::Track => "track",
+            Self::Service => "service",
+            Self::Unclassified => "unclassified",
+            Self::Primary => "primary",
+            Self::PrimaryLink => "primary_link",
+            Self::Trunk => "trunk",
+            Self::TrunkLink => "trunk_link",
+        }
+    }
+}
+
+/// Way names of a graph, each stored once: `WayAttributes::name` indexes
+/// the `names` handed over to `GraphFile::names`.
+#[derive(Default)]
+struct NameTable {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+}
+
+impl NameTable {
+    fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+}
+
+/// `10%`, `-8 %` or `5°`; `up`, `down` and other non-numeric values give `None`.
+fn parse_incline(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if let Some(degrees) = value.strip_suffix('°') {
+        let degrees: f64 = degrees.trim().parse().ok()?;
+        return Some(degrees.to_radians().tan() * 100.0);
+    }
+    value.strip_suffix('%').unwrap_or(value).trim().parse().ok()
+}
+
+/// Surroundings of an edge, precomputed at graph build time.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EdgeEnvironment {
+    /// Distance to the nearest motorway, trunk or primary road, if within
+    /// [`crate::quietness::NOISE_HORIZON_M`]
+    pub major_road_distance_m: Option<f64>,
+    /// Distance to the nearest railway, if within the same horizon
+    pub railway_distance_m: Option<f64>,
+    /// Share of the edge length inside forest, 0 to 1
+    pub forest_fraction: f64,
+    /// Distance to the nearest river, stream or lake shore, if within
+    /// [`crate::scenery::WATER_HORIZON_M`]
+    pub water_distance_m: Option<f64>,
+    /// Distance to the nearest viewpoint or peak, if within
+    /// [`crate::scenery::VIEWPOINT_HORIZON_M`]
+    pub viewpoint_distance_m: Option<f64>,
+    /// Steepest grade along the edge from the DEM, in percent
+    pub max_grade_percent: Option<f64>,
+}
+
+impl EdgeRecord {
+    /// Full geometry from `from` to `to`. `nodes` must be indexed by graph
+    /// id - 1, as produced by the graph builder.
+    pub fn polyline(&self, nodes: &[NodeRecord]) -> Option<Vec<Coordinate>> {
+        let coord = |id: u64| {
+            let node = nodes.get(id.checked_sub(1)? as usize)?;
+            Some(Coordinate { lat: node.lat, lon: node.lon })
+        };
+        let mut polyline = Vec::with_capacity(self.waypoints.len() + 2);
+        polyline.push(coord(self.from)?);
+        polyline.extend_from_slice(&self.waypoints);
+        polyline.push(coord(self.to)?);
+        Some(polyline)
+    }
 }
 
 impl GraphFile {
@@ -153,6 +413,8 @@ impl GraphFile {
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
+        writer.write_all(GRAPH_BINARY_MAGIC)?;
+        writer.write_all(&[GRAPH_BINARY_VERSION])?;
         writer.write_all(&bytes)?;
         writer.flush()
     }
@@ -160,7 +422,17 @@ impl GraphFile {
     /// Read graph from postcard binary
     pub(crate) fn read_binary(path: impl AsRef<Path>) -> Result<Self, io::Error> {
         let bytes = std::fs::read(path)?;
-        postcard::from_bytes(&bytes)
+        let header_len = GRAPH_BINARY_MAGIC.len() + 1;
+        if bytes.len() < header_len
+            || &bytes[..GRAPH_BINARY_MAGIC.len()] != GRAPH_BINARY_MAGIC
+            || bytes[GRAPH_BINARY_MAGIC.len()] != GRAPH_BINARY_VERSION
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("outdated graph binary format (expected v{GRAPH_BINARY_VERSION}), regenerate it"),
+            ));
+        }
+        postcard::from_bytes(&bytes[header_len..])
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 
@@ -398,6 +670,11 @@ struct TileEdge {
     surface: SurfaceType,
     length_m: f64,
     waypoints: Vec<Coordinate>,
+    /// Already remapped to the merged route table
+    routes: Vec<u32>,
+    /// `name` already remapped to the merged names
+    attributes: WayAttributes,
+    environment: EdgeEnvironment,
 }
 
 /// Pre-filtered PBF data stored in memory for fast processing
@@ -406,6 +683,47 @@ struct FilteredPbfData {
     nodes: NodeCoordMap,
     /// All highway ways touching the bbox: (way_id, node_refs, tags)
     ways: Vec<OsmWay>,
+    /// Tagged POI nodes inside the bbox
+    pois: Vec<Poi>,
+    /// Waymarked route relations with at least one member way in `ways`
+    routes: Vec<WaymarkedRoute>,
+    /// way_id -> indices into `routes`
+    way_routes: HashMap<i64, Vec<u32>>,
+    /// Major roads and railways with at least one node in the bbox
+    noise_ways: Vec<NoiseWay>,
+    /// Forests, rivers and lakes with at least one node in the bbox
+    land_cover: Vec<LandCoverWay>,
+    /// node_id -> `kerb=*` of kerb nodes in the bbox
+    kerbs: HashMap<i64, Kerb>,
+}
+
+/// Elements of interest found in part of a PBF file; chunks are merged
+/// across the parallel reduction.
+#[derive(Default)]
+struct PbfChunk {
+    nodes: Vec<NodeEntry>,
+    ways: Vec<OsmWay>,
+    pois: Vec<Poi>,
+    relations: Vec<RouteRelation>,
+    /// Forest multipolygons and the ids of their outer member ways
+    forest_relations: Vec<LandCoverRelation>,
+    noise_ways: Vec<NoiseWay>,
+    land_cover: Vec<LandCoverWay>,
+    kerbs: Vec<(i64, Kerb)>,
+}
+
+impl PbfChunk {
+    fn merge(mut self, other: Self) -> Self {
+        self.nodes.extend(other.nodes);
+        self.ways.extend(other.ways);
+        self.pois.extend(other.pois);
+        self.relations.extend(other.relations);
+        self.forest_relations.extend(other.forest_relations);
+        self.noise_ways.extend(other.noise_ways);
+        self.land_cover.extend(other.land_cover);
+        self.kerbs.extend(other.kerbs);
+        self
+    }
 }
 
 impl GraphBuilder {
@@ -434,9 +752,14 @@ impl GraphBuilder {
         // Second pass: collect edges
         let edges = self.collect_edges(path, &node_state)?;
 
+        // POIs, route relations and way names are only collected by the
+        // bbox-filtered single-pass pipeline
         Ok(GraphFile {
             nodes: node_state.nodes,
             edges,
+            pois: Vec::new(),
+            routes: Vec::new(),
+            names: Vec::new(),
         })
     }
 
@@ -453,9 +776,10 @@ impl GraphBuilder {
         let filtered_data = self.filter_pbf_to_memory(path, bbox)?;
 
         tracing::info!(
-            "Filtered data: {} nodes, {} ways (in-memory)",
+            "Filtered data: {} nodes, {} ways, {} POIs (in-memory)",
             filtered_data.nodes.len(),
-            filtered_data.ways.len()
+            filtered_data.ways.len(),
+            filtered_data.pois.len()
         );
 
         // PASS 2: Build graph from in-memory data (no file I/O)
@@ -494,6 +818,10 @@ impl GraphBuilder {
         let mut global_nodes: Vec<NodeRecord> = Vec::new();
 
         let mut all_tile_edges: Vec<TileEdge> = Vec::new();
+        let mut pois: Vec<Poi> = Vec::new();
+        let mut routes: Vec<WaymarkedRoute> = Vec::new();
+        let mut route_ids: HashMap<i64, u32> = HashMap::new();
+        let mut names = NameTable::default();
 
         // Map from (tile_idx, local_node_id) to global_node_id
         let mut local_to_global: HashMap<(usize, u64), u64> = HashMap::new();
@@ -516,7 +844,9 @@ impl GraphBuilder {
                     tile_idx, &tile_graph,
                     &mut coord_to_global_id, &mut global_nodes,
                     &mut local_to_global, &mut all_tile_edges,
+                    &mut routes, &mut route_ids, &mut names,
                 );
+                pois.extend(tile_graph.pois);
                 continue;
             }
 
@@ -550,7 +880,9 @@ impl GraphBuilder {
                 tile_idx, &tile_graph,
                 &mut coord_to_global_id, &mut global_nodes,
                 &mut local_to_global, &mut all_tile_edges,
+                &mut routes, &mut route_ids, &mut names,
             );
+            pois.extend(tile_graph.pois);
         }
 
         // Remap edges using the local-to-global mapping
@@ -573,6 +905,9 @@ impl GraphBuilder {
                         surface: edge.surface,
                         length_m: edge.length_m,
                         waypoints: edge.waypoints,
+                        routes: edge.routes,
+                        attributes: edge.attributes,
+                        environment: edge.environment,
                     });
                 }
                 _ => {
@@ -642,6 +977,9 @@ impl GraphBuilder {
                     surface: e.surface,
                     length_m: e.length_m,
                     waypoints: e.waypoints,
+                    routes: e.routes,
+                    attributes: e.attributes,
+                    environment: e.environment,
                 })
             })
             .collect();
@@ -654,13 +992,22 @@ impl GraphBuilder {
             final_edges.len()
         );
 
+        // Tiles are cut on node positions, so a POI belongs to exactly one tile;
+        // only the bbox filter is needed here.
+        pois.retain(|poi| bbox.contains(poi.coordinate()));
+
         Ok(GraphFile {
             nodes: final_nodes,
             edges: final_edges,
+            pois,
+            routes,
+            names: names.names,
         })
     }
 
-    /// Merge a tile's nodes and edges into the global collections, deduplicating by coordinates.
+    /// Merge a tile's nodes and edges into the global collections, deduplicating
+    /// nodes by coordinates, route relations by OSM id and way names.
+    #[allow(clippy::too_many_arguments)]
     fn merge_tile_into(
         tile_idx: usize,
         tile_graph: &GraphFile,
@@ -668,6 +1015,9 @@ impl GraphBuilder {
         global_nodes: &mut Vec<NodeRecord>,
         local_to_global: &mut HashMap<(usize, u64), u64>,
         all_tile_edges: &mut Vec<TileEdge>,
+        routes: &mut Vec<WaymarkedRoute>,
+        route_ids: &mut HashMap<i64, u32>,
+        names: &mut NameTable,
     ) {
         fn coord_key(lat: f64, lon: f64) -> (i64, i64) {
             ((lat * 1_000_000.0).round() as i64, (lon * 1_000_000.0).round() as i64)
@@ -689,7 +1039,21 @@ impl GraphBuilder {
             local_to_global.insert((tile_idx, node.id), global_id);
         }
 
+        let tile_route_ids: Vec<u32> = tile_graph
+            .routes
+            .iter()
+            .map(|route| {
+                *route_ids.entry(route.osm_id).or_insert_with(|| {
+                    routes.push(route.clone());
+                    (routes.len() - 1) as u32
+                })
+            })
+            .collect();
+        let tile_name_ids: Vec<u32> = tile_graph.names.iter().map(|name| names.intern(name)).collect();
+
         for edge in &tile_graph.edges {
+            let mut attributes = edge.attributes.clone();
+            attributes.name = attributes.name.and_then(|local| tile_name_ids.get(local as usize).copied());
             all_tile_edges.push(TileEdge {
                 tile_idx,
                 local_from: edge.from,
@@ -697,6 +1061,13 @@ impl GraphBuilder {
                 surface: edge.surface,
                 length_m: edge.length_m,
                 waypoints: edge.waypoints.clone(),
+                routes: edge
+                    .routes
+                    .iter()
+                    .filter_map(|&local| tile_route_ids.get(local as usize).copied())
+                    .collect(),
+                attributes,
+                environment: edge.environment.clone(),
             });
         }
     }
@@ -760,14 +1131,18 @@ impl GraphBuilder {
 
         if let Some(path) = disk_cache_path {
             tracing::debug!("Disk cache hit for bbox {:?}: {}", bbox, path.display());
-            let graph = GraphFile::read_from_path(path).map_err(GraphBuildError::Io)?;
+            match GraphFile::read_from_path(path) {
+                Ok(graph) => {
+                    // Populate LRU cache
+                    if let Ok(mut cache) = GRAPH_CACHE.write() {
+                        cache.put(cache_key.clone(), graph.clone());
+                    }
 
-            // Populate LRU cache
-            if let Ok(mut cache) = GRAPH_CACHE.write() {
-                cache.put(cache_key.clone(), graph.clone());
+                    return Ok(graph);
+                }
+                // Stale or corrupt cache entries are rebuilt below and overwritten
+                Err(e) => tracing::warn!("Ignoring unreadable cache {}: {}", path.display(), e),
             }
-
-            return Ok(graph);
         }
 
         tracing::info!("Cache miss, generating partial graph for bbox {:?}", bbox);
@@ -796,7 +1171,8 @@ impl GraphBuilder {
         Ok(graph)
     }
 
-    /// PASS 1: Single-pass filtering - collect all relevant nodes and ways in memory
+    /// PASS 1: Single-pass filtering - collect all relevant nodes, ways, POIs and
+    /// waymarked route relations in memory
     ///
     /// Optimized: uses Option instead of HashMap/Vec per element to avoid
     /// millions of empty collection allocations during par_map_reduce.
@@ -807,69 +1183,110 @@ impl GraphBuilder {
     ) -> Result<FilteredPbfData, GraphBuildError> {
         use std::collections::HashSet;
 
-        type NodeEntry = (i64, (f64, f64, Option<f64>));
-
         let reader = ElementReader::from_path(path)?;
+        let in_bbox = |lat: f64, lon: f64| {
+            lat >= bbox.min_lat && lat <= bbox.max_lat && lon >= bbox.min_lon && lon <= bbox.max_lon
+        };
 
-        // Collect as Vec of Options — avoids 13M empty HashMap/Vec allocations
-        let (node_entries, ways_data): (Vec<NodeEntry>, Vec<OsmWay>) = reader.par_map_reduce(
-            |element| -> (Vec<NodeEntry>, Vec<OsmWay>) {
+        // Empty chunks allocate nothing — avoids 13M empty HashMap/Vec allocations
+        let chunk = reader.par_map_reduce(
+            |element| -> PbfChunk {
                 match element {
                     Element::Node(node) => {
                         let lat = node.lat();
                         let lon = node.lon();
 
-                        if lat >= bbox.min_lat
-                            && lat <= bbox.max_lat
-                            && lon >= bbox.min_lon
-                            && lon <= bbox.max_lon
-                        {
+                        if in_bbox(lat, lon) {
                             // Inline elevation extraction — avoids tags().collect() allocation
                             let elevation = node.tags()
                                 .find_map(|(k, v)| if k == "ele" { v.parse::<f64>().ok() } else { None });
-                            (vec![(node.id(), (lat, lon, elevation))], Vec::new())
+                            PbfChunk {
+                                nodes: vec![(node.id(), (lat, lon, elevation))],
+                                pois: Poi::from_tags(lat, lon, node.tags()).into_iter().collect(),
+                                kerbs: kerb_from_tags(node.tags()).map(|kerb| (node.id(), kerb)).into_iter().collect(),
+                                ..PbfChunk::default()
+                            }
                         } else {
-                            (Vec::new(), Vec::new())
+                            PbfChunk::default()
                         }
                     }
                     Element::DenseNode(node) => {
                         let lat = node.lat();
                         let lon = node.lon();
 
-                        if lat >= bbox.min_lat
-                            && lat <= bbox.max_lat
-                            && lon >= bbox.min_lon
-                            && lon <= bbox.max_lon
-                        {
+                        if in_bbox(lat, lon) {
                             let elevation = node.tags()
                                 .find_map(|(k, v)| if k == "ele" { v.parse::<f64>().ok() } else { None });
-                            (vec![(node.id(), (lat, lon, elevation))], Vec::new())
+                            PbfChunk {
+                                nodes: vec![(node.id(), (lat, lon, elevation))],
+                                pois: Poi::from_tags(lat, lon, node.tags()).into_iter().collect(),
+                                kerbs: kerb_from_tags(node.tags()).map(|kerb| (node.id(), kerb)).into_iter().collect(),
+                                ..PbfChunk::default()
+                            }
                         } else {
-                            (Vec::new(), Vec::new())
+                            PbfChunk::default()
                         }
                     }
                     Element::Way(way) => {
+                        let mut chunk = PbfChunk::default();
                         // Quick highway check without collecting all tags (avoids millions of Vec allocations)
                         if way.tags().any(|(k, v)| k == "highway" && is_supported_highway(v)) {
                             let node_refs: Vec<i64> = way.refs().collect();
                             let tag_pairs: Vec<(String, String)> =
                                 way.tags().map(|(k, v)| (k.to_string(), v.to_string())).collect();
-
-                            (Vec::new(), vec![(way.id(), node_refs, tag_pairs)])
+                            chunk.ways.push((way.id(), node_refs, tag_pairs));
+                        }
+                        if let Some(source) = way.tags().find_map(|(k, v)| NoiseSource::from_tag(k, v)) {
+                            chunk.noise_ways.push((source, way.refs().collect()));
+                        }
+                        if let Some(cover) = way.tags().find_map(|(k, v)| LandCover::from_tag(k, v)) {
+                            chunk.land_cover.push((cover, way.refs().collect()));
+                        }
+                        chunk
+                    }
+                    Element::Relation(relation) => {
+                        if let Some(route) = WaymarkedRoute::from_tags(relation.id(), relation.tags()) {
+                            let way_ids = relation
+                                .members()
+                                .filter(|m| m.member_type == RelMemberType::Way)
+                                .map(|m| m.member_id)
+                                .collect();
+                            PbfChunk {
+                                relations: vec![(route, way_ids)],
+                                ..PbfChunk::default()
+                            }
+                        } else if let Some(cover) = LandCover::from_relation_tags(relation.tags()) {
+                            // Inner rings (clearings) are ignored
+                            let outer_ids = relation
+                                .members()
+                                .filter(|m| m.member_type == RelMemberType::Way)
+                                .filter(|m| matches!(m.role(), Ok("outer" | "")))
+                                .map(|m| m.member_id)
+                                .collect();
+                            PbfChunk {
+                                forest_relations: vec![(cover, outer_ids)],
+                                ..PbfChunk::default()
+                            }
                         } else {
-                            (Vec::new(), Vec::new())
+                            PbfChunk::default()
                         }
                     }
-                    _ => (Vec::new(), Vec::new()),
                 }
             },
-            || (Vec::new(), Vec::new()),
-            |(mut nodes1, mut ways1), (nodes2, ways2)| {
-                nodes1.extend(nodes2);
-                ways1.extend(ways2);
-                (nodes1, ways1)
-            },
+            PbfChunk::default,
+            PbfChunk::merge,
         )?;
+        let PbfChunk {
+            nodes: node_entries,
+            ways: ways_data,
+            pois,
+            relations,
+            forest_relations,
+            noise_ways,
+            land_cover,
+            kerbs,
+        } = chunk;
+        let kerbs: HashMap<i64, Kerb> = kerbs.into_iter().collect();
 
         // Build HashMap once from collected entries
         let nodes_in_bbox: NodeCoordMap = node_entries.into_iter().collect();
@@ -896,10 +1313,25 @@ impl GraphBuilder {
             }
         );
 
+        let (routes, way_routes) = index_route_relations(relations, &ways_data);
+        let noise_ways: Vec<NoiseWay> = noise_ways
+            .into_iter()
+            .filter(|(_, refs)| refs.iter().any(|id| bbox_node_ids.contains(id)))
+            .collect();
+        let mut land_cover = land_cover;
+        land_cover.extend(Self::forest_relation_rings(path, forest_relations)?);
+        // A forest enclosing the whole bbox without a node inside it is missed
+        let land_cover: Vec<LandCoverWay> = land_cover
+            .into_iter()
+            .filter(|(_, refs)| refs.iter().any(|id| bbox_node_ids.contains(id)))
+            .collect();
+
         // Compute missing nodes only from bbox-relevant ways
         let way_node_refs: HashSet<i64> = ways_data
             .iter()
             .flat_map(|(_, refs, _)| refs.iter())
+            .chain(noise_ways.iter().flat_map(|(_, refs)| refs.iter()))
+            .chain(land_cover.iter().flat_map(|(_, refs)| refs.iter()))
             .copied()
             .collect();
 
@@ -912,6 +1344,12 @@ impl GraphBuilder {
             return Ok(FilteredPbfData {
                 nodes: nodes_in_bbox,
                 ways: ways_data,
+                pois,
+                routes,
+                way_routes,
+                noise_ways,
+                land_cover,
+                kerbs,
             });
         }
 
@@ -961,9 +1399,58 @@ impl GraphBuilder {
         Ok(FilteredPbfData {
             nodes: all_nodes,
             ways: ways_data,
+            pois,
+            routes,
+            way_routes,
+            noise_ways,
+            land_cover,
+            kerbs,
         })
     }
 
+    /// Outer rings of forest multipolygons. Their member ways are usually
+    /// untagged, so they are read in a pass of their own once the relations
+    /// are known.
+    fn forest_relation_rings(
+        path: &Path,
+        relations: Vec<LandCoverRelation>,
+    ) -> Result<Vec<LandCoverWay>, GraphBuildError> {
+        if relations.is_empty() {
+            return Ok(Vec::new());
+        }
+        let member_ids: std::collections::HashSet<i64> =
+            relations.iter().flat_map(|(_, ids)| ids.iter().copied()).collect();
+        tracing::info!(
+            "Collecting {} member ways of {} forest relations",
+            member_ids.len(),
+            relations.len()
+        );
+
+        let reader = ElementReader::from_path(path)?;
+        let member_ways: HashMap<i64, NodeIds> = reader.par_map_reduce(
+            |element| match element {
+                Element::Way(way) if member_ids.contains(&way.id()) => {
+                    HashMap::from([(way.id(), way.refs().collect())])
+                }
+                _ => HashMap::new(),
+            },
+            HashMap::new,
+            |mut acc, ways| {
+                acc.extend(ways);
+                acc
+            },
+        )?;
+
+        Ok(relations
+            .into_iter()
+            .flat_map(|(cover, ids)| {
+                let ways: Vec<NodeIds> =
+                    ids.iter().filter_map(|id| member_ways.get(id).cloned()).collect();
+                assemble_rings(&ways).into_iter().map(move |ring| (cover, ring))
+            })
+            .collect())
+    }
+
     /// PASS 2: Build graph from pre-filtered in-memory data
     fn build_from_filtered_data(
         &self,
@@ -973,6 +1460,10 @@ impl GraphBuilder {
         // Build node collection state
         let mut node_state = NodeCollectionState::new();
 
+        // Indexed before the node map is consumed below
+        let noise_index = NoiseIndex::from_ways(&data.noise_ways, &data.nodes);
+        let scenery_index = SceneryIndex::from_data(&data.land_cover, &data.nodes, &data.pois);
+
         // Sort nodes by osm_id for deterministic ordering
         let mut sorted_nodes: Vec<_> = data.nodes.into_iter().collect();
         sorted_nodes.sort_by_key(|(osm_id, _)| *osm_id);
@@ -1008,13 +1499,17 @@ impl GraphBuilder {
         // For each way, split it into segments between intersections
         // Each edge stores the intermediate waypoints for precise geometry
         let ways_count = data.ways.len();
+        let mut names = NameTable::default();
 
-        for (_, node_refs, tags) in &data.ways {
+        for (way_id, node_refs, tags) in &data.ways {
             if node_refs.len() < 2 {
                 continue;
             }
 
             let surface = infer_surface(tags);
+            let mut attributes = WayAttributes::from_tags(tags);
+            attributes.name = tags.iter().find(|(k, _)| k == "name").map(|(_, name)| names.intern(name));
+            let way_routes = data.way_routes.get(way_id).map(Vec::as_slice).unwrap_or_default();
 
             // Find intersection indices in this way
             let mut segment_start = 0;
@@ -1025,12 +1520,14 @@ impl GraphBuilder {
                     // Create edge from segment_start to i (inclusive)
                     let segment = &node_refs[segment_start..=i];
 
-                    if let Some(edge) = build_edge_with_waypoints(
+                    if let Some(mut edge) = build_edge_with_waypoints(
                         segment,
                         surface,
                         &node_state.osm_to_graph_id,
                         &node_state.coords,
                     ) {
+                        edge.routes = way_routes.to_vec();
+                        edge.attributes = segment_attributes(&attributes, segment, &data.kerbs);
                         edges.push(edge);
                     }
 
@@ -1043,12 +1540,14 @@ impl GraphBuilder {
             if segment_start < node_refs.len() - 1 {
                 let segment = &node_refs[segment_start..];
 
-                if let Some(edge) = build_edge_with_waypoints(
+                if let Some(mut edge) = build_edge_with_waypoints(
                     segment,
                     surface,
                     &node_state.osm_to_graph_id,
                     &node_state.coords,
                 ) {
+                    edge.routes = way_routes.to_vec();
+                    edge.attributes = segment_attributes(&attributes, segment, &data.kerbs);
                     edges.push(edge);
                 }
             }
@@ -1097,7 +1596,7 @@ impl GraphBuilder {
         }
 
         // Remap edge node IDs
-        let remapped_edges: Vec<EdgeRecord> = edges
+        let mut remapped_edges: Vec<EdgeRecord> = edges
             .into_iter()
             .filter_map(|e| {
                 let new_from = old_to_new_id.get(&e.from)?;
@@ -1108,6 +1607,9 @@ impl GraphBuilder {
                     length_m: e.length_m,
                     surface: e.surface,
                     waypoints: e.waypoints,
+                    routes: e.routes,
+                    attributes: e.attributes,
+                    environment: e.environment,
                 })
             })
             .collect();
@@ -1119,9 +1621,24 @@ impl GraphBuilder {
             (1.0 - filtered_nodes.len() as f64 / original_node_count as f64) * 100.0
         );
 
+        noise_index.annotate(&mut remapped_edges, &filtered_nodes);
+        scenery_index.annotate(&mut remapped_edges, &filtered_nodes);
+        if let Some(dem) = crate::elevation::local_dem_grid() {
+            annotate_grades(&mut remapped_edges, &filtered_nodes, dem);
+        }
+
+        tracing::info!(
+            "Waymarked routes: {} relations, {} edges on a marked route",
+            data.routes.len(),
+            remapped_edges.iter().filter(|e| !e.routes.is_empty()).count()
+        );
+
         Ok(GraphFile {
             nodes: filtered_nodes,
             edges: remapped_edges,
+            pois: data.pois,
+            routes: data.routes,
+            names: names.names,
         })
     }
 
@@ -1354,10 +1871,13 @@ fn process_way_element(
     // Collect node references
     let node_refs: Vec<i64> = way.refs().collect();
 
+    let attributes = WayAttributes::from_tags(&tags);
+
     // Create edges for consecutive node pairs
     node_refs
         .windows(2)
         .filter_map(|pair| create_edge_record(pair[0], pair[1], surface, coords, osm_to_graph))
+        .map(|edge| EdgeRecord { attributes: attributes.clone(), ..edge })
         .collect()
 }
 
@@ -1383,6 +1903,9 @@ fn create_edge_record(
         surface,
         length_m: length_km * 1000.0,
         waypoints: Vec::new(), // No intermediate waypoints for now
+        routes: Vec::new(),
+        attributes: WayAttributes::default(),
+        environment: EdgeEnvironment::default(),
     })
 }
 
@@ -1396,28 +1919,7 @@ fn has_supported_highway(tags: &[(String, String)]) -> bool {
 
 // Pure function to check if highway value is supported
 fn is_supported_highway(highway_value: &str) -> bool {
-    matches!(
-        highway_value,
-        "path"
-            | "footway"
-            | "pedestrian"
-            | "cycleway"
-            | "bridleway"
-            | "steps"
-            | "living_street"
-            | "secondary"
-            | "secondary_link"
-            | "tertiary"
-            | "tertiary_link"
-            | "residential"
-            | "track"
-            | "service"
-            | "unclassified"
-            | "primary"
-            | "primary_link"
-            | "trunk"
-            | "trunk_link"
-    )
+    Highway::from_tag(highway_value).is_some()
 }
 
 // Pure function to infer surface type from tags
@@ -1559,9 +2061,52 @@ fn build_edge_with_waypoints(
         surface,
         length_m,
         waypoints,
+        routes: Vec::new(),
+        attributes: WayAttributes::default(),
+        environment: EdgeEnvironment::default(),
     })
 }
 
+/// Attributes of the edge covering `segment` of a way: the way's, plus the
+/// kerbs on the segment's nodes.
+fn segment_attributes(way: &WayAttributes, segment: &[i64], kerbs: &HashMap<i64, Kerb>) -> WayAttributes {
+    let mut attributes = way.clone();
+    for &kerb in segment.iter().filter_map(|id| kerbs.get(id)) {
+        attributes.kerb = attributes.kerb.max(Some(kerb));
+    }
+    attributes
+}
+
+/// Keep the route relations that have a member way among `ways`, and index
+/// them by way id. Relations are sorted by OSM id so that route indices do
+/// not depend on the order of the parallel PBF reduction.
+fn index_route_relations(
+    mut relations: Vec<RouteRelation>,
+    ways: &[OsmWay],
+) -> (Vec<WaymarkedRoute>, HashMap<i64, Vec<u32>>) {
+    let way_ids: std::collections::HashSet<i64> = ways.iter().map(|(id, _, _)| *id).collect();
+    relations.sort_by_key(|(route, _)| route.osm_id);
+    relations.dedup_by_key(|(route, _)| route.osm_id);
+
+    let mut routes = Vec::new();
+    let mut way_routes: HashMap<i64, Vec<u32>> = HashMap::new();
+    for (route, members) in relations {
+        let members: Vec<i64> = members.into_iter().filter(|id| way_ids.contains(id)).collect();
+        if members.is_empty() {
+            continue;
+        }
+        let index = routes.len() as u32;
+        for way_id in members {
+            let entry = way_routes.entry(way_id).or_default();
+            if !entry.contains(&index) {
+                entry.push(index);
+            }
+        }
+        routes.push(route);
+    }
+    (routes, way_routes)
+}
+
 /// Distance threshold for merging close nodes (meters).
 const NODE_MERGE_THRESHOLD_M: f64 = 10.0;
 
@@ -1603,7 +2148,7 @@ impl UnionFind {
 /// deduplicates parallel edges (keeping the shortest).
 fn merge_close_nodes(edges: Vec<EdgeRecord>, node_state: &NodeCollectionState) -> Vec<EdgeRecord> {
     // Build grid index: cell -> list of (graph_id, lat, lon)
-    let mut grid: HashMap<(i64, i64), Vec<(u64, f64, f64)>> = HashMap::new();
+    let mut grid: NodeGrid = HashMap::new();
     for node in &node_state.nodes {
         let cell = (
             (node.lat * 10_000.0).round() as i64,
@@ -1705,6 +2250,46 @@ fn merge_close_nodes(edges: Vec<EdgeRecord>, node_state: &NodeCollectionState) -
     result
 }
 
+/// Small graphs for unit tests. Tests override fields of the defaults with
+/// struct update syntax, e.g. `EdgeRecord { surface: SurfaceType::Paved, ..edge(1, 2, 800.0) }`.
+#[cfg(test)]
+pub(crate) mod test_graph {
+    use super::{EdgeRecord, GraphFile, NodeRecord};
+    use crate::models::SurfaceType;
+
+    /// A node without elevation nor population.
+    pub(crate) fn node(id: u64, lat: f64, lon: f64) -> NodeRecord {
+        NodeRecord { id, lat, lon, elevation: None, population_density: 0.0 }
+    }
+
+    /// A dirt way straight between two nodes, without routes nor tags.
+    pub(crate) fn edge(from: u64, to: u64, length_m: f64) -> EdgeRecord {
+        EdgeRecord {
+            from,
+            to,
+            surface: SurfaceType::Dirt,
+            length_m,
+            waypoints: vec![],
+            routes: vec![],
+            attributes: Default::default(),
+            environment: Default::default(),
+        }
+    }
+
+    /// A graph of these nodes and edges only.
+    pub(crate) fn graph(nodes: Vec<NodeRecord>, edges: Vec<EdgeRecord>) -> GraphFile {
+        GraphFile { nodes, edges, pois: vec![], routes: vec![], names: vec![] }
+    }
+
+    /// 1 → 2 straight (800 m, `edges[0]`) or 1 → 3 → 2 (1000 m).
+    pub(crate) fn detour_test_graph() -> GraphFile {
+        graph(
+            vec![node(1, 45.0, 5.0), node(2, 45.0, 5.01), node(3, 45.003, 5.005)],
+            vec![edge(1, 2, 800.0), edge(1, 3, 500.0), edge(3, 2, 500.0)],
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1882,4 +2467,115 @@ mod tests {
 
         assert!(edge.is_none());
     }
+
+    #[test]
+    fn test_binary_roundtrip_keeps_pois_and_rejects_headerless_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.bin");
+        let graph = GraphFile {
+            pois: vec![Poi { lat: 45.0, lon: 5.0, poi_type: "water".to_string(), name: None }],
+            names: vec!["Chemin du Lac".to_string()],
+            ..test_graph::graph(vec![test_graph::node(1, 45.0, 5.0)], vec![])
+        };
+
+        graph.write_binary(&path).unwrap();
+        let loaded = GraphFile::read_binary(&path).unwrap();
+        assert_eq!(loaded.nodes.len(), 1);
+        assert_eq!(loaded.pois[0].poi_type, "water");
+        assert_eq!(loaded.names, ["Chemin du Lac"]);
+
+        // Files written before the header existed are raw postcard payloads
+        std::fs::write(&path, postcard::to_allocvec(&graph).unwrap()).unwrap();
+        let err = GraphFile::read_binary(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_index_route_relations_keeps_routes_touching_kept_ways() {
+        let route = |osm_id| WaymarkedRoute::from_tags(osm_id, [("route", "hiking")]).unwrap();
+        let ways: Vec<OsmWay> = vec![(10, vec![1, 2], vec![]), (11, vec![2, 3], vec![])];
+        let relations = vec![
+            (route(300), vec![11, 99]),
+            (route(200), vec![10, 11]),
+            (route(100), vec![99]), // outside the bbox
+            (route(200), vec![10, 11]),
+        ];
+
+        let (routes, way_routes) = index_route_relations(relations, &ways);
+        assert_eq!(routes.iter().map(|r| r.osm_id).collect::<Vec<_>>(), vec![200, 300]);
+        assert_eq!(way_routes[&10], vec![0]);
+        assert_eq!(way_routes[&11], vec![0, 1]);
+        assert!(!way_routes.contains_key(&99));
+    }
+
+    #[test]
+    fn test_way_attributes_read_lit_tag() {
+        let tags = |lit: &str| vec![("highway".to_string(), "path".to_string()), ("lit".to_string(), lit.to_string())];
+        assert_eq!(WayAttributes::from_tags(&tags("yes")).lit, Some(true));
+        assert_eq!(WayAttributes::from_tags(&tags("24/7")).lit, Some(true));
+        assert_eq!(WayAttributes::from_tags(&tags("no")).lit, Some(false));
+        assert_eq!(WayAttributes::from_tags(&tags("maybe")).lit, None);
+        assert_eq!(WayAttributes::from_tags(&[]).lit, None);
+    }
+
+    #[test]
+    fn test_way_attributes_keep_closed_tag_sets_and_hardest_kerb() {
+        let tags: Vec<(String, String)> = [
+            ("highway", "living_street"),
+            ("smoothness", "very_bad"),
+            ("kerb", "lowered"),
+            ("trail_visibility", "bad"),
+            ("name", "Rue Haute"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let attributes = WayAttributes::from_tags(&tags);
+        assert_eq!(attributes.highway, Some(Highway::LivingStreet));
+        assert_eq!(attributes.smoothness, Some(Smoothness::VeryBad));
+        assert_eq!(attributes.trail_visibility, Some(TrailVisibility::Bad));
+        assert_eq!(attributes.name, None, "interned by the builder");
+        assert_eq!(Highway::from_tag("motorway"), None);
+        assert_eq!(Highway::LivingStreet.as_str(), "living_street");
+
+        let kerbs = HashMap::from([(2, Kerb::Raised), (3, Kerb::Flush)]);
+        assert_eq!(segment_attributes(&attributes, &[1, 2, 3], &kerbs).kerb, Some(Kerb::Raised));
+        assert_eq!(segment_attributes(&attributes, &[1, 3], &kerbs).kerb, Some(Kerb::Lowered));
+    }
+
+    #[test]
+    fn test_merge_tile_into_shares_way_names_across_tiles() {
+        use test_graph::{edge, node};
+
+        let tile = |names: &[&str], name| GraphFile {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            ..test_graph::graph(
+                vec![node(1, 45.0, 5.0), node(2, 45.0, 5.01)],
+                vec![EdgeRecord {
+                    surface: SurfaceType::Paved,
+                    attributes: WayAttributes { name: Some(name), ..Default::default() },
+                    ..edge(1, 2, 788.0)
+                }],
+            )
+        };
+        let mut coord_to_global_id = HashMap::new();
+        let mut global_nodes = Vec::new();
+        let mut local_to_global = HashMap::new();
+        let mut edges = Vec::new();
+        let mut routes = Vec::new();
+        let mut route_ids = HashMap::new();
+        let mut names = NameTable::default();
+        for (tile_idx, tile) in [tile(&["Rue Haute"], 0), tile(&["Rue Basse", "Rue Haute"], 1)].iter().enumerate() {
+            GraphBuilder::merge_tile_into(
+                tile_idx, tile,
+                &mut coord_to_global_id, &mut global_nodes,
+                &mut local_to_global, &mut edges,
+                &mut routes, &mut route_ids, &mut names,
+            );
+        }
+
+        assert_eq!(names.names, ["Rue Haute", "Rue Basse"]);
+        assert_eq!(edges[0].attributes.name, Some(0));
+        assert_eq!(edges[1].attributes.name, Some(0));
+    }
 }
@@ -0,0 +1,200 @@
+//! Turn-by-turn instructions.
+//!
+//! A route is described by maneuvers where it changes way (another name or,
+//! on unnamed ways, another kind of way) or turns at a junction, phrased in
+//! French or English: "Tournez à gauche sur Chemin de la Croix après
+//! 1,2 km", "Turn left onto Chemin de la Croix after 1.2 km".
+
+use crate::{
+    graph::Highway,
+    models::{Language, ManeuverType},
+};
+
+/// Smaller heading changes go straight on
+const STRAIGHT_DEG: f64 = 20.0;
+const SLIGHT_DEG: f64 = 45.0;
+const SHARP_DEG: f64 = 135.0;
+const U_TURN_DEG: f64 = 170.0;
+
+/// The way a maneuver leads onto, from the edge tags.
+#[derive(Debug, Clone, Default)]
+pub struct Way {
+    pub name: Option<String>,
+    pub highway: Option<Highway>,
+}
+
+impl Way {
+    /// Whether going from `self` to `other` is worth an instruction:
+    /// named ways differ by name, unnamed ones by kind.
+    pub fn differs_from(&self, other: &Way) -> bool {
+        match (&self.name, &other.name) {
+            (Some(a), Some(b)) => a != b,
+            (None, None) => kind(self.highway, Language::En) != kind(other.highway, Language::En),
+            _ => true,
+        }
+    }
+
+    /// Name of the way, or its kind when unnamed.
+    pub fn label(&self, language: Language) -> Option<String> {
+        self.name
+            .clone()
+            .or_else(|| kind(self.highway, language).map(|(_, bare)| bare.to_string()))
+    }
+
+    /// The way as the object of "onto" / "sur".
+    fn object(&self, language: Language) -> Option<String> {
+        self.name
+            .clone()
+            .or_else(|| kind(self.highway, language).map(|(article, _)| article.to_string()))
+    }
+}
+
+/// Kind of way for a `highway=*` value, with and without its article.
+fn kind(highway: Option<Highway>, language: Language) -> Option<(&'static str, &'static str)> {
+    let kind = match (highway?, language) {
+        (Highway::Path | Highway::Footway | Highway::Bridleway, Language::Fr) => ("le sentier", "sentier"),
+        (Highway::Path | Highway::Footway | Highway::Bridleway, Language::En) => ("the path", "path"),
+        (Highway::Track, Language::Fr) => ("la piste", "piste"),
+        (Highway::Track, Language::En) => ("the track", "track"),
+        (Highway::Steps, Language::Fr) => ("les escaliers", "escaliers"),
+        (Highway::Steps, Language::En) => ("the steps", "steps"),
+        (Highway::Cycleway, Language::Fr) => ("la piste cyclable", "piste cyclable"),
+        (Highway::Cycleway, Language::En) => ("the cycleway", "cycleway"),
+        (_, Language::Fr) => ("la route", "route"),
+        (_, Language::En) => ("the road", "road"),
+    };
+    Some(kind)
+}
+
+/// Heading change from `in_bearing` to `out_bearing`, in degrees from -180
+/// to 180, positive to the right.
+pub fn bearing_change(in_bearing: f64, out_bearing: f64) -> f64 {
+    let change = (out_bearing - in_bearing).rem_euclid(360.0);
+    if change > 180.0 {
+        change - 360.0
+    } else {
+        change
+    }
+}
+
+/// Maneuver for a heading change at a junction.
+pub fn classify(bearing_change: f64) -> ManeuverType {
+    let angle = bearing_change.abs();
+    let right = bearing_change > 0.0;
+    match angle {
+        a if a < STRAIGHT_DEG => ManeuverType::Continue,
+        a if a < SLIGHT_DEG => if right { ManeuverType::SlightRight } else { ManeuverType::SlightLeft },
+        a if a < SHARP_DEG => if right { ManeuverType::Right } else { ManeuverType::Left },
+        a if a < U_TURN_DEG => if right { ManeuverType::SharpRight } else { ManeuverType::SharpLeft },
+        _ => ManeuverType::UTurn,
+    }
+}
+
+/// The instruction for a maneuver onto `way`, `after_km` from the previous
+/// one.
+pub fn phrase(kind: ManeuverType, way: Option<&Way>, after_km: f64, language: Language) -> String {
+    let object = way.and_then(|way| way.object(language));
+    let after = format_distance(after_km, language);
+    match language {
+        Language::Fr => {
+            let verb = match kind {
+                ManeuverType::Depart => {
+                    return object.map_or("Partez".to_string(), |object| format!("Partez sur {object}"))
+                }
+                ManeuverType::Arrive => return format!("Arrivée à destination après {after}"),
+                ManeuverType::Continue => "Continuez",
+                ManeuverType::SlightLeft => "Serrez à gauche",
+                ManeuverType::Left => "Tournez à gauche",
+                ManeuverType::SharpLeft => "Tournez franchement à gauche",
+                ManeuverType::SlightRight => "Serrez à droite",
+                ManeuverType::Right => "Tournez à droite",
+                ManeuverType::SharpRight => "Tournez franchement à droite",
+                ManeuverType::UTurn => "Faites demi-tour",
+            };
+            match object {
+                Some(object) => format!("{verb} sur {object} après {after}"),
+                None => format!("{verb} après {after}"),
+            }
+        }
+        Language::En => {
+            let verb = match kind {
+                ManeuverType::Depart => {
+                    return object.map_or("Start".to_string(), |object| format!("Start on {object}"))
+                }
+                ManeuverType::Arrive => return format!("Arrive at your destination after {after}"),
+                ManeuverType::Continue => "Continue",
+                ManeuverType::SlightLeft => "Bear left",
+                ManeuverType::Left => "Turn left",
+                ManeuverType::SharpLeft => "Turn sharp left",
+                ManeuverType::SlightRight => "Bear right",
+                ManeuverType::Right => "Turn right",
+                ManeuverType::SharpRight => "Turn sharp right",
+                ManeuverType::UTurn => "Make a U-turn",
+            };
+            match object {
+                Some(object) => format!("{verb} onto {object} after {after}"),
+                None => format!("{verb} after {after}"),
+            }
+        }
+    }
+}
+
+/// "350 m" under a kilometre, "1.2 km" / "1,2 km" above.
+fn format_distance(km: f64, language: Language) -> String {
+    // Rounded first, so that 995 m reads 1,0 km rather than 1000 m
+    let metres = (km * 100.0).round() * 10.0;
+    if metres < 1000.0 {
+        return format!("{} m", metres as u32);
+    }
+    let text = format!("{km:.1} km");
+    match language {
+        Language::Fr => text.replace('.', ","),
+        Language::En => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Way {
+        Way { name: Some(name.to_string()), highway: Some(Highway::Unclassified) }
+    }
+
+    #[test]
+    fn test_turns_are_classified_by_heading_change() {
+        assert!((bearing_change(350.0, 80.0) - 90.0).abs() < 1e-9);
+        assert!((bearing_change(80.0, 350.0) + 90.0).abs() < 1e-9);
+        assert_eq!(classify(bearing_change(0.0, 10.0)), ManeuverType::Continue);
+        assert_eq!(classify(-30.0), ManeuverType::SlightLeft);
+        assert_eq!(classify(90.0), ManeuverType::Right);
+        assert_eq!(classify(-150.0), ManeuverType::SharpLeft);
+        assert_eq!(classify(180.0), ManeuverType::UTurn);
+    }
+
+    #[test]
+    fn test_instructions_in_french_and_english() {
+        let croix = named("Chemin de la Croix");
+        assert_eq!(
+            phrase(ManeuverType::Left, Some(&croix), 1.24, Language::Fr),
+            "Tournez à gauche sur Chemin de la Croix après 1,2 km"
+        );
+        assert_eq!(
+            phrase(ManeuverType::Left, Some(&croix), 1.24, Language::En),
+            "Turn left onto Chemin de la Croix after 1.2 km"
+        );
+
+        let path = Way { name: None, highway: Some(Highway::Footway) };
+        assert_eq!(phrase(ManeuverType::SlightRight, Some(&path), 0.347, Language::Fr), "Serrez à droite sur le sentier après 350 m");
+        assert_eq!(phrase(ManeuverType::SlightRight, Some(&path), 0.997, Language::Fr), "Serrez à droite sur le sentier après 1,0 km");
+        assert_eq!(phrase(ManeuverType::Depart, Some(&path), 0.0, Language::En), "Start on the path");
+        assert_eq!(path.label(Language::Fr).as_deref(), Some("sentier"));
+        assert_eq!(phrase(ManeuverType::Arrive, None, 2.0, Language::En), "Arrive at your destination after 2.0 km");
+
+        let track = Way { name: None, highway: Some(Highway::Track) };
+        let bridleway = Way { name: None, highway: Some(Highway::Bridleway) };
+        assert!(path.differs_from(&track));
+        assert!(!path.differs_from(&bridleway));
+        assert!(!croix.differs_from(&named("Chemin de la Croix")));
+    }
+}
@@ -0,0 +1,419 @@
+use std::sync::Arc;
+
+use crate::{
+    elevation::compute_elevation_profile,
+    engine::RouteEngine,
+    error::RouteError,
+    geo_utils::{approximate_distance_km, haversine_km},
+    models::{
+        Coordinate, ElevationProfile, ItineraryRequest, ItineraryResponse, ItineraryStage,
+        RouteOptions, RouteRequest, RouteResponse, StageStop,
+    },
+    poi::Poi,
+    routing::estimate_time_minutes,
+    water::{route_with_water, validate_max_km_without_water},
+};
+
+const MAX_DAYS: u32 = 30;
+const MAX_WAYPOINTS: usize = 50;
+/// POI categories where a stage may end for the night
+const STAGE_STOP_TYPES: [&str; 3] = ["hut", "shelter", "village"];
+/// A hut, shelter or village counts as on the route within this distance
+const STAGE_STOP_RADIUS_M: f64 = 500.0;
+/// Accepted stage duration, relative to the daily target
+const STAGE_MIN_RATIO: f64 = 0.6;
+const STAGE_MAX_RATIO: f64 = 1.3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ItineraryError {
+    #[error("invalid itinerary request: {0}")]
+    InvalidRequest(&'static str),
+    #[error("no path found between waypoints {0} and {1}")]
+    NoPath(usize, usize),
+    #[error(transparent)]
+    Gpx(#[from] RouteError),
+    #[error("itinerary planning task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+/// Split a trek into daily stages ending at huts, shelters or villages.
+///
+/// # Algorithm
+/// 1. Route through all waypoints and estimate the cumulative walking time at
+///    every path point with Naismith's rule ([`estimate_time_minutes`]); without
+///    a DEM the estimate only uses distance
+/// 2. Collect huts, shelters and villages within 500 m of the path
+/// 3. Greedily end each day at the stop closest to the ideal time (previous
+///    stop + daily target) among those between 0.6× and 1.3× the target; if
+///    none fits, the day ends on the trail at the ideal time
+///
+/// With `days`, the daily target is the remaining time divided by the remaining
+/// days, re-balanced after each stop. Otherwise it is `target_hours_per_day`
+/// and days are added until the rest fits in one.
+pub async fn plan_itinerary(
+    engine: &Arc<RouteEngine>,
+    req: &ItineraryRequest,
+) -> Result<ItineraryResponse, ItineraryError> {
+    let engine = Arc::clone(engine);
+    let req = req.clone();
+    tokio::task::spawn_blocking(move || plan_itinerary_blocking(&engine, &req)).await?
+}
+
+fn plan_itinerary_blocking(
+    engine: &RouteEngine,
+    req: &ItineraryRequest,
+) -> Result<ItineraryResponse, ItineraryError> {
+    validate_request(req)?;
+
+    let path = route_through_waypoints(engine, req)?;
+    let elevations = match compute_elevation_profile(&path) {
+        Ok(profile) => Some(profile.elevations),
+        Err(e) => {
+            tracing::warn!("Itinerary without elevation, times use distance only: {}", e);
+            None
+        }
+    };
+
+    let cumulative_minutes = cumulative_minutes(&path, elevations.as_deref());
+    let stops = stage_stop_candidates(engine.pois(), &path);
+    let splits = choose_splits(&cumulative_minutes, &stops, req.days, req.target_hours_per_day);
+
+    tracing::info!(
+        "Itinerary: {:.1}km, {:.0}min, {} stop candidates, {} stages",
+        approximate_distance_km(&path),
+        cumulative_minutes.last().copied().unwrap_or(0.0),
+        stops.len(),
+        splits.len() + 1
+    );
+
+    let mut stages = Vec::with_capacity(splits.len() + 1);
+    let mut from = 0;
+    let ends = splits.into_iter().chain(std::iter::once((path.len() - 1, None)));
+    for (day, (to, overnight)) in (1..).zip(ends) {
+        let stage_elevations = elevations.as_ref().map(|e| &e[from..=to]);
+        stages.push(ItineraryStage {
+            day,
+            route: stage_route(engine, &path[from..=to], stage_elevations, &req.options)?,
+            overnight,
+        });
+        from = to;
+    }
+
+    Ok(ItineraryResponse {
+        total_distance_km: stages.iter().map(|s| s.route.distance_km).sum(),
+        total_time_minutes: stages
+            .iter()
+            .filter_map(|s| s.route.estimated_time_minutes)
+            .sum(),
+        stages,
+    })
+}
+
+/// Reject requests the planner cannot serve, before any routing.
+pub fn validate_request(req: &ItineraryRequest) -> Result<(), ItineraryError> {
+    if req.waypoints.len() < 2 {
+        return Err(ItineraryError::InvalidRequest("at least a start and an end are required"));
+    }
+    if req.waypoints.len() > MAX_WAYPOINTS {
+        return Err(ItineraryError::InvalidRequest("too many waypoints (max 50)"));
+    }
+    if req.days.is_some_and(|days| days == 0 || days > MAX_DAYS) {
+        return Err(ItineraryError::InvalidRequest("days must be between 1 and 30"));
+    }
+    if !req.target_hours_per_day.is_finite()
+        || req.target_hours_per_day <= 0.0
+        || req.target_hours_per_day > 24.0
+    {
+        return Err(ItineraryError::InvalidRequest("target_hours_per_day must be within (0, 24]"));
+    }
+    validate_max_km_without_water(req.options.max_km_without_water)
+        .map_err(ItineraryError::InvalidRequest)?;
+    Ok(())
+}
+
+/// Route the legs between consecutive waypoints, each through water points
+/// as a route request would be when `max_km_without_water` is set. A
+/// stretch across a waypoint can still exceed it: the stages report it.
+fn route_through_waypoints(
+    engine: &RouteEngine,
+    req: &ItineraryRequest,
+) -> Result<Vec<Coordinate>, ItineraryError> {
+    let mut path: Vec<Coordinate> = Vec::new();
+    for (i, leg) in req.waypoints.windows(2).enumerate() {
+        let leg_req = RouteRequest {
+            start: leg[0],
+            end: leg[1],
+            w_pop: req.w_pop,
+            w_paved: req.w_paved,
+            options: req.options.clone(),
+        };
+        let leg_path = match req.options.max_km_without_water {
+            Some(max_km) => route_with_water(engine, &leg_req, max_km).map(|(path, _, _)| path),
+            None => engine.find_path(&leg_req),
+        };
+        let mut leg_path = leg_path
+            .filter(|p| !p.is_empty())
+            .ok_or(ItineraryError::NoPath(i, i + 1))?;
+        if !path.is_empty() {
+            leg_path.remove(0); // same point as the previous leg's end
+        }
+        path.extend(leg_path);
+    }
+    Ok(path)
+}
+
+/// Naismith time from the start to every path point.
+fn cumulative_minutes(path: &[Coordinate], elevations: Option<&[Option<f64>]>) -> Vec<f64> {
+    let mut distance_km = 0.0;
+    let mut ascent_m = 0.0;
+    let mut minutes = Vec::with_capacity(path.len());
+    for i in 0..path.len() {
+        if i > 0 {
+            distance_km += haversine_km(path[i - 1], path[i]);
+            if let Some((Some(e1), Some(e2))) = elevations.map(|e| (e[i - 1], e[i])) {
+                ascent_m += (e2 - e1).max(0.0);
+            }
+        }
+        minutes.push(estimate_time_minutes(distance_km, ascent_m) as f64);
+    }
+    minutes
+}
+
+/// Huts, shelters and villages near the path, with the index of the closest
+/// path point, in path order.
+fn stage_stop_candidates(pois: &[Poi], path: &[Coordinate]) -> Vec<(usize, StageStop)> {
+    let mut stops: Vec<(usize, StageStop)> = pois
+        .iter()
+        .filter(|poi| STAGE_STOP_TYPES.contains(&poi.poi_type.as_str()))
+        .filter_map(|poi| {
+            let location = poi.coordinate();
+            let (index, distance_m) = path
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| (i, haversine_km(location, c) * 1000.0))
+                .min_by(|a, b| a.1.total_cmp(&b.1))?;
+            (distance_m <= STAGE_STOP_RADIUS_M).then(|| {
+                (
+                    index,
+                    StageStop {
+                        poi_type: poi.poi_type.clone(),
+                        name: poi.name.clone(),
+                        location,
+                        distance_from_route_m: distance_m,
+                    },
+                )
+            })
+        })
+        .collect();
+    stops.sort_by_key(|(index, _)| *index);
+    stops
+}
+
+/// Path index ending every stage but the last, with the overnight stop if any.
+fn choose_splits(
+    cumulative_minutes: &[f64],
+    stops: &[(usize, StageStop)],
+    days: Option<u32>,
+    target_hours_per_day: f64,
+) -> Vec<(usize, Option<StageStop>)> {
+    let Some(&total) = cumulative_minutes.last() else {
+        return Vec::new();
+    };
+    let last_index = cumulative_minutes.len() - 1;
+
+    let mut splits = Vec::new();
+    let mut prev_index = 0;
+    loop {
+        let prev_time = cumulative_minutes[prev_index];
+        let remaining = total - prev_time;
+        let stage_count = splits.len() as u32 + 1;
+        let target = match days {
+            Some(days) if stage_count >= days => break,
+            Some(days) => remaining / f64::from(days - stage_count + 1),
+            None if remaining <= target_hours_per_day * 60.0 * STAGE_MAX_RATIO
+                || stage_count >= MAX_DAYS =>
+            {
+                break
+            }
+            None => target_hours_per_day * 60.0,
+        };
+        let ideal = prev_time + target;
+        let (min_time, max_time) = (prev_time + target * STAGE_MIN_RATIO, prev_time + target * STAGE_MAX_RATIO);
+
+        let best_stop = stops
+            .iter()
+            .filter(|(index, _)| *index > prev_index && *index < last_index)
+            .filter(|(index, _)| (min_time..=max_time).contains(&cumulative_minutes[*index]))
+            .min_by(|a, b| {
+                (cumulative_minutes[a.0] - ideal)
+                    .abs()
+                    .total_cmp(&(cumulative_minutes[b.0] - ideal).abs())
+            });
+
+        let split = match best_stop {
+            Some((index, stop)) => (*index, Some(stop.clone())),
+            None => {
+                let index = (prev_index + 1..last_index)
+                    .min_by(|&a, &b| {
+                        (cumulative_minutes[a] - ideal)
+                            .abs()
+                            .total_cmp(&(cumulative_minutes[b] - ideal).abs())
+                    });
+                match index {
+                    Some(index) => (index, None),
+                    None => break, // path too short for another stage
+                }
+            }
+        };
+        prev_index = split.0;
+        splits.push(split);
+    }
+    splits
+}
+
+fn stage_route(
+    engine: &RouteEngine,
+    path: &[Coordinate],
+    elevations: Option<&[Option<f64>]>,
+    options: &RouteOptions,
+) -> Result<RouteResponse, ItineraryError> {
+    let elevation_profile = elevations.map(profile_from_elevations);
+    Ok(engine.route_response(path.to_vec(), &[], elevation_profile, options)?)
+}
+
+/// Profile statistics of an already smoothed slice of elevations.
+fn profile_from_elevations(elevations: &[Option<f64>]) -> ElevationProfile {
+    let valid = || elevations.iter().filter_map(|&e| e);
+    let (mut total_ascent, mut total_descent) = (0.0, 0.0);
+    for pair in elevations.windows(2) {
+        if let (Some(prev), Some(curr)) = (pair[0], pair[1]) {
+            let diff = curr - prev;
+            if diff > 0.0 {
+                total_ascent += diff;
+            } else {
+                total_descent -= diff;
+            }
+        }
+    }
+    ElevationProfile {
+        elevations: elevations.to_vec(),
+        min_elevation: valid().reduce(f64::min),
+        max_elevation: valid().reduce(f64::max),
+        total_ascent,
+        total_descent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(name: &str) -> StageStop {
+        StageStop {
+            poi_type: "hut".to_string(),
+            name: Some(name.to_string()),
+            location: Coordinate { lat: 45.0, lon: 5.0 },
+            distance_from_route_m: 10.0,
+        }
+    }
+
+    /// One path point every 30 minutes, 20 hours in total
+    fn half_hour_steps() -> Vec<f64> {
+        (0..=40).map(|i| i as f64 * 30.0).collect()
+    }
+
+    #[test]
+    fn test_splits_prefer_stops_near_daily_target() {
+        let minutes = half_hour_steps();
+        // Huts after 5h, 6.5h and 13h; the 6h target picks 6.5h then 13h
+        let stops = vec![(10, stop("A")), (13, stop("B")), (26, stop("C"))];
+        let splits = choose_splits(&minutes, &stops, None, 6.0);
+
+        let names: Vec<_> = splits
+            .iter()
+            .map(|(_, s)| s.as_ref().and_then(|s| s.name.clone()))
+            .collect();
+        assert_eq!(names[..2], [Some("B".to_string()), Some("C".to_string())]);
+        // 7h left after C: within 1.3× the target, so that is the last day
+        assert_eq!(splits.len(), 2);
+    }
+
+    #[test]
+    fn test_splits_fall_back_to_trail_without_stops() {
+        let minutes = half_hour_steps();
+        let splits = choose_splits(&minutes, &[], None, 6.0);
+        assert_eq!(splits.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![12, 24, 36]);
+        assert!(splits.iter().all(|(_, s)| s.is_none()));
+    }
+
+    #[test]
+    fn test_splits_honour_fixed_day_count() {
+        let minutes = half_hour_steps();
+        let stops = vec![(19, stop("A"))];
+        let splits = choose_splits(&minutes, &stops, Some(2), 6.0);
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].0, 19, "hut within tolerance of the 10h midpoint");
+
+        assert!(choose_splits(&minutes, &stops, Some(1), 6.0).is_empty());
+        // More days than path points: stop when no point is left
+        assert_eq!(choose_splits(&[0.0, 30.0, 60.0], &[], Some(10), 6.0).len(), 1);
+    }
+
+    #[test]
+    fn test_validate_request() {
+        let mut req = ItineraryRequest {
+            waypoints: vec![Coordinate { lat: 45.0, lon: 5.0 }, Coordinate { lat: 45.1, lon: 5.1 }],
+            days: Some(3),
+            target_hours_per_day: 6.0,
+            w_pop: 1.0,
+            w_paved: 1.0,
+            options: Default::default(),
+        };
+        assert!(validate_request(&req).is_ok());
+        req.options.max_km_without_water = Some(-1.0);
+        assert!(validate_request(&req).is_err());
+        req.options.max_km_without_water = None;
+
+        req.days = Some(0);
+        assert!(validate_request(&req).is_err());
+        req.days = None;
+        req.target_hours_per_day = f64::NAN;
+        assert!(validate_request(&req).is_err());
+        req.target_hours_per_day = 6.0;
+        req.waypoints.truncate(1);
+        assert!(validate_request(&req).is_err());
+    }
+
+    #[test]
+    fn test_plan_on_sample_graph_ends_stage_at_nearby_hut() {
+        let mut graph: crate::graph::GraphFile =
+            serde_json::from_str(include_str!("../data/sample_graph.json")).expect("sample graph");
+        graph.pois = vec![Poi {
+            lat: 45.0005,
+            lon: 5.0249,
+            poi_type: "hut".to_string(),
+            name: Some("Refuge".to_string()),
+        }];
+        let engine = RouteEngine::from_graph_file(graph).expect("engine");
+        let req = ItineraryRequest {
+            waypoints: vec![Coordinate { lat: 45.0, lon: 5.0 }, Coordinate { lat: 45.025, lon: 5.035 }],
+            days: Some(2),
+            target_hours_per_day: 6.0,
+            w_pop: 1.0,
+            w_paved: 1.0,
+            options: Default::default(),
+        };
+
+        let plan = plan_itinerary_blocking(&engine, &req).expect("plan");
+        assert_eq!(plan.stages.len(), 2);
+        let overnight = plan.stages[0].overnight.as_ref().expect("hut stop");
+        assert_eq!(overnight.name.as_deref(), Some("Refuge"));
+        assert!(plan.stages[1].overnight.is_none());
+
+        let first_end = *plan.stages[0].route.path.last().unwrap();
+        assert_eq!(first_end.lat, plan.stages[1].route.path[0].lat);
+        let full_km = approximate_distance_km(&plan.stages[0].route.path)
+            + approximate_distance_km(&plan.stages[1].route.path);
+        assert!((plan.total_distance_km - full_km).abs() < 1e-9);
+    }
+}
@@ -1,4 +1,7 @@
+pub mod accessibility;
 pub mod buildings;
+pub mod chemins_noirs;
+pub mod cycling;
 pub mod database;
 pub mod dem;
 pub mod elevation;
@@ -7,34 +10,50 @@ pub mod error;
 pub mod geo_utils;
 pub mod gpx_export;
 pub mod graph;
+pub mod instructions;
+pub mod itinerary;
 pub mod loops;
+pub mod map_matching;
 pub mod models;
+pub mod off_trail;
 pub mod partial_graph;
 pub mod poi;
+pub mod quietness;
 pub mod routing;
+pub mod sac_scale;
 pub mod saved_routes_handlers;
+pub mod scenery;
 pub mod terrain;
+pub mod water;
+pub mod waymarked;
 
+use std::convert::Infallible;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
 use tower_http::cors::{Any, CorsLayer};
 
 use crate::engine::RouteEngine;
 use crate::error::RouteError;
-use crate::geo_utils::{approximate_distance_km, compute_bounds};
-use crate::gpx_export::encode_route_as_gpx;
-use crate::loops::LoopGenerationError;
+use crate::geo_utils::compute_bounds;
+use crate::itinerary::ItineraryError;
+use crate::loops::{LoopEvent, LoopGenerationError};
+use crate::map_matching::MapMatchError;
 use crate::models::{
-    ApiError, Coordinate, LoopRouteRequest, RouteBounds, RouteMetadata, RouteRequest, RouteResponse,
+    ApiError, Coordinate, DetourRouteRequest, ItineraryRequest, LoopRouteRequest, MapMatchRequest,
+    NearestRequest, NearestResponse, RouteBounds, RouteMetadata, RouteRequest, RouteResponse,
 };
 use crate::routing::generate_route;
 
@@ -63,6 +82,13 @@ pub struct LoadRouteQuery {
     pub filename: String,
 }
 
+/// Query string of `GET /api/loops/stream` (`EventSource` cannot send a body)
+#[derive(Debug, Deserialize)]
+pub struct LoopStreamQuery {
+    /// JSON-encoded [`LoopRouteRequest`]
+    pub request: String,
+}
+
 const SAVED_ROUTES_DIR: &str = "backend/data/saved_routes";
 
 pub fn create_router(state: AppState) -> Router {
@@ -73,7 +99,15 @@ pub fn create_router(state: AppState) -> Router {
 
     Router::new()
         .route("/api/route", post(route_handler))
+        .route("/api/route/detour", post(detour_route_handler))
+        .route("/api/route/match", post(map_match_handler))
+        .route("/api/nearest", post(nearest_handler))
+        .route("/api/itinerary", post(itinerary_handler))
         .route("/api/loops", post(loop_route_handler))
+        .route(
+            "/api/loops/stream",
+            get(loop_stream_get_handler).post(loop_stream_post_handler),
+        )
         .route("/api/routes/save", post(save_route_handler))
         .route("/api/routes/load", get(load_route_handler))
         .route("/api/routes/list", get(list_routes_handler))
@@ -93,7 +127,15 @@ pub fn create_router_with_partial(
 
     Router::new()
         .route("/api/route", post(route_handler))
+        .route("/api/route/detour", post(detour_route_handler))
+        .route("/api/route/match", post(map_match_handler))
+        .route("/api/nearest", post(nearest_handler))
+        .route("/api/itinerary", post(itinerary_handler))
         .route("/api/loops", post(loop_route_handler))
+        .route(
+            "/api/loops/stream",
+            get(loop_stream_get_handler).post(loop_stream_post_handler),
+        )
         .route("/api/routes/save", post(save_route_handler))
         .route("/api/routes/load", get(load_route_handler))
         .route("/api/routes/list", get(list_routes_handler))
@@ -110,26 +152,35 @@ async fn route_handler(
     State(state): State<AppState>,
     Json(req): Json<RouteRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
-    let path = state
-        .engine
-        .find_path(&req)
-        .unwrap_or_else(|| generate_route(&req));
-    let distance_km = approximate_distance_km(&path);
-    let gpx_base64 = encode_route_as_gpx(&path).map_err(internal_error)?;
-    let metadata = build_metadata(&path);
-    let response = RouteResponse {
-        path,
-        distance_km,
-        gpx_base64,
-        metadata: Some(metadata),
-        elevation_profile: None, // Not available in this handler (legacy backend)
-        terrain: None,
-        snapped_waypoints: None,
-        estimated_time_minutes: None,
-        difficulty: None,
-        surface_breakdown: None,
-        segments: None,
+    water::validate_max_km_without_water(req.options.max_km_without_water).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                message: message.to_string(),
+            }),
+        )
+    })?;
+    let routed = match req.options.max_km_without_water {
+        Some(max_km) => water::route_with_water(&state.engine, &req, max_km)
+            .map(|(path, nodes, _)| (path, nodes)),
+        None => state.engine.find_path_returning_indices(&req),
     };
+    if routed.is_none() {
+        if let Some(message) = state
+            .engine
+            .disconnected_island_message(&req)
+            .or_else(|| state.engine.no_accessible_route_message(&req))
+        {
+            return Err((StatusCode::NOT_FOUND, Json(ApiError { message })));
+        }
+    }
+    let (path, nodes) = routed.unwrap_or_else(|| (generate_route(&req), Vec::new()));
+    // No elevation profile in this handler (legacy backend)
+    let mut response = state
+        .engine
+        .route_response(path, &nodes, None, &req.options)
+        .map_err(internal_error)?;
+    response.debug = req.options.debug.then(|| state.engine.explain_route(&req, &nodes)).flatten();
 
     Ok(Json(response))
 }
@@ -144,6 +195,129 @@ async fn loop_route_handler(
         .map_err(loop_error)
 }
 
+async fn detour_route_handler(
+    State(state): State<AppState>,
+    Json(req): Json<DetourRouteRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    loops::generate_detours(&state.engine, &req)
+        .await
+        .map(Json)
+        .map_err(loop_error)
+}
+
+async fn itinerary_handler(
+    State(state): State<AppState>,
+    Json(req): Json<ItineraryRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    itinerary::plan_itinerary(&state.engine, &req)
+        .await
+        .map(Json)
+        .map_err(itinerary_error)
+}
+
+async fn map_match_handler(
+    State(state): State<AppState>,
+    Json(req): Json<MapMatchRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let trace = map_matching::decode_trace(&req.gpx_base64).map_err(map_match_error)?;
+    map_matching::map_match(&state.engine, trace)
+        .await
+        .map(Json)
+        .map_err(map_match_error)
+}
+
+async fn nearest_handler(
+    State(state): State<AppState>,
+    Json(req): Json<NearestRequest>,
+) -> Json<NearestResponse> {
+    Json(NearestResponse {
+        candidates: state.engine.nearest(req.point, req.count),
+    })
+}
+
+async fn loop_stream_get_handler(
+    State(state): State<AppState>,
+    Query(query): Query<LoopStreamQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let req: LoopRouteRequest = serde_json::from_str(&query.request).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                message: format!("Invalid loop request: {}", e),
+            }),
+        )
+    })?;
+    loop_event_stream(Arc::clone(&state.engine), req).map_err(loop_error)
+}
+
+async fn loop_stream_post_handler(
+    State(state): State<AppState>,
+    Json(req): Json<LoopRouteRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    loop_event_stream(Arc::clone(&state.engine), req).map_err(loop_error)
+}
+
+/// Stream loop generation as Server-Sent Events.
+///
+/// Events, in order: `progress` (one per attempt, a [`crate::models::LoopProgress`]),
+/// `candidate` (each kept [`crate::models::LoopCandidate`]), then either `done`
+/// with the final sorted [`crate::models::LoopRouteResponse`] or `error` with an
+/// [`ApiError`]. Invalid requests fail before the stream is opened.
+pub fn loop_event_stream(
+    engine: Arc<RouteEngine>,
+    req: LoopRouteRequest,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, LoopGenerationError> {
+    loops::validate_request(&req)?;
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (loop_tx, mut loop_rx) = tokio::sync::mpsc::unbounded_channel();
+        let generation =
+            tokio::spawn(async move { loops::generate_loops_with_events(&engine, &req, loop_tx).await });
+
+        // The sender is dropped when generation ends, which closes this loop.
+        // When the client goes away, returning drops `loop_rx`, which stops
+        // the search before its next attempt.
+        loop {
+            let event = tokio::select! {
+                event = loop_rx.recv() => event,
+                () = tx.closed() => return,
+            };
+            let Some(event) = event else {
+                break;
+            };
+            let event = match event {
+                LoopEvent::Progress(progress) => sse_json("progress", &progress),
+                LoopEvent::Candidate(candidate) => sse_json("candidate", &candidate),
+            };
+            if tx.send(event).is_err() {
+                return; // client went away
+            }
+        }
+
+        let last = match generation.await {
+            Ok(Ok(response)) => sse_json("done", &response),
+            Ok(Err(err)) => sse_json("error", &ApiError { message: err.to_string() }),
+            Err(err) => sse_json(
+                "error",
+                &ApiError {
+                    message: LoopGenerationError::from(err).to_string(),
+                },
+            ),
+        };
+        let _ = tx.send(last);
+    });
+
+    Ok(Sse::new(UnboundedReceiverStream::new(rx).map(Ok)).keep_alive(KeepAlive::default()))
+}
+
+fn sse_json(name: &str, value: &impl Serialize) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(value)
+        .unwrap_or_else(|err| Event::default().event("error").data(err.to_string()))
+}
+
 pub(crate) fn build_metadata(path: &[Coordinate]) -> RouteMetadata {
     let (min_lat, max_lat, min_lon, max_lon) = compute_bounds(path);
 
@@ -175,13 +349,49 @@ fn internal_error(err: RouteError) -> (StatusCode, Json<ApiError>) {
     )
 }
 
-fn loop_error(err: LoopGenerationError) -> (StatusCode, Json<ApiError>) {
+/// HTTP status and body for a failed loop or detour request.
+pub fn loop_error(err: LoopGenerationError) -> (StatusCode, Json<ApiError>) {
     let status = match err {
-        LoopGenerationError::InvalidTargetDistance => StatusCode::BAD_REQUEST,
-        LoopGenerationError::NoLoopFound => StatusCode::NOT_FOUND,
-        LoopGenerationError::Gpx(_) | LoopGenerationError::Elevation(_) => {
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
+        LoopGenerationError::InvalidTargetDistance
+        | LoopGenerationError::InvalidDirection(_)
+        | LoopGenerationError::InvalidConstraint(_)
+        | LoopGenerationError::TargetShorterThanDirect { .. } => StatusCode::BAD_REQUEST,
+        LoopGenerationError::NoLoopFound | LoopGenerationError::NoDirectRoute => StatusCode::NOT_FOUND,
+        LoopGenerationError::Gpx(_)
+        | LoopGenerationError::Elevation(_)
+        | LoopGenerationError::Task(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    (
+        status,
+        Json(ApiError {
+            message: err.to_string(),
+        }),
+    )
+}
+
+/// HTTP status and body for a failed itinerary request.
+pub fn itinerary_error(err: ItineraryError) -> (StatusCode, Json<ApiError>) {
+    let status = match err {
+        ItineraryError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        ItineraryError::NoPath(..) => StatusCode::NOT_FOUND,
+        ItineraryError::Gpx(_) | ItineraryError::Task(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    (
+        status,
+        Json(ApiError {
+            message: err.to_string(),
+        }),
+    )
+}
+
+/// HTTP status and body for a failed map matching request.
+pub fn map_match_error(err: MapMatchError) -> (StatusCode, Json<ApiError>) {
+    let status = match err {
+        MapMatchError::InvalidGpx(_) | MapMatchError::TraceLength(_) => StatusCode::BAD_REQUEST,
+        MapMatchError::NoMatch => StatusCode::NOT_FOUND,
+        MapMatchError::Gpx(_) | MapMatchError::Task(_) => StatusCode::INTERNAL_SERVER_ERROR,
     };
 
     (
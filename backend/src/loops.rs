@@ -1,31 +1,67 @@
-use std::{collections::HashSet, f64::consts::PI};
+use std::{collections::HashSet, f64::consts::PI, sync::Arc};
+
+use petgraph::graph::NodeIndex;
+use rayon::prelude::*;
+use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
-    elevation::{create_elevation_profile, ElevationError},
+    elevation::{compute_elevation_profile, ElevationError},
     engine::RouteEngine,
     error::RouteError,
-    geo_utils::{approximate_distance_km, EARTH_RADIUS_KM},
-    gpx_export::encode_route_as_gpx,
+    geo_utils::{
+        approximate_distance_km, distance_to_path_m, haversine_km, initial_bearing_deg,
+        is_clockwise, point_in_polygon, EARTH_RADIUS_KM,
+    },
     models::{
-        Coordinate, LoopCandidate, LoopRouteRequest, LoopRouteResponse, RouteRequest, RouteResponse,
+        BearingSector, Coordinate, DetourCandidate, DetourRouteRequest, DetourRouteResponse,
+        LoopAttemptStats, LoopCandidate, LoopConstraintKind, LoopConstraintStatus,
+        LoopDirectionGrades, LoopDirectionPreference, LoopOrientation, LoopProgress, LoopRouteRequest,
+        LoopRouteResponse, LoopSort, RouteRequest,
     },
+    poi::Poi,
+    routing::max_sustained_grades,
+    water::{validate_max_km_without_water, water_report},
 };
 
 const MIN_TARGET_DISTANCE_KM: f64 = 2.0;
 const MIN_DISTANCE_TOLERANCE_KM: f64 = 0.5;
 const MAX_LOOP_CANDIDATES: usize = 12;
 const TARGET_RING_FACTORS: [f64; 3] = [0.75, 1.0, 1.25];
+const MAX_VIA_POINTS: usize = 8;
+/// A via point or POI counts as visited when the loop passes this close
+const CONSTRAINT_RADIUS_M: f64 = 200.0;
+/// Horizontal length over which a grade counts as sustained
+const SUSTAINED_GRADE_WINDOW_M: f64 = 200.0;
 
 #[derive(Debug, thiserror::Error)]
 pub enum LoopGenerationError {
     #[error("loop distance must be strictly positive and larger than {MIN_TARGET_DISTANCE_KM} km")]
     InvalidTargetDistance,
+    #[error("invalid loop direction: {0}")]
+    InvalidDirection(&'static str),
+    #[error("invalid loop constraint: {0}")]
+    InvalidConstraint(&'static str),
     #[error("no loop could be generated with the provided constraints")]
     NoLoopFound,
+    #[error("target distance {target_km:.1} km is shorter than the direct route ({shortest_km:.1} km)")]
+    TargetShorterThanDirect { target_km: f64, shortest_km: f64 },
+    #[error("no route found between start and end")]
+    NoDirectRoute,
     #[error(transparent)]
     Gpx(#[from] RouteError),
     #[error("failed to fetch elevation data: {0}")]
     Elevation(#[from] ElevationError),
+    #[error("loop generation task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+/// Intermediate results reported while loops are being generated.
+#[derive(Debug, Clone)]
+pub enum LoopEvent {
+    /// One attempt was evaluated (accepted or rejected)
+    Progress(LoopProgress),
+    /// A candidate passed validation and was kept
+    Candidate(Box<LoopCandidate>),
 }
 
 /// Generate loop routes using geometric waypoint placement algorithm
@@ -38,6 +74,8 @@ pub enum LoopGenerationError {
 /// - Place intermediate waypoints on concentric circles (rings) around start
 /// - Ring distances: [0.75×, 1.0×, 1.25×] of half_target_distance
 /// - Points evenly distributed by bearing angle (2π / attempts_per_ring)
+/// - With a `bearing_sector` or `via_area`, the same number of attempts is
+///   spread over that sector only (see [`BearingWindow`])
 ///
 /// ## 2. Route Construction
 /// For each candidate waypoint:
@@ -46,18 +84,37 @@ pub enum LoopGenerationError {
 ///
 /// with constraint: return path excludes outbound edges
 /// ```
+/// Mandatory via points and one POI per requested category (the one closest
+/// to the attempt bearing) are added as extra stops, visited in bearing order.
+/// The loop through the via points alone is tried first.
+///
+/// Each loop is then graded in both orientations (max sustained grade over
+/// 200 m) and reversed when that suits `direction_preference` better.
 ///
 /// ## 3. Candidate Filtering
 /// Accept only if:
 /// - Total distance within tolerance: |distance - target| ≤ tolerance_km
 /// - Total ascent within bounds: min_ascent ≤ ascent ≤ max_ascent
 /// - Path has ≥ 3 points (prevents degenerate loops)
+/// - With a `via_area`, the path enters the area
+/// - Loops passing within 200 m of every via point / POI category rank first;
+///   the others are only returned to fill the remaining slots
 ///
 /// ## 4. Optimization Parameters
 /// - `TARGET_RING_FACTORS = [0.75, 1.0, 1.25]`: Explore 3 distance scales
 /// - `MAX_LOOP_CANDIDATES = 12`: Limit results to prevent overload
 /// - Early termination when `candidate_goal` candidates found
 ///
+/// ## 5. Execution
+/// - Runs under `spawn_blocking`; the attempts of one ring are evaluated in
+///   parallel on the rayon pool
+/// - Outcomes are consumed in plan order, so results do not depend on thread
+///   scheduling; `seed` adds reproducible jitter to bearings and distances
+/// - The response reports how many attempts were tried and why they were rejected
+/// - [`generate_loops_with_events`] reports each attempt and each candidate
+///   passing validation as soon as it has been evaluated, and stops early once
+///   its receiver is dropped
+///
 /// # Example
 /// For a 20km loop:
 /// - Half distance = 10km
@@ -70,12 +127,49 @@ pub enum LoopGenerationError {
 /// - `Ok(LoopRouteResponse)`: List of valid loop candidates sorted by quality
 /// - `Err(LoopGenerationError)`: If no valid loops found or invalid parameters
 pub async fn generate_loops(
-    engine: &RouteEngine,
+    engine: &Arc<RouteEngine>,
+    req: &LoopRouteRequest,
+) -> Result<LoopRouteResponse, LoopGenerationError> {
+    let engine = Arc::clone(engine);
+    let req = req.clone();
+    tokio::task::spawn_blocking(move || generate_loops_blocking(&engine, &req, None)).await?
+}
+
+/// Same as [`generate_loops`], but also sends every [`LoopEvent`] to `events`
+/// as soon as it happens, from the worker that evaluated the attempt: events
+/// arrive in completion order, and every candidate passing validation is sent
+/// even if the final response does not keep it. The final response is still
+/// returned at the end.
+///
+/// Once the receiver is dropped, no further attempt is started.
+pub async fn generate_loops_with_events(
+    engine: &Arc<RouteEngine>,
     req: &LoopRouteRequest,
+    events: tokio::sync::mpsc::UnboundedSender<LoopEvent>,
 ) -> Result<LoopRouteResponse, LoopGenerationError> {
+    let engine = Arc::clone(engine);
+    let req = req.clone();
+    tokio::task::spawn_blocking(move || generate_loops_blocking(&engine, &req, Some(&events))).await?
+}
+
+/// Check a loop request without routing anything.
+pub fn validate_request(req: &LoopRouteRequest) -> Result<(), LoopGenerationError> {
     if !req.target_distance_km.is_finite() || req.target_distance_km <= MIN_TARGET_DISTANCE_KM {
         return Err(LoopGenerationError::InvalidTargetDistance);
     }
+    validate_constraints(req)?;
+    BearingWindow::from_request(req)?;
+    Ok(())
+}
+
+/// Blocking body of [`generate_loops`]; attempts of a ring run on the rayon pool.
+fn generate_loops_blocking(
+    engine: &RouteEngine,
+    req: &LoopRouteRequest,
+    events: Option<&UnboundedSender<LoopEvent>>,
+) -> Result<LoopRouteResponse, LoopGenerationError> {
+    let cancelled = || events.is_some_and(|events| events.is_closed());
+    validate_request(req)?;
 
     let tolerance = req
         .distance_tolerance_km
@@ -84,107 +178,449 @@ pub async fn generate_loops(
     let candidate_goal = req.candidate_count.clamp(1, MAX_LOOP_CANDIDATES);
     let attempts_per_ring = candidate_goal.max(4);
     let half_distance = (req.target_distance_km / 2.0).max(0.5);
+    let window = BearingWindow::from_request(req)?;
+    let stops = LoopStops::new(engine, req, half_distance * TARGET_RING_FACTORS[TARGET_RING_FACTORS.len() - 1]);
 
     tracing::info!(
-        "Generating loops: target {:.1}km ± {:.1}km, {} candidates goal, {} attempts per ring ({} rings)",
-        req.target_distance_km, tolerance, candidate_goal, attempts_per_ring, TARGET_RING_FACTORS.len()
+        "Generating loops: target {:.1}km ± {:.1}km, {} candidates goal, {} attempts per ring ({} rings), {} via points, {} POI types",
+        req.target_distance_km, tolerance, candidate_goal, attempts_per_ring, TARGET_RING_FACTORS.len(),
+        req.via_points.len(), req.required_poi_types.len()
     );
 
+    // Batch 0 is the loop through the via points alone (it may already fit),
+    // then one batch per ring.
+    let mut batches: Vec<Vec<LoopAttempt>> = Vec::with_capacity(TARGET_RING_FACTORS.len() + 1);
+    if let Some(first_stop) = req.via_points.first() {
+        batches.push(vec![LoopAttempt {
+            ring: None,
+            step: 0,
+            bearing: initial_bearing_deg(req.start, *first_stop).to_radians(),
+            waypoint: None,
+        }]);
+    }
+    for (ring_idx, factor) in TARGET_RING_FACTORS.iter().enumerate() {
+        let attempts = (0..attempts_per_ring)
+            .map(|step| {
+                let (bearing, distance_km) =
+                    jittered_attempt(req.seed, window, ring_idx, step, attempts_per_ring, half_distance * factor);
+                LoopAttempt {
+                    ring: Some(ring_idx),
+                    step,
+                    bearing,
+                    waypoint: Some(destination_point(req.start, distance_km, bearing)),
+                }
+            })
+            .collect();
+        batches.push(attempts);
+    }
+
+    // Candidates meeting every constraint come first; the others only fill
+    // remaining slots so the caller can see what was missed.
     let mut candidates = Vec::with_capacity(candidate_goal);
+    let mut partial_candidates = Vec::new();
+    let mut stats = LoopAttemptStats::default();
 
-    'rings: for (ring_idx, factor) in TARGET_RING_FACTORS.iter().enumerate() {
-        for step in 0..attempts_per_ring {
-            if candidates.len() >= candidate_goal {
-                break 'rings;
-            }
+    for batch in batches {
+        if candidates.len() >= candidate_goal || cancelled() {
+            break;
+        }
 
-            let phase_offset = ring_idx as f64 * 0.35;
-            let bearing = 2.0 * PI * (step as f64 / attempts_per_ring as f64) + phase_offset;
-            let waypoint = destination_point(req.start, half_distance * factor, bearing);
-
-            let Some(loop_path) = build_loop_path(engine, req, waypoint) else {
-                tracing::debug!("Rejected: no path found to/from waypoint at bearing {:.0}°", bearing.to_degrees());
-                continue;
-            };
-            if loop_path.len() < 3 {
-                tracing::debug!("Rejected: path too short ({} points)", loop_path.len());
-                continue;
-            }
+        // Evaluate the whole batch in parallel, then consume outcomes in plan
+        // order: the accepted set is the same as a sequential run would produce.
+        // Attempts not started before cancellation come back as `None`.
+        let outcomes: Vec<Option<Result<AttemptOutcome, LoopGenerationError>>> = batch
+            .par_iter()
+            .map(|attempt| {
+                if cancelled() {
+                    return None;
+                }
+                let outcome = evaluate_attempt(engine, req, &stops, tolerance, attempt);
+                if let (Some(events), Ok(outcome)) = (events, &outcome) {
+                    let _ = events.send(LoopEvent::Progress(attempt.progress(outcome)));
+                    if let AttemptOutcome::Accepted(candidate) = outcome {
+                        let _ = events.send(LoopEvent::Candidate(candidate.clone()));
+                    }
+                }
+                Some(outcome)
+            })
+            .collect();
 
-            let distance_km = approximate_distance_km(&loop_path);
-            let distance_error = (distance_km - req.target_distance_km).abs();
-            if distance_error > tolerance {
-                tracing::debug!(
-                    "Rejected: distance {:.1}km out of tolerance (target {:.1}km ± {:.1}km, error {:.1}km)",
-                    distance_km, req.target_distance_km, tolerance, distance_error
-                );
-                continue;
+        for outcome in outcomes.into_iter().flatten() {
+            stats.tried += 1;
+            match outcome? {
+                AttemptOutcome::Accepted(candidate) => {
+                    stats.accepted += 1;
+                    if candidates.len() < candidate_goal {
+                        push_candidate(*candidate, &mut candidates, &mut partial_candidates);
+                    }
+                }
+                AttemptOutcome::Rejected(reason) => {
+                    *stats.rejected_by_reason.entry(reason.as_str().to_string()).or_default() += 1;
+                }
             }
+        }
+    }
 
-            let elevation_profile = create_elevation_profile(&loop_path).await?;
-            if let Some(max_ascent) = req.max_total_ascent {
-                if elevation_profile.total_ascent > max_ascent {
-                    tracing::debug!(
-                        "Rejected: ascent {:.0}m exceeds max {:.0}m",
-                        elevation_profile.total_ascent, max_ascent
-                    );
-                    continue;
-                }
+    tracing::info!(
+        "Loop attempts: {} tried, {} accepted, rejected {:?}",
+        stats.tried, stats.accepted, stats.rejected_by_reason
+    );
+
+    sort_candidates(&mut candidates, req.sort_by);
+    sort_candidates(&mut partial_candidates, req.sort_by);
+    candidates.extend(partial_candidates);
+
+    if candidates.is_empty() {
+        return Err(LoopGenerationError::NoLoopFound);
+    }
+    candidates.truncate(candidate_goal);
+
+    Ok(LoopRouteResponse {
+        target_distance_km: req.target_distance_km,
+        distance_tolerance_km: tolerance,
+        candidates,
+        stats: Some(stats),
+    })
+}
+
+/// One planned loop attempt: the bearing it explores and its ring waypoint
+/// (`None` for the loop through the mandatory stops alone).
+#[derive(Debug, Clone, Copy)]
+struct LoopAttempt {
+    ring: Option<usize>,
+    step: usize,
+    bearing: f64,
+    waypoint: Option<Coordinate>,
+}
+
+impl LoopAttempt {
+    fn progress(&self, outcome: &AttemptOutcome) -> LoopProgress {
+        LoopProgress {
+            ring: self.ring,
+            attempt: self.step,
+            bearing_deg: normalize_bearing(self.bearing.to_degrees()),
+            accepted: matches!(outcome, AttemptOutcome::Accepted(_)),
+            rejection_reason: match outcome {
+                AttemptOutcome::Accepted(_) => None,
+                AttemptOutcome::Rejected(reason) => Some(reason.as_str().to_string()),
+            },
+        }
+    }
+}
+
+enum AttemptOutcome {
+    Accepted(Box<LoopCandidate>),
+    Rejected(RejectionReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RejectionReason {
+    NoPath,
+    PathTooShort,
+    DistanceOutOfTolerance,
+    AscentAboveMax,
+    AscentBelowMin,
+    WaterGapExceeded,
+    OutsideViaArea,
+}
+
+impl RejectionReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            RejectionReason::NoPath => "no_path",
+            RejectionReason::PathTooShort => "path_too_short",
+            RejectionReason::DistanceOutOfTolerance => "distance_out_of_tolerance",
+            RejectionReason::AscentAboveMax => "ascent_above_max",
+            RejectionReason::AscentBelowMin => "ascent_below_min",
+            RejectionReason::WaterGapExceeded => "water_gap_exceeded",
+            RejectionReason::OutsideViaArea => "outside_via_area",
+        }
+    }
+}
+
+fn validate_constraints(req: &LoopRouteRequest) -> Result<(), LoopGenerationError> {
+    if req.via_points.len() > MAX_VIA_POINTS {
+        return Err(LoopGenerationError::InvalidConstraint("too many via points (max 8)"));
+    }
+    if req.via_points.iter().any(|c| !c.lat.is_finite() || !c.lon.is_finite()) {
+        return Err(LoopGenerationError::InvalidConstraint("via point coordinates must be finite"));
+    }
+    if req.required_poi_types.iter().any(|t| t.trim().is_empty()) {
+        return Err(LoopGenerationError::InvalidConstraint("POI categories must not be empty"));
+    }
+    validate_max_km_without_water(req.options.max_km_without_water)
+        .map_err(LoopGenerationError::InvalidConstraint)?;
+    Ok(())
+}
+
+/// Mandatory stops of a loop request, with the POIs that could satisfy each
+/// requested category (only those reachable within the largest ring).
+struct LoopStops<'a> {
+    via_points: &'a [Coordinate],
+    poi_options: Vec<(&'a str, Vec<&'a Poi>)>,
+}
+
+impl<'a> LoopStops<'a> {
+    fn new(engine: &'a RouteEngine, req: &'a LoopRouteRequest, max_radius_km: f64) -> Self {
+        let poi_options = req
+            .required_poi_types
+            .iter()
+            .map(|poi_type| {
+                let options = engine
+                    .pois()
+                    .iter()
+                    .filter(|poi| poi.poi_type == *poi_type)
+                    .filter(|poi| haversine_km(req.start, poi.coordinate()) <= max_radius_km)
+                    .collect();
+                (poi_type.as_str(), options)
+            })
+            .collect();
+
+        Self {
+            via_points: &req.via_points,
+            poi_options,
+        }
+    }
+
+    /// For each POI category, the option lying closest to `bearing_rad` as seen
+    /// from `start`, so that attempts on different bearings visit different POIs.
+    fn pick_pois(&self, start: Coordinate, bearing_rad: f64) -> Vec<&'a Poi> {
+        let bearing_deg = normalize_bearing(bearing_rad.to_degrees());
+        self.poi_options
+            .iter()
+            .filter_map(|(_, options)| {
+                options.iter().copied().min_by(|a, b| {
+                    let off_a = bearing_difference_deg(initial_bearing_deg(start, a.coordinate()), bearing_deg);
+                    let off_b = bearing_difference_deg(initial_bearing_deg(start, b.coordinate()), bearing_deg);
+                    off_a.partial_cmp(&off_b).unwrap_or(std::cmp::Ordering::Equal)
+                })
+            })
+            .collect()
+    }
+
+    /// Report whether `path` passes each via point and each POI category.
+    fn report(&self, path: &[Coordinate]) -> Vec<LoopConstraintStatus> {
+        let via_reports = self.via_points.iter().map(|&via| {
+            let distance = distance_to_path_m(via, path);
+            LoopConstraintStatus {
+                kind: LoopConstraintKind::ViaPoint,
+                target: format!("{:.6},{:.6}", via.lat, via.lon),
+                satisfied: distance <= CONSTRAINT_RADIUS_M,
+                location: Some(via),
+                name: None,
+                distance_from_route_m: Some(distance),
             }
-            if let Some(min_ascent) = req.min_total_ascent {
-                if elevation_profile.total_ascent < min_ascent {
-                    tracing::debug!(
-                        "Rejected: ascent {:.0}m below min {:.0}m",
-                        elevation_profile.total_ascent, min_ascent
-                    );
-                    continue;
-                }
+        });
+
+        let poi_reports = self.poi_options.iter().map(|(poi_type, options)| {
+            let nearest = options
+                .iter()
+                .map(|poi| (*poi, distance_to_path_m(poi.coordinate(), path)))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+            LoopConstraintStatus {
+                kind: LoopConstraintKind::Poi,
+                target: poi_type.to_string(),
+                satisfied: nearest.is_some_and(|(_, distance)| distance <= CONSTRAINT_RADIUS_M),
+                location: nearest.map(|(poi, _)| poi.coordinate()),
+                name: nearest.and_then(|(poi, _)| poi.name.clone()),
+                distance_from_route_m: nearest.map(|(_, distance)| distance),
             }
+        });
 
-            tracing::info!(
-                "✓ Accepted loop #{}: {:.1}km, bearing {:.0}°, ascent {:.0}m",
-                candidates.len() + 1,
-                distance_km,
-                normalize_bearing(bearing.to_degrees()),
-                elevation_profile.total_ascent
-            );
+        via_reports.chain(poi_reports).collect()
+    }
+}
 
-            let gpx_base64 = encode_route_as_gpx(&loop_path)?;
-            let metadata = Some(crate::build_metadata(&loop_path));
-            let estimated_time_minutes = Some(
-                crate::routing::estimate_time_minutes(distance_km, elevation_profile.total_ascent),
+/// Route one loop attempt through the mandatory stops plus an optional ring
+/// waypoint and validate it against the request.
+///
+/// Stops are visited in order of bearing from the start, which gives a loop
+/// that sweeps around the start without crossing itself.
+///
+/// Runs on a rayon worker thread, so everything here is blocking.
+fn evaluate_attempt(
+    engine: &RouteEngine,
+    req: &LoopRouteRequest,
+    stops: &LoopStops<'_>,
+    tolerance: f64,
+    attempt: &LoopAttempt,
+) -> Result<AttemptOutcome, LoopGenerationError> {
+    let bearing = attempt.bearing;
+    let mut ordered_stops: Vec<Coordinate> = stops.via_points.to_vec();
+    ordered_stops.extend(stops.pick_pois(req.start, bearing).iter().map(|poi| poi.coordinate()));
+    ordered_stops.extend(attempt.waypoint);
+    ordered_stops.sort_by(|a, b| {
+        initial_bearing_deg(req.start, *a)
+            .partial_cmp(&initial_bearing_deg(req.start, *b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let Some((mut loop_path, mut loop_nodes)) = build_loop_path(engine, req, &ordered_stops) else {
+        tracing::debug!("Rejected: no path found to/from waypoint at bearing {:.0}°", bearing.to_degrees());
+        return Ok(AttemptOutcome::Rejected(RejectionReason::NoPath));
+    };
+    if loop_path.len() < 3 {
+        tracing::debug!("Rejected: path too short ({} points)", loop_path.len());
+        return Ok(AttemptOutcome::Rejected(RejectionReason::PathTooShort));
+    }
+    if let Some(area) = req.via_area.as_deref().filter(|area| !passes_through(&loop_path, area)) {
+        tracing::debug!("Rejected: loop never enters the {}-vertex via area", area.len());
+        return Ok(AttemptOutcome::Rejected(RejectionReason::OutsideViaArea));
+    }
+
+    let distance_km = approximate_distance_km(&loop_path);
+    let distance_error = (distance_km - req.target_distance_km).abs();
+    if distance_error > tolerance {
+        tracing::debug!(
+            "Rejected: distance {:.1}km out of tolerance (target {:.1}km ± {:.1}km, error {:.1}km)",
+            distance_km, req.target_distance_km, tolerance, distance_error
+        );
+        return Ok(AttemptOutcome::Rejected(RejectionReason::DistanceOutOfTolerance));
+    }
+
+    let mut elevation_profile = compute_elevation_profile(&loop_path)?;
+    let (reverse, directions) =
+        choose_direction(&loop_path, &elevation_profile.elevations, req.direction_preference);
+    if reverse {
+        loop_path.reverse();
+        loop_nodes.reverse();
+        elevation_profile.elevations.reverse();
+        std::mem::swap(&mut elevation_profile.total_ascent, &mut elevation_profile.total_descent);
+    }
+
+    if let Some(max_ascent) = req.max_total_ascent {
+        if elevation_profile.total_ascent > max_ascent {
+            tracing::debug!(
+                "Rejected: ascent {:.0}m exceeds max {:.0}m",
+                elevation_profile.total_ascent, max_ascent
+            );
+            return Ok(AttemptOutcome::Rejected(RejectionReason::AscentAboveMax));
+        }
+    }
+    if let Some(min_ascent) = req.min_total_ascent {
+        if elevation_profile.total_ascent < min_ascent {
+            tracing::debug!(
+                "Rejected: ascent {:.0}m below min {:.0}m",
+                elevation_profile.total_ascent, min_ascent
             );
-            let difficulty = Some(crate::routing::rate_difficulty(
-                &elevation_profile.elevations,
-                &loop_path,
-                elevation_profile.total_ascent,
-            ));
-            let route = RouteResponse {
-                path: loop_path,
-                distance_km,
-                gpx_base64,
-                metadata,
-                elevation_profile: Some(elevation_profile),
-                terrain: None,
-                snapped_waypoints: None,
-                estimated_time_minutes,
-                difficulty,
-                surface_breakdown: None,
-                segments: None,
-            };
-
-            candidates.push(LoopCandidate {
-                route,
-                distance_error_km: distance_error,
-                bearing_deg: normalize_bearing(bearing.to_degrees()),
-            });
+            return Ok(AttemptOutcome::Rejected(RejectionReason::AscentBelowMin));
         }
     }
 
-    if candidates.is_empty() {
-        return Err(LoopGenerationError::NoLoopFound);
+    let water = req
+        .options
+        .max_km_without_water
+        .map(|max_km| water_report(engine.pois(), &loop_path, max_km));
+    if let Some(report) = water.as_ref().filter(|report| !report.satisfied) {
+        tracing::debug!(
+            "Rejected: {:.1}km without water exceeds max {:.1}km",
+            report.longest_gap_km, report.max_km_without_water
+        );
+        return Ok(AttemptOutcome::Rejected(RejectionReason::WaterGapExceeded));
+    }
+
+    let constraints = stops.report(&loop_path);
+
+    tracing::info!(
+        "✓ Accepted loop: {:.1}km, bearing {:.0}°, ascent {:.0}m, {}/{} constraints met",
+        distance_km,
+        normalize_bearing(bearing.to_degrees()),
+        elevation_profile.total_ascent,
+        constraints.iter().filter(|c| c.satisfied).count(),
+        constraints.len()
+    );
+
+    let route = engine.route_response(loop_path, &loop_nodes, Some(elevation_profile), &req.options)?;
+
+    Ok(AttemptOutcome::Accepted(Box::new(LoopCandidate {
+        route,
+        distance_error_km: distance_error,
+        bearing_deg: normalize_bearing(bearing.to_degrees()),
+        constraints,
+        orientation: Some(directions[0].orientation),
+        directions: directions.to_vec(),
+    })))
+}
+
+/// Whether `path` has a point inside `area`: waypoints aimed at the area
+/// can still be snapped, or routed around it, outside.
+fn passes_through(path: &[Coordinate], area: &[Coordinate]) -> bool {
+    path.iter().any(|&c| point_in_polygon(c, area))
+}
+
+/// Grade both orientations of a loop and pick the one matching `preference`.
+///
+/// Returns whether the path should be reversed, and the grades of both
+/// orientations with the chosen one first. Ties keep the generated direction.
+fn choose_direction(
+    path: &[Coordinate],
+    elevations: &[Option<f64>],
+    preference: Option<LoopDirectionPreference>,
+) -> (bool, [LoopDirectionGrades; 2]) {
+    let forward = max_sustained_grades(elevations, path, SUSTAINED_GRADE_WINDOW_M);
+    let reversed_path: Vec<Coordinate> = path.iter().rev().copied().collect();
+    let reversed_elevations: Vec<Option<f64>> = elevations.iter().rev().copied().collect();
+    let backward = max_sustained_grades(&reversed_elevations, &reversed_path, SUSTAINED_GRADE_WINDOW_M);
+
+    let reverse = match preference {
+        Some(LoopDirectionPreference::GentleAscent) => backward.ascent_pct < forward.ascent_pct,
+        Some(LoopDirectionPreference::GentleDescent) => backward.descent_pct < forward.descent_pct,
+        None => false,
+    };
+
+    let (forward_orientation, backward_orientation) = if is_clockwise(path) {
+        (LoopOrientation::Clockwise, LoopOrientation::Counterclockwise)
+    } else {
+        (LoopOrientation::Counterclockwise, LoopOrientation::Clockwise)
+    };
+    let forward = LoopDirectionGrades {
+        orientation: forward_orientation,
+        max_sustained_ascent_pct: forward.ascent_pct,
+        max_sustained_descent_pct: forward.descent_pct,
+    };
+    let backward = LoopDirectionGrades {
+        orientation: backward_orientation,
+        max_sustained_ascent_pct: backward.ascent_pct,
+        max_sustained_descent_pct: backward.descent_pct,
+    };
+
+    if reverse {
+        (true, [backward, forward])
+    } else {
+        (false, [forward, backward])
+    }
+}
+
+fn push_candidate(
+    candidate: LoopCandidate,
+    candidates: &mut Vec<LoopCandidate>,
+    partial_candidates: &mut Vec<LoopCandidate>,
+) {
+    if candidate.constraints.iter().all(|c| c.satisfied) {
+        candidates.push(candidate);
+    } else {
+        partial_candidates.push(candidate);
     }
+}
 
+/// Sort by total ascent or by descending chemins noirs score, then by
+/// distance error.
+fn sort_candidates(candidates: &mut [LoopCandidate], sort_by: LoopSort) {
+    if sort_by == LoopSort::CheminsNoirs {
+        let score = |c: &LoopCandidate| c.route.chemins_noirs.map_or(f64::MIN, |s| s.score);
+        candidates.sort_by(|a, b| {
+            score(b)
+                .partial_cmp(&score(a))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| {
+                    a.distance_error_km
+                        .partial_cmp(&b.distance_error_km)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+        });
+        return;
+    }
     candidates.sort_by(|a, b| {
         let ascent_a = a
             .route
@@ -208,21 +644,14 @@ pub async fn generate_loops(
                     .unwrap_or(std::cmp::Ordering::Equal)
             })
     });
-    candidates.truncate(candidate_goal);
-
-    Ok(LoopRouteResponse {
-        target_distance_km: req.target_distance_km,
-        distance_tolerance_km: tolerance,
-        candidates,
-    })
 }
 
-/// Build a complete loop path: start → waypoint → start
+/// Build a complete loop path: start → stop₁ → … → stopₙ → start
 ///
 /// # Algorithm
-/// 1. **Outbound**: A* from start to waypoint (unrestricted)
-/// 2. **Track edges**: Record all edges used in outbound path
-/// 3. **Return**: A* from waypoint to start, **excluding** outbound edges
+/// 1. **Legs**: A* from each stop to the next, starting and ending at `req.start`
+/// 2. **Track edges**: Record all edges used by the previous legs
+/// 3. **Exclusion**: Every leg avoids the edges of the legs before it
 ///
 /// This ensures the return path differs from outbound, creating a true loop.
 ///
@@ -232,51 +661,379 @@ pub async fn generate_loops(
 /// - Final edge to start is always allowed (to close the loop)
 ///
 /// # Returns
-/// - `Some(Vec<Coordinate>)`: Complete loop if both paths found
-/// - `None`: If either outbound or return path fails
+/// - `Some(Vec<Coordinate>)`: Complete loop if every leg was found
+/// - `None`: If any leg fails, or there are no stops
 fn build_loop_path(
     engine: &RouteEngine,
     req: &LoopRouteRequest,
-    waypoint: Coordinate,
-) -> Option<Vec<Coordinate>> {
-    let to_waypoint = RouteRequest {
+    stops: &[Coordinate],
+) -> Option<(Vec<Coordinate>, Vec<NodeIndex>)> {
+    if stops.is_empty() {
+        return None;
+    }
+    let template = RouteRequest {
         start: req.start,
-        end: waypoint,
+        end: req.start,
         w_pop: req.w_pop,
         w_paved: req.w_paved,
+        options: req.options.clone(),
     };
+    build_path_through(engine, &template, stops)
+}
 
-    // Find outbound path with node indices (avoids costly reverse-mapping via closest_node)
-    let (outbound, outbound_indices) = engine.find_path_returning_indices(&to_waypoint)?;
-    if outbound.is_empty() {
-        return None;
+/// start → stop₁ → … → stopₙ → end, each leg avoiding the edges of the previous ones.
+/// Shared by loops (`end == start`) and detours; every leg reuses the weights
+/// and options of `template`. Returns the path and the graph nodes it goes
+/// through.
+fn build_path_through(
+    engine: &RouteEngine,
+    template: &RouteRequest,
+    stops: &[Coordinate],
+) -> Option<(Vec<Coordinate>, Vec<NodeIndex>)> {
+    let mut excluded_edges = HashSet::new();
+    let mut result: Vec<Coordinate> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut leg_start = template.start;
+
+    for &leg_end in stops.iter().chain(std::iter::once(&template.end)) {
+        let leg_req = RouteRequest {
+            start: leg_start,
+            end: leg_end,
+            ..template.clone()
+        };
+
+        // Node indices avoid costly reverse-mapping via closest_node
+        let (mut leg, leg_indices) =
+            engine.find_path_with_excluded_edges_returning_indices(&leg_req, &excluded_edges)?;
+        if leg.is_empty() {
+            return None;
+        }
+
+        // Build excluded edges directly from A* node indices — O(N) instead of O(N * log N)
+        for window in leg_indices.windows(2) {
+            excluded_edges.insert((window[0], window[1]));
+        }
+
+        if !result.is_empty() {
+            leg.remove(0); // drop duplicate stop before concatenation
+        }
+        result.extend(leg);
+        RouteEngine::append_leg_nodes(&mut nodes, &leg_indices);
+        leg_start = leg_end;
     }
 
-    // Build excluded edges directly from A* node indices — O(N) instead of O(N * log N)
-    let mut excluded_edges = HashSet::new();
-    for window in outbound_indices.windows(2) {
-        excluded_edges.insert((window[0], window[1]));
+    Some((result, nodes))
+}
+
+/// Generate A→B routes close to `target_distance_km`, longer than the shortest path.
+///
+/// Reuses the loop machinery: one intermediate waypoint per attempt, with the
+/// second leg avoiding the edges of the first (see [`build_path_through`]).
+/// Waypoints lie on ellipses whose foci are A and B, so that
+/// `|A→W| + |W→B|` is the target divided by the road detour factor of the
+/// shortest route. The three rings scale the extra length by
+/// `TARGET_RING_FACTORS`; distance and ascent filters are the same as for loops.
+///
+/// When the shortest route already fits the tolerance, it is returned first.
+pub async fn generate_detours(
+    engine: &Arc<RouteEngine>,
+    req: &DetourRouteRequest,
+) -> Result<DetourRouteResponse, LoopGenerationError> {
+    let engine = Arc::clone(engine);
+    let req = req.clone();
+    tokio::task::spawn_blocking(move || generate_detours_blocking(&engine, &req)).await?
+}
+
+fn generate_detours_blocking(
+    engine: &RouteEngine,
+    req: &DetourRouteRequest,
+) -> Result<DetourRouteResponse, LoopGenerationError> {
+    if !req.target_distance_km.is_finite() || req.target_distance_km <= MIN_TARGET_DISTANCE_KM {
+        return Err(LoopGenerationError::InvalidTargetDistance);
     }
+    validate_max_km_without_water(req.options.max_km_without_water)
+        .map_err(LoopGenerationError::InvalidConstraint)?;
 
-    // Find return path while avoiding outbound edges
-    let from_waypoint = RouteRequest {
-        start: waypoint,
-        end: req.start,
+    let tolerance = req
+        .distance_tolerance_km
+        .max(MIN_DISTANCE_TOLERANCE_KM)
+        .min(req.target_distance_km);
+    let candidate_goal = req.candidate_count.clamp(1, MAX_LOOP_CANDIDATES);
+    let attempts_per_ring = candidate_goal.max(4);
+
+    let direct_req = RouteRequest {
+        start: req.start,
+        end: req.end,
         w_pop: req.w_pop,
         w_paved: req.w_paved,
+        options: req.options.clone(),
     };
+    let direct = engine
+        .find_path_returning_indices(&direct_req)
+        .ok_or(LoopGenerationError::NoDirectRoute)?;
+    let shortest_km = approximate_distance_km(&direct.0);
+    if req.target_distance_km + tolerance < shortest_km {
+        return Err(LoopGenerationError::TargetShorterThanDirect {
+            target_km: req.target_distance_km,
+            shortest_km,
+        });
+    }
 
-    let (mut inbound, _) =
-        engine.find_path_with_excluded_edges_returning_indices(&from_waypoint, &excluded_edges)?;
-    if inbound.is_empty() {
-        return None;
+    let straight_km = haversine_km(req.start, req.end);
+    let road_factor = if straight_km > 0.1 { (shortest_km / straight_km).max(1.0) } else { 1.0 };
+    let straight_target_km = req.target_distance_km / road_factor;
+
+    tracing::info!(
+        "Generating detours: target {:.1}km ± {:.1}km, shortest {:.1}km (road factor {:.2})",
+        req.target_distance_km, tolerance, shortest_km, road_factor
+    );
+
+    let mut candidates = Vec::with_capacity(candidate_goal);
+    if (shortest_km - req.target_distance_km).abs() <= tolerance {
+        candidates.extend(evaluate_detour(engine, req, tolerance, direct, None)?);
+    }
+
+    for factor in TARGET_RING_FACTORS {
+        if candidates.len() >= candidate_goal {
+            break;
+        }
+        let ellipse_km = straight_km + (straight_target_km - straight_km).max(0.0) * factor;
+        let vias: Vec<Coordinate> = (0..attempts_per_ring)
+            .map(|step| {
+                let angle = 2.0 * PI * (step as f64 + 0.5) / attempts_per_ring as f64;
+                ellipse_point(req.start, req.end, ellipse_km, angle)
+            })
+            .collect();
+
+        let outcomes: Vec<Result<Option<DetourCandidate>, LoopGenerationError>> = vias
+            .par_iter()
+            .map(|&via| {
+                match build_path_through(engine, &direct_req, &[via]) {
+                    Some(routed) if routed.0.len() >= 3 => evaluate_detour(engine, req, tolerance, routed, Some(via)),
+                    _ => Ok(None),
+                }
+            })
+            .collect();
+
+        for outcome in outcomes {
+            if let Some(candidate) = outcome? {
+                if candidates.len() < candidate_goal {
+                    candidates.push(candidate);
+                }
+            }
+        }
+    }
+
+    if candidates.is_empty() {
+        return Err(LoopGenerationError::NoLoopFound);
+    }
+    candidates.sort_by(|a, b| {
+        a.distance_error_km
+            .partial_cmp(&b.distance_error_km)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    Ok(DetourRouteResponse {
+        target_distance_km: req.target_distance_km,
+        distance_tolerance_km: tolerance,
+        shortest_distance_km: shortest_km,
+        candidates,
+    })
+}
+
+/// Apply the loop distance, ascent and water filters to a detour path.
+fn evaluate_detour(
+    engine: &RouteEngine,
+    req: &DetourRouteRequest,
+    tolerance: f64,
+    (path, nodes): (Vec<Coordinate>, Vec<NodeIndex>),
+    via: Option<Coordinate>,
+) -> Result<Option<DetourCandidate>, LoopGenerationError> {
+    let distance_km = approximate_distance_km(&path);
+    let distance_error = (distance_km - req.target_distance_km).abs();
+    if distance_error > tolerance {
+        tracing::debug!(
+            "Rejected detour: {:.1}km out of tolerance (target {:.1}km)",
+            distance_km, req.target_distance_km
+        );
+        return Ok(None);
+    }
+
+    let elevation_profile = compute_elevation_profile(&path)?;
+    if req.max_total_ascent.is_some_and(|max| elevation_profile.total_ascent > max)
+        || req.min_total_ascent.is_some_and(|min| elevation_profile.total_ascent < min)
+    {
+        tracing::debug!("Rejected detour: ascent {:.0}m out of bounds", elevation_profile.total_ascent);
+        return Ok(None);
+    }
+
+    let water = req
+        .options
+        .max_km_without_water
+        .map(|max_km| water_report(engine.pois(), &path, max_km));
+    if let Some(report) = water.as_ref().filter(|report| !report.satisfied) {
+        tracing::debug!("Rejected detour: {:.1}km without water", report.longest_gap_km);
+        return Ok(None);
+    }
+
+    let route = engine.route_response(path, &nodes, Some(elevation_profile), &req.options)?;
+    Ok(Some(DetourCandidate {
+        route,
+        distance_error_km: distance_error,
+        via,
+    }))
+}
+
+/// Point on the ellipse with foci `a` and `b` whose distances to the foci sum
+/// to `sum_km`, at parametric `angle` (0 lies beyond `b`, π behind `a`).
+/// Uses a local flat projection around the midpoint.
+fn ellipse_point(a: Coordinate, b: Coordinate, sum_km: f64, angle: f64) -> Coordinate {
+    let km_per_deg = EARTH_RADIUS_KM * PI / 180.0;
+    let center = a.interpolate(b, 0.5);
+    let cos_lat = center.lat.to_radians().cos();
+
+    let east = (b.lon - a.lon) * km_per_deg * cos_lat;
+    let north = (b.lat - a.lat) * km_per_deg;
+    let focal_km = east.hypot(north);
+    let (ux, uy) = if focal_km > 1e-9 { (east / focal_km, north / focal_km) } else { (0.0, 1.0) };
+
+    let semi_major = (sum_km / 2.0).max(focal_km / 2.0);
+    let semi_minor = (semi_major * semi_major - focal_km * focal_km / 4.0).max(0.0).sqrt();
+    let x = semi_major * angle.cos();
+    let y = semi_minor * angle.sin();
+
+    // Perpendicular (-uy, ux) points to the left of A→B
+    let dx = ux * x - uy * y;
+    let dy = uy * x + ux * y;
+    Coordinate {
+        lat: center.lat + dy / km_per_deg,
+        lon: normalize_longitude(center.lon + dx / (km_per_deg * cos_lat)),
+    }
+}
+
+/// Compass sector inside which loop waypoints are placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BearingWindow {
+    /// Sector start in degrees [0, 360)
+    start_deg: f64,
+    /// Clockwise sector width in degrees [0, 360)
+    width_deg: f64,
+}
+
+impl BearingWindow {
+    /// Resolve the requested direction, `None` meaning the full circle.
+    fn from_request(req: &LoopRouteRequest) -> Result<Option<Self>, LoopGenerationError> {
+        match (&req.bearing_sector, &req.via_area) {
+            (Some(_), Some(_)) => Err(LoopGenerationError::InvalidDirection(
+                "specify either bearing_sector or via_area, not both",
+            )),
+            (Some(sector), None) => Self::from_sector(sector),
+            (None, Some(polygon)) => Self::from_polygon(req.start, polygon),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn from_sector(sector: &BearingSector) -> Result<Option<Self>, LoopGenerationError> {
+        if !sector.min_bearing_deg.is_finite() || !sector.max_bearing_deg.is_finite() {
+            return Err(LoopGenerationError::InvalidDirection("bearing sector bounds must be finite"));
+        }
+        if sector.max_bearing_deg - sector.min_bearing_deg >= 360.0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            start_deg: normalize_bearing(sector.min_bearing_deg),
+            width_deg: normalize_bearing(sector.max_bearing_deg - sector.min_bearing_deg),
+        }))
+    }
+
+    /// Smallest sector (seen from `start`) containing every polygon vertex.
+    /// A start located inside the area leaves every direction open.
+    fn from_polygon(start: Coordinate, polygon: &[Coordinate]) -> Result<Option<Self>, LoopGenerationError> {
+        if polygon.len() < 3 {
+            return Err(LoopGenerationError::InvalidDirection("via area needs at least 3 vertices"));
+        }
+        if polygon.iter().any(|c| !c.lat.is_finite() || !c.lon.is_finite()) {
+            return Err(LoopGenerationError::InvalidDirection("via area coordinates must be finite"));
+        }
+        if point_in_polygon(start, polygon) {
+            return Ok(None);
+        }
+
+        let mut bearings: Vec<f64> = polygon.iter().map(|&c| initial_bearing_deg(start, c)).collect();
+        bearings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        // The sector is the complement of the widest angular gap between vertices
+        let mut widest_gap = 360.0 - bearings[bearings.len() - 1] + bearings[0];
+        let mut start_deg = bearings[0];
+        for pair in bearings.windows(2) {
+            let gap = pair[1] - pair[0];
+            if gap > widest_gap {
+                widest_gap = gap;
+                start_deg = pair[1];
+            }
+        }
+
+        Ok(Some(Self {
+            start_deg,
+            width_deg: 360.0 - widest_gap,
+        }))
     }
+}
+
+/// Bearing (radians) of attempt `step` on ring `ring_idx`.
+///
+/// Without a window, attempts are spread over 360° and each ring is rotated by
+/// a fixed phase offset. Inside a window, attempts are spread evenly across the
+/// sector and rings are staggered by a fraction of the spacing so that they never
+/// leave it.
+fn attempt_bearing(window: Option<BearingWindow>, ring_idx: usize, step: usize, attempts_per_ring: usize) -> f64 {
+    match window {
+        None => {
+            let phase_offset = ring_idx as f64 * 0.35;
+            2.0 * PI * (step as f64 / attempts_per_ring as f64) + phase_offset
+        }
+        Some(window) => {
+            let stagger = (ring_idx + 1) as f64 / (TARGET_RING_FACTORS.len() + 1) as f64;
+            let fraction = (step as f64 + stagger) / attempts_per_ring as f64;
+            (window.start_deg + window.width_deg * fraction).to_radians()
+        }
+    }
+}
+
+/// Bearing (radians) and waypoint distance of an attempt, with seeded jitter.
+///
+/// Without a seed this is exactly [`attempt_bearing`] at the ring distance.
+/// With a seed, the bearing moves by up to a quarter of the spacing between
+/// attempts and the distance by up to ±10%, so neighbouring attempts never swap.
+fn jittered_attempt(
+    seed: Option<u64>,
+    window: Option<BearingWindow>,
+    ring_idx: usize,
+    step: usize,
+    attempts_per_ring: usize,
+    ring_distance_km: f64,
+) -> (f64, f64) {
+    let bearing = attempt_bearing(window, ring_idx, step, attempts_per_ring);
+    let Some(seed) = seed else {
+        return (bearing, ring_distance_km);
+    };
+
+    let spacing = window.map_or(2.0 * PI, |w| w.width_deg.to_radians()) / attempts_per_ring as f64;
+    let attempt_id = (ring_idx * attempts_per_ring + step) as u64;
+    let bearing_jitter = unit_jitter(seed, attempt_id * 2) * spacing * 0.25;
+    let distance_jitter = unit_jitter(seed, attempt_id * 2 + 1) * 0.1;
 
-    // Merge paths
-    let mut result = outbound;
-    inbound.remove(0); // drop duplicate waypoint before concatenation
-    result.extend(inbound);
-    Some(result)
+    (bearing + bearing_jitter, ring_distance_km * (1.0 + distance_jitter))
+}
+
+/// Deterministic value in [-1, 1) derived from `seed` and `index` (SplitMix64).
+fn unit_jitter(seed: u64, index: u64) -> f64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
 }
 
 fn destination_point(start: Coordinate, distance_km: f64, bearing_rad: f64) -> Coordinate {
@@ -302,7 +1059,7 @@ fn destination_point(start: Coordinate, distance_km: f64, bearing_rad: f64) -> C
 
 fn normalize_longitude(lon: f64) -> f64 {
     // Early return if already in valid range (preserves -180.0 and 180.0)
-    if lon >= -180.0 && lon <= 180.0 {
+    if (-180.0..=180.0).contains(&lon) {
         return lon;
     }
     // Use modulo arithmetic to handle arbitrarily large values
@@ -317,6 +1074,12 @@ fn normalize_longitude(lon: f64) -> f64 {
     }
 }
 
+/// Absolute angular difference between two bearings, in degrees [0, 180].
+fn bearing_difference_deg(a: f64, b: f64) -> f64 {
+    let diff = normalize_bearing(a - b);
+    diff.min(360.0 - diff)
+}
+
 fn normalize_bearing(bearing_deg: f64) -> f64 {
     let mut value = bearing_deg % 360.0;
     if value < 0.0 {
@@ -356,6 +1119,368 @@ mod tests {
         assert_eq!(normalize_bearing(450.0), 90.0);
     }
 
+    fn sector_request(bearing_sector: Option<BearingSector>, via_area: Option<Vec<Coordinate>>) -> LoopRouteRequest {
+        LoopRouteRequest {
+            start: Coordinate { lat: 45.0, lon: 5.0 },
+            target_distance_km: 20.0,
+            distance_tolerance_km: 3.0,
+            candidate_count: 6,
+            w_pop: 1.0,
+            w_paved: 1.0,
+            max_total_ascent: None,
+            min_total_ascent: None,
+            bearing_sector,
+            via_area,
+            via_points: vec![],
+            required_poi_types: vec![],
+            seed: None,
+            direction_preference: None,
+            sort_by: Default::default(),
+            options: Default::default(),
+        }
+    }
+
+    fn sample_engine_with_pois(pois: Vec<Poi>) -> RouteEngine {
+        let mut graph: crate::graph::GraphFile =
+            serde_json::from_str(include_str!("../data/sample_graph.json")).expect("sample graph");
+        graph.pois = pois;
+        RouteEngine::from_graph_file(graph).expect("engine")
+    }
+
+    fn poi(lat: f64, lon: f64, poi_type: &str, name: &str) -> Poi {
+        Poi {
+            lat,
+            lon,
+            poi_type: poi_type.to_string(),
+            name: Some(name.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_validate_constraints() {
+        let mut req = sector_request(None, None);
+        assert!(validate_constraints(&req).is_ok());
+
+        req.via_points = vec![Coordinate { lat: 45.01, lon: 5.0 }; MAX_VIA_POINTS + 1];
+        assert!(matches!(validate_constraints(&req), Err(LoopGenerationError::InvalidConstraint(_))));
+
+        req.via_points.clear();
+        req.required_poi_types = vec![" ".to_string()];
+        assert!(validate_constraints(&req).is_err());
+    }
+
+    #[test]
+    fn test_pick_pois_prefers_attempt_bearing() {
+        let engine = sample_engine_with_pois(vec![
+            poi(45.01, 5.005, "water", "north fountain"),
+            poi(44.99, 5.0, "water", "south fountain"),
+            poi(45.01, 5.0, "hut", "refuge"),
+            poi(46.0, 5.0, "water", "too far"),
+        ]);
+        let mut req = sector_request(None, None);
+        req.start = Coordinate { lat: 45.0, lon: 5.0 };
+        req.required_poi_types = vec!["water".to_string()];
+        let stops = LoopStops::new(&engine, &req, 12.5);
+
+        assert_eq!(stops.poi_options[0].1.len(), 2, "distant POI must be ignored");
+        let north = stops.pick_pois(req.start, 0.0);
+        assert_eq!(north[0].name.as_deref(), Some("north fountain"));
+        let south = stops.pick_pois(req.start, PI);
+        assert_eq!(south[0].name.as_deref(), Some("south fountain"));
+    }
+
+    #[test]
+    fn test_constraint_report() {
+        let engine = sample_engine_with_pois(vec![poi(45.0101, 5.005, "water", "fountain")]);
+        let mut req = sector_request(None, None);
+        req.via_points = vec![Coordinate { lat: 45.02, lon: 5.015 }];
+        req.required_poi_types = vec!["water".to_string(), "hut".to_string()];
+        let stops = LoopStops::new(&engine, &req, 12.5);
+
+        let path = vec![
+            Coordinate { lat: 45.0, lon: 5.0 },
+            Coordinate { lat: 45.01, lon: 5.005 },
+            Coordinate { lat: 45.0, lon: 5.01 },
+        ];
+        let report = stops.report(&path);
+        assert_eq!(report.len(), 3);
+
+        assert_eq!(report[0].kind, LoopConstraintKind::ViaPoint);
+        assert!(!report[0].satisfied, "{:?}", report[0]);
+
+        assert_eq!(report[1].target, "water");
+        assert!(report[1].satisfied);
+        assert_eq!(report[1].name.as_deref(), Some("fountain"));
+
+        assert_eq!(report[2].target, "hut");
+        assert!(!report[2].satisfied);
+        assert!(report[2].location.is_none());
+    }
+
+    #[test]
+    fn test_build_loop_path_passes_through_stops() {
+        let engine = sample_engine_with_pois(vec![]);
+        let mut req = sector_request(None, None);
+        req.start = Coordinate { lat: 45.0, lon: 5.0 };
+        let stops = [
+            Coordinate { lat: 45.01, lon: 5.005 },
+            Coordinate { lat: 45.02, lon: 5.015 },
+        ];
+
+        let (path, _) = build_loop_path(&engine, &req, &stops).expect("loop path");
+        let first = path.first().unwrap();
+        let last = path.last().unwrap();
+        assert!(haversine_km(*first, *last) < 0.05, "loop must return to start");
+        for stop in stops {
+            assert!(distance_to_path_m(stop, &path) < CONSTRAINT_RADIUS_M);
+        }
+
+        assert!(build_loop_path(&engine, &req, &[]).is_none());
+    }
+
+    fn all_attempt_bearings_deg(window: Option<BearingWindow>) -> Vec<f64> {
+        (0..TARGET_RING_FACTORS.len())
+            .flat_map(|ring| (0..6).map(move |step| (ring, step)))
+            .map(|(ring, step)| normalize_bearing(attempt_bearing(window, ring, step, 6).to_degrees()))
+            .collect()
+    }
+
+    #[test]
+    fn test_bearing_window_defaults_to_full_circle() {
+        assert_eq!(BearingWindow::from_request(&sector_request(None, None)).unwrap(), None);
+
+        let full = BearingSector { min_bearing_deg: 0.0, max_bearing_deg: 360.0 };
+        assert_eq!(BearingWindow::from_request(&sector_request(Some(full), None)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_attempt_bearings_stay_inside_sector() {
+        let north = BearingSector { min_bearing_deg: 315.0, max_bearing_deg: 45.0 };
+        let window = BearingWindow::from_request(&sector_request(Some(north), None)).unwrap();
+        assert_eq!(window, Some(BearingWindow { start_deg: 315.0, width_deg: 90.0 }));
+
+        let bearings = all_attempt_bearings_deg(window);
+        assert!(bearings.iter().all(|&b| !(45.0..315.0).contains(&b)), "{bearings:?}");
+
+        // Rings are staggered, so no two attempts share a bearing
+        let mut unique = bearings.clone();
+        unique.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        unique.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+        assert_eq!(unique.len(), bearings.len());
+    }
+
+    #[test]
+    fn test_via_area_restricts_bearings_to_polygon() {
+        // Square area north-east of the start
+        let area = vec![
+            Coordinate { lat: 45.05, lon: 5.05 },
+            Coordinate { lat: 45.05, lon: 5.10 },
+            Coordinate { lat: 45.10, lon: 5.10 },
+            Coordinate { lat: 45.10, lon: 5.05 },
+        ];
+        let window = BearingWindow::from_request(&sector_request(None, Some(area)))
+            .unwrap()
+            .unwrap();
+        assert!(window.start_deg > 15.0 && window.start_deg < 45.0, "{window:?}");
+        assert!(window.start_deg + window.width_deg < 75.0, "{window:?}");
+
+        for bearing in all_attempt_bearings_deg(Some(window)) {
+            assert!(bearing >= window.start_deg && bearing <= window.start_deg + window.width_deg);
+        }
+    }
+
+    #[test]
+    fn test_loops_must_enter_via_area() {
+        let area = [
+            Coordinate { lat: 45.05, lon: 5.05 },
+            Coordinate { lat: 45.05, lon: 5.10 },
+            Coordinate { lat: 45.10, lon: 5.10 },
+            Coordinate { lat: 45.10, lon: 5.05 },
+        ];
+        let start = Coordinate { lat: 45.0, lon: 5.0 };
+        let short = [start, Coordinate { lat: 45.04, lon: 5.06 }, start];
+        let through = [start, Coordinate { lat: 45.07, lon: 5.07 }, start];
+        assert!(!passes_through(&short, &area));
+        assert!(passes_through(&through, &area));
+        assert_eq!(RejectionReason::OutsideViaArea.as_str(), "outside_via_area");
+    }
+
+    #[test]
+    fn test_via_area_containing_start_is_unrestricted() {
+        let area = vec![
+            Coordinate { lat: 44.9, lon: 4.9 },
+            Coordinate { lat: 44.9, lon: 5.1 },
+            Coordinate { lat: 45.1, lon: 5.1 },
+            Coordinate { lat: 45.1, lon: 4.9 },
+        ];
+        assert_eq!(BearingWindow::from_request(&sector_request(None, Some(area))).unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_direction_is_rejected() {
+        let sector = BearingSector { min_bearing_deg: 0.0, max_bearing_deg: f64::NAN };
+        assert!(matches!(
+            BearingWindow::from_request(&sector_request(Some(sector), None)),
+            Err(LoopGenerationError::InvalidDirection(_))
+        ));
+
+        let both = sector_request(
+            Some(BearingSector { min_bearing_deg: 0.0, max_bearing_deg: 90.0 }),
+            Some(vec![Coordinate { lat: 45.1, lon: 5.0 }; 3]),
+        );
+        assert!(BearingWindow::from_request(&both).is_err());
+
+        let degenerate = sector_request(None, Some(vec![Coordinate { lat: 45.1, lon: 5.0 }; 2]));
+        assert!(BearingWindow::from_request(&degenerate).is_err());
+    }
+
+    #[test]
+    fn test_choose_direction_follows_preference() {
+        // Clockwise square loop, steep climb on the first side, gentle descent after
+        let path = vec![
+            Coordinate { lat: 45.0, lon: 5.0 },
+            Coordinate { lat: 45.003, lon: 5.0 },
+            Coordinate { lat: 45.006, lon: 5.0 },
+            Coordinate { lat: 45.006, lon: 5.004 },
+            Coordinate { lat: 45.006, lon: 5.008 },
+            Coordinate { lat: 45.003, lon: 5.008 },
+            Coordinate { lat: 45.0, lon: 5.008 },
+            Coordinate { lat: 45.0, lon: 5.004 },
+            Coordinate { lat: 45.0, lon: 5.0 },
+        ];
+        let elevations: Vec<Option<f64>> = [0.0, 100.0, 200.0, 175.0, 150.0, 125.0, 100.0, 50.0, 0.0]
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        let (reverse, directions) = choose_direction(&path, &elevations, None);
+        assert!(!reverse);
+        assert_eq!(directions[0].orientation, LoopOrientation::Clockwise);
+        assert_eq!(directions[1].orientation, LoopOrientation::Counterclockwise);
+        assert!(directions[0].max_sustained_ascent_pct > directions[1].max_sustained_ascent_pct);
+
+        let (reverse, directions) =
+            choose_direction(&path, &elevations, Some(LoopDirectionPreference::GentleAscent));
+        assert!(reverse);
+        assert_eq!(directions[0].orientation, LoopOrientation::Counterclockwise);
+
+        let (reverse, _) =
+            choose_direction(&path, &elevations, Some(LoopDirectionPreference::GentleDescent));
+        assert!(!reverse, "the steep side is already climbed");
+    }
+
+    #[test]
+    fn test_ellipse_point_keeps_focal_distance_sum() {
+        let a = Coordinate { lat: 45.0, lon: 5.0 };
+        let b = Coordinate { lat: 45.05, lon: 5.05 };
+        let sum_km = haversine_km(a, b) * 2.0;
+        for step in 0..8 {
+            let p = ellipse_point(a, b, sum_km, 2.0 * PI * step as f64 / 8.0);
+            let total = haversine_km(a, p) + haversine_km(p, b);
+            assert!((total - sum_km).abs() / sum_km < 0.01, "step {step}: {total} vs {sum_km}");
+        }
+
+        // Degenerate ellipse: the point lies on the segment
+        let p = ellipse_point(a, b, haversine_km(a, b), PI / 2.0);
+        assert!(distance_to_path_m(p, &[a, b]) < 1.0);
+    }
+
+    #[test]
+    fn test_detour_rejects_target_shorter_than_direct_route() {
+        let engine = sample_engine_with_pois(vec![]);
+        let req = DetourRouteRequest {
+            start: Coordinate { lat: 45.0, lon: 5.0 },
+            end: Coordinate { lat: 45.025, lon: 5.035 },
+            target_distance_km: 2.5,
+            distance_tolerance_km: 0.5,
+            candidate_count: 3,
+            w_pop: 1.0,
+            w_paved: 1.0,
+            max_total_ascent: None,
+            min_total_ascent: None,
+            options: Default::default(),
+        };
+        assert!(matches!(
+            generate_detours_blocking(&engine, &req),
+            Err(LoopGenerationError::TargetShorterThanDirect { .. })
+        ));
+
+        let dry = DetourRouteRequest {
+            options: crate::models::RouteOptions { max_km_without_water: Some(0.0), ..Default::default() },
+            ..req
+        };
+        assert!(matches!(
+            generate_detours_blocking(&engine, &dry),
+            Err(LoopGenerationError::InvalidConstraint(_))
+        ));
+    }
+
+    #[test]
+    fn test_jitter_is_deterministic_and_bounded() {
+        let unseeded = jittered_attempt(None, None, 1, 2, 6, 10.0);
+        assert_eq!(unseeded, (attempt_bearing(None, 1, 2, 6), 10.0));
+
+        let spacing = 2.0 * PI / 6.0;
+        for step in 0..6 {
+            let a = jittered_attempt(Some(42), None, 1, step, 6, 10.0);
+            let b = jittered_attempt(Some(42), None, 1, step, 6, 10.0);
+            assert_eq!(a, b, "same seed must give the same attempt");
+            assert!((a.0 - attempt_bearing(None, 1, step, 6)).abs() <= spacing * 0.25);
+            assert!((9.0..=11.0).contains(&a.1));
+        }
+
+        let seeded: Vec<_> = (0..6).map(|step| jittered_attempt(Some(1), None, 0, step, 6, 10.0)).collect();
+        let other: Vec<_> = (0..6).map(|step| jittered_attempt(Some(2), None, 0, step, 6, 10.0)).collect();
+        assert_ne!(seeded, other);
+    }
+
+    #[test]
+    fn test_evaluate_attempt_reports_rejection_reason() {
+        let engine = sample_engine_with_pois(vec![]);
+        let mut req = sector_request(None, None);
+        req.start = Coordinate { lat: 45.0, lon: 5.0 };
+        req.target_distance_km = 80.0;
+        let stops = LoopStops::new(&engine, &req, 40.0);
+
+        // The sample graph is far smaller than the target: the loop routes but
+        // cannot reach the requested distance.
+        let attempt = LoopAttempt {
+            ring: Some(0),
+            step: 0,
+            bearing: 0.0,
+            waypoint: Some(Coordinate { lat: 45.02, lon: 5.015 }),
+        };
+        let outcome = evaluate_attempt(&engine, &req, &stops, 1.0, &attempt).unwrap();
+        assert!(matches!(
+            outcome,
+            AttemptOutcome::Rejected(RejectionReason::DistanceOutOfTolerance)
+        ));
+        assert_eq!(RejectionReason::DistanceOutOfTolerance.as_str(), "distance_out_of_tolerance");
+    }
+
+    #[test]
+    fn test_events_are_sent_per_attempt_and_stop_with_the_receiver() {
+        let engine = sample_engine_with_pois(vec![]);
+        let mut req = sector_request(None, None);
+        req.candidate_count = 4;
+        req.target_distance_km = 60.0;
+
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let result = generate_loops_blocking(&engine, &req, Some(&events));
+        assert!(matches!(result, Err(LoopGenerationError::NoLoopFound)));
+        let mut progress = 0;
+        while let Ok(event) = received.try_recv() {
+            assert!(matches!(event, LoopEvent::Progress(ref p) if !p.accepted));
+            progress += 1;
+        }
+        assert_eq!(progress, 12, "3 rings × 4 attempts");
+
+        // Nobody listening: no attempt is started
+        drop(received);
+        let result = generate_loops_blocking(&engine, &req, Some(&events));
+        assert!(matches!(result, Err(LoopGenerationError::NoLoopFound)));
+    }
+
     #[test]
     fn test_destination_point_north() {
         // Starting point
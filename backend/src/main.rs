@@ -1,12 +1,11 @@
 // Phase 1 Complete API Server ✅
 
 use axum::{
-    response::{Html, IntoResponse},
+    response::Html,
     routing::get,
     Router,
 };
 use std::net::SocketAddr;
-use tokio;
 
 async fn root() -> Html<&'static str> {
     Html(r#"
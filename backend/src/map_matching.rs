@@ -0,0 +1,338 @@
+//! Snapping a recorded GPX track onto the routing network.
+//!
+//! The track is thinned, then matched with a hidden Markov model: each point
+//! has the ways within [`CANDIDATE_RADIUS_M`] as candidates, and Viterbi picks
+//! the sequence whose routed distances best agree with the recorded ones.
+//! Stretches that cannot be matched keep the recorded points and are reported
+//! as [`UnmatchedSection`]s; the result is described like a planned route.
+
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::{
+    elevation::compute_elevation_profile,
+    engine::{RouteEngine, TraceCandidate},
+    error::RouteError,
+    geo_utils::haversine_km,
+    models::{Coordinate, MapMatchResponse, RouteOptions, UnmatchedSection},
+};
+
+const MAX_TRACE_POINTS: usize = 50_000;
+/// Recorded points closer than this to the previous kept point are dropped
+const MIN_POINT_SPACING_M: f64 = 10.0;
+/// Ways further than this from a recorded point are not candidates
+const CANDIDATE_RADIUS_M: f64 = 50.0;
+const MAX_CANDIDATES: usize = 6;
+/// Standard deviation of the GPS position error
+const GPS_SIGMA_M: f64 = 10.0;
+/// Scale of the gap between straight and routed distances of consecutive
+/// points: larger values tolerate more meandering
+const TRANSITION_BETA_M: f64 = 30.0;
+/// Routed distance allowed between consecutive points, relative to the
+/// straight distance, plus a fixed slack for switchbacks
+const MAX_DETOUR_FACTOR: f64 = 3.0;
+const DETOUR_SLACK_M: f64 = 200.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MapMatchError {
+    #[error("invalid GPX: {0}")]
+    InvalidGpx(String),
+    #[error("the track has {0} points, between 2 and {MAX_TRACE_POINTS} are supported")]
+    TraceLength(usize),
+    #[error("no recorded point lies within {CANDIDATE_RADIUS_M} m of a way")]
+    NoMatch,
+    #[error(transparent)]
+    Gpx(#[from] RouteError),
+    #[error("map matching task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+/// Points of every track segment of a base64-encoded GPX file, in order.
+pub fn decode_trace(gpx_base64: &str) -> Result<Vec<Coordinate>, MapMatchError> {
+    let bytes = BASE64
+        .decode(gpx_base64.trim())
+        .map_err(|e| MapMatchError::InvalidGpx(e.to_string()))?;
+    let gpx = gpx::read(bytes.as_slice()).map_err(|e| MapMatchError::InvalidGpx(e.to_string()))?;
+    let trace: Vec<Coordinate> = gpx
+        .tracks
+        .iter()
+        .flat_map(|track| &track.segments)
+        .flat_map(|segment| &segment.points)
+        .map(|point| {
+            let p = point.point();
+            Coordinate { lat: p.y(), lon: p.x() }
+        })
+        .collect();
+    if !(2..=MAX_TRACE_POINTS).contains(&trace.len()) {
+        return Err(MapMatchError::TraceLength(trace.len()));
+    }
+    Ok(trace)
+}
+
+/// Snap a recorded track onto the graph and describe it like a planned
+/// route: elevation, time, difficulty and the usual per-route reports.
+pub async fn map_match(engine: &Arc<RouteEngine>, trace: Vec<Coordinate>) -> Result<MapMatchResponse, MapMatchError> {
+    let engine = Arc::clone(engine);
+    tokio::task::spawn_blocking(move || map_match_blocking(&engine, &trace)).await?
+}
+
+fn map_match_blocking(engine: &RouteEngine, trace: &[Coordinate]) -> Result<MapMatchResponse, MapMatchError> {
+    let (path, unmatched) = match_trace(engine, trace)?;
+
+    let elevation_profile = match compute_elevation_profile(&path) {
+        Ok(profile) => Some(profile),
+        Err(e) => {
+            tracing::warn!("Matched track without elevation: {}", e);
+            None
+        }
+    };
+    let route = engine.route_response(path, &[], elevation_profile, &RouteOptions::default())?;
+    Ok(MapMatchResponse { route, unmatched })
+}
+
+/// Viterbi column of a recorded point: its candidates and, per candidate,
+/// the log probability of the best sequence ending there with the index of
+/// the previous candidate
+type LatticeColumn = (Coordinate, Vec<TraceCandidate>, Vec<(f64, usize)>);
+
+/// A maximal run of recorded points matched with consistent transitions,
+/// or the recorded points between two runs.
+enum Piece {
+    Matched(Vec<TraceCandidate>),
+    Unmatched(Vec<Coordinate>),
+}
+
+/// Hidden Markov Model map matching (Newson & Krumm, 2009).
+///
+/// # Algorithm
+/// 1. Drop recorded points within 10 m of the previous one
+/// 2. Candidates of each point are the ways within 50 m, found through the
+///    engine's road-point index; the emission probability is Gaussian in
+///    the GPS error
+/// 3. The transition probability between candidates of consecutive points
+///    decays exponentially with the difference between the routed and the
+///    straight distance
+/// 4. Viterbi picks the most likely candidate sequence. A point without
+///    candidates, or without any transition to the previous one, ends the
+///    run; the next point starts a new one
+///
+/// Returns the matched path, made of the network paths between the chosen
+/// candidates and, between runs, of the recorded points, and the unmatched
+/// stretches.
+pub fn match_trace(
+    engine: &RouteEngine,
+    trace: &[Coordinate],
+) -> Result<(Vec<Coordinate>, Vec<UnmatchedSection>), MapMatchError> {
+    let mut points: Vec<Coordinate> = Vec::with_capacity(trace.len());
+    for &point in trace {
+        if points.last().is_none_or(|&last| haversine_km(last, point) * 1000.0 >= MIN_POINT_SPACING_M) {
+            points.push(point);
+        }
+    }
+
+    let emission = |candidate: &TraceCandidate| -0.5 * (candidate.distance_m / GPS_SIGMA_M).powi(2);
+    let mut pieces: Vec<Piece> = Vec::new();
+    // Viterbi lattice of the current run
+    let mut lattice: Vec<LatticeColumn> = Vec::new();
+
+    for &point in &points {
+        let candidates = engine.trace_candidates(point, CANDIDATE_RADIUS_M, MAX_CANDIDATES);
+        if candidates.is_empty() {
+            close_run(&mut lattice, &mut pieces);
+            match pieces.last_mut() {
+                Some(Piece::Unmatched(recorded)) => recorded.push(point),
+                _ => pieces.push(Piece::Unmatched(vec![point])),
+            }
+            continue;
+        }
+
+        let mut scores: Vec<(f64, usize)> = vec![(f64::NEG_INFINITY, 0); candidates.len()];
+        if let Some((previous_point, previous, previous_scores)) = lattice.last() {
+            let straight_m = haversine_km(*previous_point, point) * 1000.0;
+            let max_km = (straight_m * MAX_DETOUR_FACTOR + DETOUR_SLACK_M) / 1000.0;
+            for (j, from) in previous.iter().enumerate() {
+                let base = previous_scores[j].0;
+                if !base.is_finite() {
+                    continue;
+                }
+                let distances = engine.candidate_distances(from, &candidates, max_km);
+                for (k, routed_km) in distances.into_iter().enumerate() {
+                    let Some(routed_km) = routed_km else {
+                        continue;
+                    };
+                    let transition = -(routed_km * 1000.0 - straight_m).abs() / TRANSITION_BETA_M;
+                    let score = base + transition + emission(&candidates[k]);
+                    if score > scores[k].0 {
+                        scores[k] = (score, j);
+                    }
+                }
+            }
+            if scores.iter().all(|(score, _)| !score.is_finite()) {
+                close_run(&mut lattice, &mut pieces);
+            }
+        }
+        if lattice.is_empty() {
+            scores = candidates.iter().map(|c| (emission(c), 0)).collect();
+        }
+        lattice.push((point, candidates, scores));
+    }
+    close_run(&mut lattice, &mut pieces);
+
+    if !pieces.iter().any(|piece| matches!(piece, Piece::Matched(_))) {
+        return Err(MapMatchError::NoMatch);
+    }
+    Ok(assemble(engine, pieces))
+}
+
+/// Backtrack the best candidate sequence of the current run.
+fn close_run(
+    lattice: &mut Vec<LatticeColumn>,
+    pieces: &mut Vec<Piece>,
+) {
+    let Some((_, last_candidates, last_scores)) = lattice.last() else {
+        return;
+    };
+    let Some(mut best) = (0..last_candidates.len())
+        .max_by(|&a, &b| last_scores[a].0.partial_cmp(&last_scores[b].0).unwrap_or(std::cmp::Ordering::Equal))
+    else {
+        lattice.clear();
+        return;
+    };
+    let mut matched = Vec::with_capacity(lattice.len());
+    for (_, candidates, scores) in lattice.iter().rev() {
+        matched.push(candidates[best]);
+        best = scores[best].1;
+    }
+    matched.reverse();
+    lattice.clear();
+    pieces.push(Piece::Matched(matched));
+}
+
+/// Join the runs and the recorded points between them into one path, and
+/// measure the unmatched stretches along it, from the last matched point
+/// before them to the first one after. Consecutive runs are joined by a
+/// straight line, reported as unmatched.
+fn assemble(engine: &RouteEngine, pieces: Vec<Piece>) -> (Vec<Coordinate>, Vec<UnmatchedSection>) {
+    fn push(path: &mut Vec<Coordinate>, along_km: &mut f64, coord: Coordinate) {
+        if let Some(&last) = path.last() {
+            if (last.lat - coord.lat).abs() < 1e-9 && (last.lon - coord.lon).abs() < 1e-9 {
+                return;
+            }
+            *along_km += haversine_km(last, coord);
+        }
+        path.push(coord);
+    }
+
+    let mut path: Vec<Coordinate> = Vec::new();
+    let mut along_km = 0.0;
+    let mut unmatched: Vec<UnmatchedSection> = Vec::new();
+    let mut open: Option<UnmatchedSection> = None;
+
+    for piece in pieces {
+        match piece {
+            Piece::Unmatched(recorded) => {
+                let section = open.get_or_insert(UnmatchedSection { from_km: along_km, to_km: along_km, point_count: 0 });
+                section.point_count += recorded.len();
+                for coord in recorded {
+                    push(&mut path, &mut along_km, coord);
+                }
+            }
+            Piece::Matched(run) => {
+                let gap = (!path.is_empty()).then(|| {
+                    open.take()
+                        .unwrap_or(UnmatchedSection { from_km: along_km, to_km: along_km, point_count: 0 })
+                });
+                push(&mut path, &mut along_km, run[0].coord);
+                if let Some(mut section) = gap {
+                    section.to_km = along_km;
+                    unmatched.push(section);
+                }
+                for pair in run.windows(2) {
+                    let straight_km = haversine_km(pair[0].coord, pair[1].coord);
+                    let max_km = straight_km * MAX_DETOUR_FACTOR + 2.0 * DETOUR_SLACK_M / 1000.0;
+                    let leg = engine
+                        .candidate_path(&pair[0], &pair[1], max_km)
+                        .unwrap_or_else(|| vec![pair[0].coord, pair[1].coord]);
+                    for coord in leg {
+                        push(&mut path, &mut along_km, coord);
+                    }
+                }
+            }
+        }
+    }
+    if let Some(mut section) = open {
+        section.to_km = along_km;
+        unmatched.push(section);
+    }
+    (path, unmatched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpx_export::encode_route_as_gpx;
+    use crate::graph::test_graph::detour_test_graph;
+
+    /// A straight road from (45.0, 5.0) to (45.0, 5.01) and a path over
+    /// (45.003, 5.005) between the same nodes.
+    fn engine() -> RouteEngine {
+        RouteEngine::from_graph_file(detour_test_graph()).expect("map matching test graph")
+    }
+
+    /// Points every ~50 m from `a` to `b`, ~11 m north of the line.
+    fn recorded(a: Coordinate, b: Coordinate) -> Vec<Coordinate> {
+        (0..10)
+            .map(|i| {
+                let c = a.interpolate(b, i as f64 / 10.0);
+                Coordinate { lat: c.lat + 0.0001, lon: c.lon }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_noisy_trace_follows_the_path_it_was_recorded_on() {
+        let engine = engine();
+        let (start, top, end) = (
+            Coordinate { lat: 45.0, lon: 5.0 },
+            Coordinate { lat: 45.003, lon: 5.005 },
+            Coordinate { lat: 45.0, lon: 5.01 },
+        );
+        let mut trace = recorded(start, top);
+        trace.extend(recorded(top, end));
+        trace.push(end);
+
+        let (path, unmatched) = match_trace(&engine, &trace).expect("match");
+        assert!(unmatched.is_empty());
+        assert!(path.iter().any(|c| (c.lat - 45.003).abs() < 1e-9), "goes over the top node");
+        assert!(path.iter().all(|c| c.lat <= 45.003 + 1e-9), "on the network, not on the noise");
+        let surfaces = engine.surface_breakdown(&engine.path_edges(&path)).expect("matched path surfaces");
+        assert_eq!(surfaces.len(), 1, "{surfaces:?}");
+        assert!((surfaces[0].1 - crate::geo_utils::approximate_distance_km(&path)).abs() < 1e-9);
+
+        // A stretch walked 200 m off every way
+        let mut detour = recorded(start, top);
+        detour.extend([Coordinate { lat: 45.005, lon: 5.006 }, Coordinate { lat: 45.005, lon: 5.007 }]);
+        detour.extend(recorded(top, end));
+        let (path, unmatched) = match_trace(&engine, &detour).expect("match");
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0].point_count, 2);
+        assert!(unmatched[0].from_km > 0.4 && unmatched[0].to_km > unmatched[0].from_km + 0.4);
+        assert!(path.iter().any(|c| (c.lat - 45.005).abs() < 1e-9));
+
+        let nowhere = [Coordinate { lat: 46.0, lon: 6.0 }, Coordinate { lat: 46.001, lon: 6.0 }];
+        assert!(matches!(match_trace(&engine, &nowhere), Err(MapMatchError::NoMatch)));
+    }
+
+    #[test]
+    fn test_decode_trace_reads_exported_gpx() {
+        let path = [Coordinate { lat: 45.0, lon: 5.0 }, Coordinate { lat: 45.001, lon: 5.002 }];
+        let trace = decode_trace(&encode_route_as_gpx(&path).unwrap()).expect("trace");
+        assert_eq!(trace.len(), 2);
+        assert!((trace[1].lon - 5.002).abs() < 1e-9);
+
+        assert!(matches!(decode_trace("not base64!"), Err(MapMatchError::InvalidGpx(_))));
+        let single = encode_route_as_gpx(&path[..1]).unwrap();
+        assert!(matches!(decode_trace(&single), Err(MapMatchError::TraceLength(1))));
+    }
+}
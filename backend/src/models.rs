@@ -1,5 +1,13 @@
 pub use shared::{
-    default_distance_tolerance_km, default_loop_candidate_count, default_weight, ApiError,
-    Coordinate, LoopCandidate, LoopRouteRequest, LoopRouteResponse, RouteBounds, RouteMetadata,
-    RouteRequest, RouteResponse, SurfaceType,
+    default_distance_tolerance_km, default_hours_per_day, default_loop_candidate_count,
+    default_nearest_count, default_weight, ApiError, BearingSector, CheminsNoirsScore, Coordinate,
+    CyclingReport, DetourCandidate, DetourRouteRequest, DetourRouteResponse, EdgeCost,
+    ElevationProfile, GeoJsonMultiPoint, GradeClass, HardestSection, ItineraryRequest,
+    ItineraryResponse, ItineraryStage, Language, LoopAttemptStats, LoopCandidate,
+    LoopConstraintKind, LoopConstraintStatus, LoopDirectionGrades, LoopDirectionPreference,
+    LoopOrientation, LoopProgress, LoopRouteRequest, LoopRouteResponse, LoopSort, Maneuver,
+    ManeuverType, MapMatchRequest, MapMatchResponse, NearestRequest, NearestResponse,
+    OffTrailSection, RouteBounds, RouteDebug, RouteLeg, RouteMetadata, RouteOptions, RouteRequest,
+    RouteResponse, RoutingProfile, SnapCandidate, SnapChoice, StageStop, SurfaceType,
+    UnmatchedSection, WaterReport, WaterStop, WaymarkedSection,
 };
@@ -0,0 +1,207 @@
+//! Off-trail segments across the DEM.
+//!
+//! A waypoint far from any way (a summit, a col) is joined to the network by
+//! a least-cost path over a grid laid on the terrain: Dijkstra between
+//! neighbouring cells, each step costing its walking time by Tobler's hiking
+//! function. Cells without elevation are impassable.
+
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::models::Coordinate;
+
+/// Graph nodes further than this from the waypoint are not considered
+pub const OFF_TRAIL_MAX_KM: f64 = 5.0;
+/// Finest grid spacing, close to the resolution of the regional DEM
+const MIN_CELL_M: f64 = 25.0;
+/// The grid is coarsened so that it never exceeds this many cells per side
+const MAX_CELLS_PER_SIDE: usize = 300;
+/// Margin around the waypoint and the candidate nodes, to allow detours
+const MARGIN_M: f64 = 300.0;
+const METERS_PER_DEG_LAT: f64 = 111_320.0;
+
+/// Walking speed in km/h on a slope (rise over run), by Tobler's hiking
+/// function: 6 km/h at a 5 % descent, slower either way.
+pub fn tobler_speed_kmh(slope: f64) -> f64 {
+    6.0 * (-3.5 * (slope + 0.05).abs()).exp()
+}
+
+/// Local metric frame centred on a point, precise enough at grid scale.
+struct Frame {
+    origin: Coordinate,
+    meters_per_deg_lon: f64,
+}
+
+impl Frame {
+    fn new(origin: Coordinate) -> Self {
+        Self { origin, meters_per_deg_lon: METERS_PER_DEG_LAT * origin.lat.to_radians().cos() }
+    }
+
+    fn to_xy(&self, c: Coordinate) -> (f64, f64) {
+        (
+            (c.lon - self.origin.lon) * self.meters_per_deg_lon,
+            (c.lat - self.origin.lat) * METERS_PER_DEG_LAT,
+        )
+    }
+
+    fn to_coord(&self, x: f64, y: f64) -> Coordinate {
+        Coordinate {
+            lat: self.origin.lat + y / METERS_PER_DEG_LAT,
+            lon: self.origin.lon + x / self.meters_per_deg_lon,
+        }
+    }
+}
+
+#[derive(PartialEq)]
+struct Visit {
+    hours: f64,
+    cell: usize,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Min-heap on walking time
+        other.hours.partial_cmp(&self.hours).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Quickest walk over the terrain from `from` to any of `targets`: the index
+/// of the target reached and the path, starting at `from` and ending at that
+/// target. `None` when the terrain is unknown around `from` or no target can
+/// be reached.
+pub fn least_cost_path(
+    elevation: impl Fn(Coordinate) -> Option<f64>,
+    from: Coordinate,
+    targets: &[Coordinate],
+) -> Option<(usize, Vec<Coordinate>)> {
+    if targets.is_empty() {
+        return None;
+    }
+    let frame = Frame::new(from);
+    let points: Vec<(f64, f64)> = std::iter::once(from)
+        .chain(targets.iter().copied())
+        .map(|c| frame.to_xy(c))
+        .collect();
+    let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min) - MARGIN_M;
+    let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max) + MARGIN_M;
+    let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min) - MARGIN_M;
+    let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max) + MARGIN_M;
+    let cell_m = MIN_CELL_M.max((max_x - min_x).max(max_y - min_y) / MAX_CELLS_PER_SIDE as f64);
+    let cols = ((max_x - min_x) / cell_m).ceil() as usize + 1;
+    let rows = ((max_y - min_y) / cell_m).ceil() as usize + 1;
+
+    let centre = |cell: usize| (min_x + (cell % cols) as f64 * cell_m, min_y + (cell / cols) as f64 * cell_m);
+    let cell_of = |(x, y): (f64, f64)| {
+        let col = ((x - min_x) / cell_m).round() as usize;
+        let row = ((y - min_y) / cell_m).round() as usize;
+        row.min(rows - 1) * cols + col.min(cols - 1)
+    };
+    let heights: Vec<Option<f64>> = (0..rows * cols)
+        .map(|cell| {
+            let (x, y) = centre(cell);
+            elevation(frame.to_coord(x, y))
+        })
+        .collect();
+
+    let start = cell_of(points[0]);
+    heights[start]?;
+    let mut target_of = vec![None; rows * cols];
+    for (i, &point) in points[1..].iter().enumerate() {
+        target_of[cell_of(point)].get_or_insert(i);
+    }
+
+    let mut best = vec![f64::INFINITY; rows * cols];
+    let mut previous = vec![usize::MAX; rows * cols];
+    let mut queue = BinaryHeap::new();
+    best[start] = 0.0;
+    queue.push(Visit { hours: 0.0, cell: start });
+
+    while let Some(Visit { hours, cell }) = queue.pop() {
+        if hours > best[cell] {
+            continue;
+        }
+        if let Some(target) = target_of[cell] {
+            let mut cells = vec![cell];
+            while let Some(&last) = cells.last().filter(|&&c| c != start) {
+                cells.push(previous[last]);
+            }
+            // Cell centres between the exact endpoints
+            let mut path = vec![from];
+            path.extend(cells.iter().rev().skip(1).take(cells.len().saturating_sub(2)).map(|&c| {
+                let (x, y) = centre(c);
+                frame.to_coord(x, y)
+            }));
+            path.push(targets[target]);
+            return Some((target, path));
+        }
+
+        let (row, col) = ((cell / cols) as isize, (cell % cols) as isize);
+        let here = heights[cell]?;
+        for (dr, dc) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
+            let (r, c) = (row + dr, col + dc);
+            if r < 0 || c < 0 || r >= rows as isize || c >= cols as isize {
+                continue;
+            }
+            let next = r as usize * cols + c as usize;
+            let Some(there) = heights[next] else {
+                continue;
+            };
+            let run_m = cell_m * ((dr * dr + dc * dc) as f64).sqrt();
+            let step = run_m / 1000.0 / tobler_speed_kmh((there - here) / run_m);
+            if hours + step < best[next] {
+                best[next] = hours + step;
+                previous[next] = cell;
+                queue.push(Visit { hours: hours + step, cell: next });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_utils::haversine_km;
+
+    #[test]
+    fn test_tobler_is_fastest_slightly_downhill() {
+        assert!((tobler_speed_kmh(-0.05) - 6.0).abs() < 1e-12);
+        assert!(tobler_speed_kmh(0.0) > tobler_speed_kmh(0.3));
+        assert!(tobler_speed_kmh(-0.05) > tobler_speed_kmh(-0.4));
+    }
+
+    #[test]
+    fn test_least_cost_path_walks_around_a_cliff() {
+        // A 300 m high wall runs north-south between the waypoint and the
+        // target, with a gap in the south
+        let from = Coordinate { lat: 45.0, lon: 5.0 };
+        let wall_lon = 5.005;
+        let elevation = |c: Coordinate| {
+            let on_wall = (c.lon - wall_lon).abs() < 0.0006 && c.lat > 44.999;
+            Some(if on_wall { 1300.0 } else { 1000.0 })
+        };
+
+        let beyond_wall = Coordinate { lat: 45.0, lon: 5.01 };
+        let (target, path) = least_cost_path(elevation, from, &[beyond_wall]).expect("path");
+        assert_eq!(target, 0);
+        assert_eq!((path[0].lat, path[0].lon), (from.lat, from.lon));
+        let last = path[path.len() - 1];
+        assert_eq!((last.lat, last.lon), (beyond_wall.lat, beyond_wall.lon));
+        assert!(path.iter().any(|c| c.lat < 44.999), "goes through the gap");
+        let length_km: f64 = path.windows(2).map(|pair| haversine_km(pair[0], pair[1])).sum();
+        assert!(length_km > 1.1 * haversine_km(from, beyond_wall), "got {length_km}");
+
+        let same_side = Coordinate { lat: 45.004, lon: 4.998 };
+        let (target, _) = least_cost_path(elevation, from, &[beyond_wall, same_side]).expect("path");
+        assert_eq!(target, 1);
+
+        assert!(least_cost_path(|_| None, from, &[beyond_wall]).is_none(), "no DEM");
+    }
+}
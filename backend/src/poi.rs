@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 use crate::graph::BoundingBox;
+use crate::models::Coordinate;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Poi {
@@ -12,8 +13,55 @@ pub struct Poi {
     pub name: Option<String>,
 }
 
+impl Poi {
+    /// Build a POI from an OSM node's tags, or `None` if no tag is of interest.
+    pub fn from_tags<'a>(lat: f64, lon: f64, tags: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut poi_type = None;
+        let mut name = None;
+
+        for (key, value) in tags {
+            if key == "name" {
+                name = Some(value.to_string());
+            }
+            if poi_type.is_none() {
+                poi_type = classify_tag(key, value);
+            }
+        }
+
+        Some(Self {
+            lat,
+            lon,
+            poi_type: poi_type?.to_string(),
+            name,
+        })
+    }
+
+    pub fn coordinate(&self) -> Coordinate {
+        Coordinate {
+            lat: self.lat,
+            lon: self.lon,
+        }
+    }
+}
+
+fn classify_tag(key: &str, value: &str) -> Option<&'static str> {
+    match (key, value) {
+        ("amenity", "drinking_water") => Some("water"),
+        ("amenity", "shelter") => Some("shelter"),
+        ("amenity", "parking") => Some("parking"),
+        ("tourism", "alpine_hut") => Some("hut"),
+        ("tourism", "viewpoint") => Some("viewpoint"),
+        ("natural", "peak") => Some("peak"),
+        ("natural", "saddle") => Some("saddle"),
+        ("natural", "spring") => Some("water"),
+        ("place", "village" | "hamlet" | "town") => Some("village"),
+        _ => None,
+    }
+}
+
 /// Extract POIs from a PBF file within a bounding box.
-/// Looks for: drinking_water, shelter, alpine_hut, peak, saddle, parking, viewpoint
+/// Looks for: drinking_water, shelter, alpine_hut, peak, saddle, parking, viewpoint,
+/// and villages (place=village/hamlet/town)
 pub fn extract_pois_from_pbf(pbf_path: &Path, bbox: BoundingBox) -> Result<Vec<Poi>, String> {
     let reader =
         ElementReader::from_path(pbf_path).map_err(|e| format!("Failed to open PBF: {}", e))?;
@@ -42,36 +90,7 @@ pub fn extract_pois_from_pbf(pbf_path: &Path, bbox: BoundingBox) -> Result<Vec<P
                     return Vec::new();
                 }
 
-                let name = tags
-                    .iter()
-                    .find(|(k, _)| *k == "name")
-                    .map(|(_, v)| v.to_string());
-
-                // Check for POI tags
-                for (key, value) in &tags {
-                    let poi_type = match (*key, *value) {
-                        ("amenity", "drinking_water") => Some("water"),
-                        ("amenity", "shelter") => Some("shelter"),
-                        ("amenity", "parking") => Some("parking"),
-                        ("tourism", "alpine_hut") => Some("hut"),
-                        ("tourism", "viewpoint") => Some("viewpoint"),
-                        ("natural", "peak") => Some("peak"),
-                        ("natural", "saddle") => Some("saddle"),
-                        ("natural", "spring") => Some("water"),
-                        _ => None,
-                    };
-
-                    if let Some(pt) = poi_type {
-                        return vec![Poi {
-                            lat,
-                            lon,
-                            poi_type: pt.to_string(),
-                            name,
-                        }];
-                    }
-                }
-
-                Vec::new()
+                Poi::from_tags(lat, lon, tags).into_iter().collect()
             },
             Vec::new,
             |mut acc, pois| {
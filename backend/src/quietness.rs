@@ -0,0 +1,159 @@
+//! Noise from major roads and railways, precomputed per edge at graph build time.
+//!
+//! Every edge stores its distance to the nearest motorway/trunk/primary road
+//! and to the nearest railway (see [`EdgeEnvironment`]); the engine turns
+//! those into a noise level between 0 and 1 with [`noise_level`].
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::{
+    geo_utils::{sample_polyline, SegmentGrid},
+    graph::{EdgeEnvironment, EdgeRecord, NodeRecord},
+    models::Coordinate,
+};
+
+/// Noise sources further away than this are ignored
+pub const NOISE_HORIZON_M: f64 = 500.0;
+/// Trains pass now and then: a railway counts for less than a busy road
+const RAILWAY_NOISE_FACTOR: f64 = 0.7;
+/// Grid cell size of the noise index, in degrees
+const CELL_DEG: f64 = 0.005;
+/// Edges are sampled at least this often when measuring distances
+const SAMPLE_SPACING_M: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseSource {
+    MajorRoad,
+    Railway,
+}
+
+impl NoiseSource {
+    pub fn from_tag(key: &str, value: &str) -> Option<Self> {
+        match (key, value) {
+            (
+                "highway",
+                "motorway" | "motorway_link" | "trunk" | "trunk_link" | "primary" | "primary_link",
+            ) => Some(Self::MajorRoad),
+            ("railway", "rail" | "light_rail" | "narrow_gauge") => Some(Self::Railway),
+            _ => None,
+        }
+    }
+}
+
+/// Noise level of an edge, from 0 (quiet) to 1 (on a major road), decaying
+/// linearly with the distance to the source.
+pub fn noise_level(environment: &EdgeEnvironment) -> f64 {
+    let decay = |distance_m: Option<f64>| {
+        distance_m.map_or(0.0, |d| (1.0 - d / NOISE_HORIZON_M).clamp(0.0, 1.0))
+    };
+    decay(environment.major_road_distance_m)
+        .max(RAILWAY_NOISE_FACTOR * decay(environment.railway_distance_m))
+}
+
+/// Grid of noise source segments, for nearest-source queries.
+pub struct NoiseIndex {
+    grid: SegmentGrid<NoiseSource>,
+}
+
+impl NoiseIndex {
+    /// Index the segments of noise source ways; nodes missing from `nodes`
+    /// split the way instead of being bridged.
+    pub fn from_ways(
+        ways: &[(NoiseSource, Vec<i64>)],
+        nodes: &HashMap<i64, (f64, f64, Option<f64>)>,
+    ) -> Self {
+        let mut grid = SegmentGrid::new(CELL_DEG);
+        for (source, refs) in ways {
+            let coords: Vec<Option<Coordinate>> = refs
+                .iter()
+                .map(|id| nodes.get(id).map(|&(lat, lon, _)| Coordinate { lat, lon }))
+                .collect();
+            for pair in coords.windows(2) {
+                if let (Some(a), Some(b)) = (pair[0], pair[1]) {
+                    grid.insert(*source, a, b);
+                }
+            }
+        }
+        Self { grid }
+    }
+
+    /// Distance from `point` to the nearest source of the given kind, if
+    /// within [`NOISE_HORIZON_M`].
+    pub fn nearest_m(&self, point: Coordinate, source: NoiseSource) -> Option<f64> {
+        self.grid.nearest_m(point, source, NOISE_HORIZON_M)
+    }
+
+    /// Fill the noise distances of every edge. `nodes` must be indexed by
+    /// graph id - 1, as produced by the graph builder.
+    pub fn annotate(&self, edges: &mut [EdgeRecord], nodes: &[NodeRecord]) {
+        if self.grid.is_empty() {
+            return;
+        }
+        edges.par_iter_mut().for_each(|edge| {
+            let Some(polyline) = edge.polyline(nodes) else {
+                return;
+            };
+            let samples = sample_polyline(&polyline, SAMPLE_SPACING_M);
+            let nearest = |source| {
+                samples
+                    .iter()
+                    .filter_map(|&p| self.nearest_m(p, source))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            };
+            edge.environment.major_road_distance_m = nearest(NoiseSource::MajorRoad);
+            edge.environment.railway_distance_m = nearest(NoiseSource::Railway);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::test_graph::{edge, node};
+
+    #[test]
+    fn test_annotate_measures_distance_to_major_roads_and_railways() {
+        // Trunk road running north along lon 5.0, railway along lon 5.01
+        let nodes: HashMap<i64, (f64, f64, Option<f64>)> = HashMap::from([
+            (1, (45.0, 5.0, None)),
+            (2, (45.02, 5.0, None)),
+            (3, (45.0, 5.01, None)),
+            (4, (45.02, 5.01, None)),
+        ]);
+        let index = NoiseIndex::from_ways(
+            &[(NoiseSource::MajorRoad, vec![1, 2]), (NoiseSource::Railway, vec![3, 4])],
+            &nodes,
+        );
+
+        // Path parallel to the road, ~79 m east of it (0.001° of longitude)
+        let graph_nodes = vec![
+            node(1, 45.005, 5.001),
+            node(2, 45.015, 5.001),
+            node(3, 45.015, 5.03),
+            node(4, 45.015, 5.04),
+        ];
+        let mut edges = vec![edge(1, 2, 0.0), edge(3, 4, 0.0)];
+        index.annotate(&mut edges, &graph_nodes);
+
+        let road = edges[0].environment.major_road_distance_m.unwrap();
+        assert!((road - 78.7).abs() < 1.0, "got {road}");
+        assert_eq!(edges[0].environment.railway_distance_m, None, "railway ~710 m away");
+        assert_eq!(edges[1].environment, EdgeEnvironment::default(), "far from both sources");
+
+        let level = noise_level(&edges[0].environment);
+        assert!(level > 0.8 && level <= 1.0, "got {level}");
+        assert_eq!(noise_level(&EdgeEnvironment::default()), 0.0);
+    }
+
+    #[test]
+    fn test_railways_count_less_than_roads() {
+        let road = EdgeEnvironment { major_road_distance_m: Some(0.0), ..Default::default() };
+        let rail = EdgeEnvironment { railway_distance_m: Some(0.0), ..Default::default() };
+        assert_eq!(noise_level(&road), 1.0);
+        assert!((noise_level(&rail) - RAILWAY_NOISE_FACTOR).abs() < 1e-12);
+        assert_eq!(NoiseSource::from_tag("railway", "abandoned"), None);
+        assert_eq!(NoiseSource::from_tag("highway", "trunk_link"), Some(NoiseSource::MajorRoad));
+    }
+}
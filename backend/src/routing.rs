@@ -72,10 +72,79 @@ pub fn rate_difficulty(
     }
 }
 
+/// Steepest average grades, in percent, over any stretch of at least
+/// `window_m` meters of horizontal distance, in the direction of travel.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SustainedGrades {
+    pub ascent_pct: f64,
+    pub descent_pct: f64,
+}
+
+/// Scan every stretch starting at a path point and ending at the first point
+/// at least `window_m` further along. Points without elevation are skipped.
+/// Both grades are 0 when the path is shorter than the window.
+pub fn max_sustained_grades(
+    elevations: &[Option<f64>],
+    path: &[Coordinate],
+    window_m: f64,
+) -> SustainedGrades {
+    let n = path.len().min(elevations.len());
+    let mut cumulative_m = Vec::with_capacity(n);
+    let mut total = 0.0;
+    for i in 0..n {
+        if i > 0 {
+            total += haversine_km(path[i - 1], path[i]) * 1000.0;
+        }
+        cumulative_m.push(total);
+    }
+
+    let mut grades = SustainedGrades::default();
+    let mut end = 0;
+    for start in 0..n {
+        end = end.max(start + 1);
+        while end < n && cumulative_m[end] - cumulative_m[start] < window_m {
+            end += 1;
+        }
+        if end >= n {
+            break;
+        }
+        if let (Some(e1), Some(e2)) = (elevations[start], elevations[end]) {
+            let grade = (e2 - e1) / (cumulative_m[end] - cumulative_m[start]) * 100.0;
+            grades.ascent_pct = grades.ascent_pct.max(grade);
+            grades.descent_pct = grades.descent_pct.max(-grade);
+        }
+    }
+    grades
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_max_sustained_grades_depend_on_direction() {
+        // ~111 m between points: a steep 40 m wall then a gentle 40 m run-out
+        let path: Vec<Coordinate> = (0..6)
+            .map(|i| Coordinate { lat: 45.0 + i as f64 * 0.001, lon: 5.0 })
+            .collect();
+        let elevations = vec![Some(0.0), Some(40.0), Some(30.0), Some(20.0), Some(10.0), Some(0.0)];
+
+        let forward = max_sustained_grades(&elevations, &path, 100.0);
+        assert!((forward.ascent_pct - 36.0).abs() < 0.5, "{forward:?}");
+        assert!((forward.descent_pct - 9.0).abs() < 0.5, "{forward:?}");
+
+        let reversed_path: Vec<_> = path.iter().rev().copied().collect();
+        let reversed_elevations: Vec<_> = elevations.iter().rev().copied().collect();
+        let reverse = max_sustained_grades(&reversed_elevations, &reversed_path, 100.0);
+        assert!((reverse.ascent_pct - forward.descent_pct).abs() < 1e-9);
+        assert!((reverse.descent_pct - forward.ascent_pct).abs() < 1e-9);
+
+        assert_eq!(
+            max_sustained_grades(&elevations, &path, 10_000.0),
+            SustainedGrades::default()
+        );
+    }
+
     // Property-based tests using proptest
     mod proptests {
         use super::*;
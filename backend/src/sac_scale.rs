@@ -0,0 +1,115 @@
+//! SAC hiking scale and trail visibility.
+//!
+//! `sac_scale=*` grades mountain paths from T1 (`hiking`) to T6
+//! (`difficult_alpine_hiking`). A faint trail is harder than its tag says:
+//! [`effective_sac_scale`] raises the grade of ways with poor
+//! `trail_visibility` to the level where the SAC scale expects route finding.
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph::WayAttributes;
+
+/// `sac_scale=*` value as a level from 1 (T1) to 6 (T6).
+pub fn parse_sac_scale(value: &str) -> Option<u8> {
+    match value {
+        "hiking" => Some(1),
+        "mountain_hiking" => Some(2),
+        "demanding_mountain_hiking" => Some(3),
+        "alpine_hiking" => Some(4),
+        "demanding_alpine_hiking" => Some(5),
+        "difficult_alpine_hiking" => Some(6),
+        _ => None,
+    }
+}
+
+/// `trail_visibility=*`, ordered from the easiest trail to follow to the
+/// hardest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TrailVisibility {
+    Excellent,
+    Good,
+    Intermediate,
+    Bad,
+    Horrible,
+    No,
+}
+
+impl TrailVisibility {
+    pub fn from_tag(value: &str) -> Option<Self> {
+        match value {
+            "excellent" => Some(Self::Excellent),
+            "good" => Some(Self::Good),
+            "intermediate" => Some(Self::Intermediate),
+            "bad" => Some(Self::Bad),
+            "horrible" => Some(Self::Horrible),
+            "no" => Some(Self::No),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Excellent => "excellent",
+            Self::Good => "good",
+            Self::Intermediate => "intermediate",
+            Self::Bad => "bad",
+            Self::Horrible => "horrible",
+            Self::No => "no",
+        }
+    }
+
+    /// Lowest SAC level of a trail this hard to follow: T3 where the trail
+    /// is "not necessarily visible everywhere", T4 where it "may be
+    /// missing", T5 for trail-less ground.
+    fn sac_floor(self) -> Option<u8> {
+        match self {
+            Self::Bad => Some(3),
+            Self::Horrible => Some(4),
+            Self::No => Some(5),
+            Self::Excellent | Self::Good | Self::Intermediate => None,
+        }
+    }
+}
+
+/// SAC level of a way, taking trail visibility into account.
+pub fn effective_sac_scale(attributes: &WayAttributes) -> Option<u8> {
+    let floor = attributes.trail_visibility.and_then(TrailVisibility::sac_floor);
+    match (attributes.sac_scale, floor) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `T1` to `T6`.
+pub fn label(sac_scale: u8) -> String {
+    format!("T{sac_scale}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(tags: &[(&str, &str)]) -> WayAttributes {
+        let tags: Vec<(String, String)> =
+            tags.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        WayAttributes::from_tags(&tags)
+    }
+
+    #[test]
+    fn test_trail_visibility_raises_sac_scale() {
+        let ridge = attributes(&[("sac_scale", "demanding_alpine_hiking")]);
+        assert_eq!(ridge.sac_scale, Some(5));
+        assert_eq!(effective_sac_scale(&ridge), Some(5));
+
+        let faint = attributes(&[("sac_scale", "mountain_hiking"), ("trail_visibility", "horrible")]);
+        assert_eq!(effective_sac_scale(&faint), Some(4));
+        let clear = attributes(&[("sac_scale", "mountain_hiking"), ("trail_visibility", "good")]);
+        assert_eq!(effective_sac_scale(&clear), Some(2));
+        assert_eq!(effective_sac_scale(&attributes(&[("trail_visibility", "no")])), Some(5));
+
+        assert_eq!(effective_sac_scale(&attributes(&[("sac_scale", "T3")])), None);
+        assert_eq!(attributes(&[("trail_visibility", "yes")]).trail_visibility, None);
+        assert!(TrailVisibility::Horrible > TrailVisibility::Good);
+        assert_eq!(label(3), "T3");
+    }
+}
@@ -3,14 +3,17 @@
 // Principles: Functional, immutable, type-safe
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use serde::Deserialize;
 use std::sync::Arc;
 
-use crate::database::{Database, DatabaseError, SaveRouteRequest, SavedRoute};
+use crate::database::{
+    Database, DatabaseError, RouteSort, SaveRouteGroupRequest, SaveRouteRequest, SavedRoute,
+    SavedRouteGroup,
+};
 use crate::models::ApiError;
 use shared::RouteResponse;
 
@@ -45,11 +48,19 @@ pub async fn save_route(
         .map_err(db_error_to_api_error)
 }
 
-/// GET /api/routes - List all saved routes
+/// Query of the saved routes list
+#[derive(Debug, Deserialize)]
+pub struct ListRoutesQuery {
+    #[serde(default)]
+    pub sort: RouteSort,
+}
+
+/// GET /api/routes?sort=newest|chemins_noirs - List all saved routes
 pub async fn list_routes(
     State(db): State<Arc<Database>>,
+    Query(query): Query<ListRoutesQuery>,
 ) -> Result<Json<Vec<SavedRoute>>, (StatusCode, Json<ApiError>)> {
-    db.list_routes()
+    db.list_routes(query.sort)
         .await
         .map(Json)
         .map_err(db_error_to_api_error)
@@ -88,6 +99,28 @@ pub async fn toggle_favorite(
         .map_err(db_error_to_api_error)
 }
 
+/// POST /api/route-groups - Save routes (e.g. itinerary stages) as one group
+pub async fn save_route_group(
+    State(db): State<Arc<Database>>,
+    Json(req): Json<SaveRouteGroupRequest>,
+) -> Result<Json<SavedRouteGroup>, (StatusCode, Json<ApiError>)> {
+    db.save_route_group(req)
+        .await
+        .map(Json)
+        .map_err(db_error_to_api_error)
+}
+
+/// GET /api/route-groups/:id - Get a route group with its routes in order
+pub async fn get_route_group(
+    State(db): State<Arc<Database>>,
+    Path(id): Path<i32>,
+) -> Result<Json<SavedRouteGroup>, (StatusCode, Json<ApiError>)> {
+    db.get_route_group(id)
+        .await
+        .map(Json)
+        .map_err(db_error_to_api_error)
+}
+
 /// Convert DatabaseError to API error response
 fn db_error_to_api_error(err: DatabaseError) -> (StatusCode, Json<ApiError>) {
     let (status, message) = match err {
@@ -95,6 +128,10 @@ fn db_error_to_api_error(err: DatabaseError) -> (StatusCode, Json<ApiError>) {
             StatusCode::NOT_FOUND,
             format!("Route with ID {} not found", id),
         ),
+        DatabaseError::GroupNotFound(id) => (
+            StatusCode::NOT_FOUND,
+            format!("Route group with ID {} not found", id),
+        ),
         DatabaseError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
         DatabaseError::ConfigError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         DatabaseError::ConnectionError(e) => (
@@ -0,0 +1,401 @@
+//! Scenery along edges: forest cover, rivers and lakes, viewpoints and peaks.
+//!
+//! Land cover comes from the same PBF pass as the road network and is
+//! reduced at graph build time to a few figures per edge (see
+//! [`EdgeEnvironment`]); the engine turns those into a scenery level between
+//! 0 and 1 with [`scenery_level`].
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::{
+    geo_utils::{
+        compute_bounds, haversine_km, point_in_polygon, sample_polyline, GridCell, SegmentGrid,
+    },
+    graph::{EdgeEnvironment, EdgeRecord, NodeRecord},
+    models::Coordinate,
+    poi::Poi,
+};
+
+/// Rivers and lake shores further away than this do not count
+pub const WATER_HORIZON_M: f64 = 200.0;
+/// Viewpoints and peaks further away than this do not count
+pub const VIEWPOINT_HORIZON_M: f64 = 500.0;
+/// Grid cell size of the land cover index (forests and features), in degrees
+const CELL_DEG: f64 = 0.005;
+/// Edges are sampled at least this often when measuring land cover
+const SAMPLE_SPACING_M: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandCover {
+    /// `landuse=forest` or `natural=wood` areas
+    Forest,
+    /// Rivers and streams, lakes and reservoirs (their outline)
+    Water,
+    /// Viewpoint and peak POIs
+    Viewpoint,
+}
+
+impl LandCover {
+    /// Land cover of an OSM way from one of its tags. Viewpoints are POIs
+    /// and never come from a way.
+    pub fn from_tag(key: &str, value: &str) -> Option<Self> {
+        match (key, value) {
+            ("landuse", "forest") | ("natural", "wood") => Some(Self::Forest),
+            ("natural", "water")
+            | ("landuse", "reservoir")
+            | ("waterway", "river" | "stream" | "canal" | "riverbank") => Some(Self::Water),
+            _ => None,
+        }
+    }
+
+    /// Land cover of a `type=multipolygon` relation from its tags. Only
+    /// forests are taken from relations; water is indexed from its ways.
+    pub fn from_relation_tags<'a>(tags: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut multipolygon = false;
+        let mut cover = None;
+        for (key, value) in tags {
+            if key == "type" {
+                multipolygon = value == "multipolygon";
+            } else if cover.is_none() {
+                cover = Self::from_tag(key, value).filter(|c| *c == Self::Forest);
+            }
+        }
+        cover.filter(|_| multipolygon)
+    }
+}
+
+/// Closed rings formed by joining `ways` (node ids) end to end, as the outer
+/// members of a multipolygon relation are split. Ways that never close a
+/// ring are dropped.
+pub fn assemble_rings(ways: &[Vec<i64>]) -> Vec<Vec<i64>> {
+    let mut open: Vec<Vec<i64>> = ways.iter().filter(|way| way.len() >= 2).cloned().collect();
+    let mut rings = Vec::new();
+    while let Some(mut ring) = open.pop() {
+        loop {
+            if ring.len() >= 4 && ring.first() == ring.last() {
+                rings.push(ring);
+                break;
+            }
+            let end = ring[ring.len() - 1];
+            let Some(i) = open.iter().position(|way| way[0] == end || way[way.len() - 1] == end)
+            else {
+                break;
+            };
+            let mut next = open.swap_remove(i);
+            if next[0] != end {
+                next.reverse();
+            }
+            ring.extend_from_slice(&next[1..]);
+        }
+    }
+    rings
+}
+
+/// Scenery level of an edge, from 0 (nothing of note) to 1: the best of its
+/// forest cover and its closeness to water and to a viewpoint or peak.
+pub fn scenery_level(environment: &EdgeEnvironment) -> f64 {
+    let decay = |distance_m: Option<f64>, horizon_m: f64| {
+        distance_m.map_or(0.0, |d| (1.0 - d / horizon_m).clamp(0.0, 1.0))
+    };
+    environment
+        .forest_fraction
+        .clamp(0.0, 1.0)
+        .max(decay(environment.water_distance_m, WATER_HORIZON_M))
+        .max(decay(environment.viewpoint_distance_m, VIEWPOINT_HORIZON_M))
+}
+
+/// Forest polygons, water outlines and viewpoints of the graph area.
+pub struct SceneryIndex {
+    forests: Vec<Vec<Coordinate>>,
+    /// Grid cell -> indices into `forests` of the polygons overlapping it
+    forest_cells: HashMap<GridCell, Vec<usize>>,
+    /// Water outlines and viewpoints (as zero-length segments)
+    features: SegmentGrid<LandCover>,
+}
+
+impl SceneryIndex {
+    /// Index land cover ways and viewpoint/peak POIs. Forests must be closed
+    /// rings (ways, or relation members joined by [`assemble_rings`]) with
+    /// all their nodes in `nodes`; water ways are split where nodes are
+    /// missing.
+    pub fn from_data(
+        ways: &[(LandCover, Vec<i64>)],
+        nodes: &HashMap<i64, (f64, f64, Option<f64>)>,
+        pois: &[Poi],
+    ) -> Self {
+        let mut index = Self {
+            forests: Vec::new(),
+            forest_cells: HashMap::new(),
+            features: SegmentGrid::new(CELL_DEG),
+        };
+        let coord = |id: &i64| nodes.get(id).map(|&(lat, lon, _)| Coordinate { lat, lon });
+
+        for (cover, refs) in ways {
+            match cover {
+                LandCover::Forest => {
+                    if refs.len() < 4 || refs.first() != refs.last() {
+                        continue;
+                    }
+                    if let Some(ring) = refs.iter().map(coord).collect::<Option<Vec<_>>>() {
+                        index.add_forest(ring);
+                    }
+                }
+                LandCover::Water | LandCover::Viewpoint => {
+                    let coords: Vec<Option<Coordinate>> = refs.iter().map(coord).collect();
+                    for pair in coords.windows(2) {
+                        if let (Some(a), Some(b)) = (pair[0], pair[1]) {
+                            index.features.insert(*cover, a, b);
+                        }
+                    }
+                }
+            }
+        }
+        for poi in pois {
+            if matches!(poi.poi_type.as_str(), "viewpoint" | "peak") {
+                let at = poi.coordinate();
+                index.features.insert(LandCover::Viewpoint, at, at);
+            }
+        }
+        index
+    }
+
+    fn add_forest(&mut self, ring: Vec<Coordinate>) {
+        let (min_lat, max_lat, min_lon, max_lon) = compute_bounds(&ring);
+        let id = self.forests.len();
+        let (min_y, min_x) = self.features.cell_of(Coordinate { lat: min_lat, lon: min_lon });
+        let (max_y, max_x) = self.features.cell_of(Coordinate { lat: max_lat, lon: max_lon });
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                self.forest_cells.entry((y, x)).or_default().push(id);
+            }
+        }
+        self.forests.push(ring);
+    }
+
+    pub fn in_forest(&self, point: Coordinate) -> bool {
+        self.forest_cells
+            .get(&self.features.cell_of(point))
+            .into_iter()
+            .flatten()
+            .any(|&i| point_in_polygon(point, &self.forests[i]))
+    }
+
+    /// Share of the length of `polyline` inside forest, from the exact
+    /// crossings of each segment with the forest outlines. Overlapping
+    /// forests count once.
+    pub fn forest_fraction(&self, polyline: &[Coordinate]) -> f64 {
+        if self.forests.is_empty() || polyline.is_empty() {
+            return 0.0;
+        }
+        let mut inside_m = 0.0;
+        let mut total_m = 0.0;
+
+        for segment in polyline.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let length_m = haversine_km(a, b) * 1000.0;
+            total_m += length_m;
+
+            // Split the segment where it crosses a forest outline; each piece
+            // is then entirely in or out of forest.
+            let mut cuts = vec![0.0, 1.0];
+            for forest in self.forests_near(a, b) {
+                for side in self.forests[forest].windows(2) {
+                    cuts.extend(segment_crossing(a, b, side[0], side[1]));
+                }
+            }
+            cuts.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+            cuts.dedup();
+
+            for piece in cuts.windows(2) {
+                if self.in_forest(a.interpolate(b, (piece[0] + piece[1]) / 2.0)) {
+                    inside_m += length_m * (piece[1] - piece[0]);
+                }
+            }
+        }
+
+        if total_m > 0.0 {
+            inside_m / total_m
+        } else if self.in_forest(polyline[0]) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Indices of the forests overlapping the grid cells spanned by `a → b`.
+    fn forests_near(&self, a: Coordinate, b: Coordinate) -> Vec<usize> {
+        let (ay, ax) = self.features.cell_of(a);
+        let (by, bx) = self.features.cell_of(b);
+        let mut forests: Vec<usize> = (ay.min(by)..=ay.max(by))
+            .flat_map(|y| (ax.min(bx)..=ax.max(bx)).map(move |x| (y, x)))
+            .filter_map(|cell| self.forest_cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        forests.sort_unstable();
+        forests.dedup();
+        forests
+    }
+
+    /// Fill the scenery figures of every edge. `nodes` must be indexed by
+    /// graph id - 1, as produced by the graph builder.
+    pub fn annotate(&self, edges: &mut [EdgeRecord], nodes: &[NodeRecord]) {
+        if self.forests.is_empty() && self.features.is_empty() {
+            return;
+        }
+        edges.par_iter_mut().for_each(|edge| {
+            let Some(polyline) = edge.polyline(nodes) else {
+                return;
+            };
+            let samples = sample_polyline(&polyline, SAMPLE_SPACING_M);
+            let nearest = |cover, horizon_m| {
+                samples
+                    .iter()
+                    .filter_map(|&p| self.features.nearest_m(p, cover, horizon_m))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            };
+            edge.environment.forest_fraction = self.forest_fraction(&polyline);
+            edge.environment.water_distance_m = nearest(LandCover::Water, WATER_HORIZON_M);
+            edge.environment.viewpoint_distance_m =
+                nearest(LandCover::Viewpoint, VIEWPOINT_HORIZON_M);
+        });
+    }
+}
+
+/// Position along `a → b` (0 to 1) where it crosses `c → d`, if it does.
+/// Planar in lat/lon, which keeps positions exact under the affine
+/// distortion of the projection.
+fn segment_crossing(a: Coordinate, b: Coordinate, c: Coordinate, d: Coordinate) -> Option<f64> {
+    let (rx, ry) = (b.lon - a.lon, b.lat - a.lat);
+    let (sx, sy) = (d.lon - c.lon, d.lat - c.lat);
+    let denominator = rx * sy - ry * sx;
+    if denominator == 0.0 {
+        return None;
+    }
+    let (qx, qy) = (c.lon - a.lon, c.lat - a.lat);
+    let t = (qx * sy - qy * sx) / denominator;
+    let u = (qx * ry - qy * rx) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::test_graph::{edge, node};
+
+    #[test]
+    fn test_annotate_measures_forest_water_and_viewpoints() {
+        // Square forest over lon 5.000..5.010, river along lat 45.012
+        let nodes: HashMap<i64, (f64, f64, Option<f64>)> = HashMap::from([
+            (1, (45.0, 5.0, None)),
+            (2, (45.0, 5.01, None)),
+            (3, (45.01, 5.01, None)),
+            (4, (45.01, 5.0, None)),
+            (5, (45.012, 4.99, None)),
+            (6, (45.012, 5.03, None)),
+        ]);
+        let pois = vec![
+            Poi { lat: 45.005, lon: 5.021, poi_type: "viewpoint".to_string(), name: None },
+            Poi { lat: 45.005, lon: 5.0, poi_type: "hut".to_string(), name: None },
+        ];
+        let index = SceneryIndex::from_data(
+            &[(LandCover::Forest, vec![1, 2, 3, 4, 1]), (LandCover::Water, vec![5, 6])],
+            &nodes,
+            &pois,
+        );
+
+        // West to east along lat 45.005: half in the forest, then towards the viewpoint
+        let graph_nodes = vec![
+            node(1, 45.005, 5.0),
+            node(2, 45.005, 5.02),
+            node(3, 45.03, 5.05),
+            node(4, 45.03, 5.06),
+        ];
+        let mut edges = vec![edge(1, 2, 0.0), edge(3, 4, 0.0)];
+        index.annotate(&mut edges, &graph_nodes);
+
+        let env = &edges[0].environment;
+        assert!((env.forest_fraction - 0.5).abs() < 1e-6, "got {}", env.forest_fraction);
+        assert_eq!(env.water_distance_m, None, "river ~780 m north");
+        let viewpoint = env.viewpoint_distance_m.unwrap();
+        assert!((viewpoint - 78.7).abs() < 1.0, "got {viewpoint}");
+        assert!(scenery_level(env) > 0.8);
+
+        assert_eq!(edges[1].environment, EdgeEnvironment::default());
+        assert_eq!(scenery_level(&edges[1].environment), 0.0);
+    }
+
+    #[test]
+    fn test_open_forest_ways_are_ignored() {
+        let nodes: HashMap<i64, (f64, f64, Option<f64>)> = HashMap::from([
+            (1, (45.0, 5.0, None)),
+            (2, (45.0, 5.01, None)),
+            (3, (45.01, 5.01, None)),
+        ]);
+        let index = SceneryIndex::from_data(&[(LandCover::Forest, vec![1, 2, 3])], &nodes, &[]);
+        assert!(!index.in_forest(Coordinate { lat: 45.002, lon: 5.008 }));
+
+        let closed = SceneryIndex::from_data(&[(LandCover::Forest, vec![1, 2, 3, 1])], &nodes, &[]);
+        assert!(closed.in_forest(Coordinate { lat: 45.002, lon: 5.008 }));
+        assert_eq!(LandCover::from_tag("natural", "wood"), Some(LandCover::Forest));
+        assert_eq!(LandCover::from_tag("waterway", "ditch"), None);
+    }
+
+    #[test]
+    fn test_multipolygon_forests_are_assembled_from_members() {
+        let tags = [("type", "multipolygon"), ("landuse", "forest")];
+        assert_eq!(LandCover::from_relation_tags(tags), Some(LandCover::Forest));
+        assert_eq!(LandCover::from_relation_tags([("landuse", "forest")]), None);
+        assert_eq!(
+            LandCover::from_relation_tags([("type", "multipolygon"), ("natural", "water")]),
+            None
+        );
+
+        // Square split in three outer ways, one drawn backwards, plus a stray way
+        let rings = assemble_rings(&[vec![1, 2], vec![4, 3, 2], vec![4, 1], vec![7, 8]]);
+        assert_eq!(rings.len(), 1);
+        let ring = &rings[0];
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring.first(), ring.last());
+        let mut corners = ring[1..].to_vec();
+        corners.sort_unstable();
+        assert_eq!(corners, vec![1, 2, 3, 4]);
+
+        let nodes: HashMap<i64, (f64, f64, Option<f64>)> = HashMap::from([
+            (1, (45.0, 5.0, None)),
+            (2, (45.0, 5.01, None)),
+            (3, (45.01, 5.01, None)),
+            (4, (45.01, 5.0, None)),
+        ]);
+        let index = SceneryIndex::from_data(&[(LandCover::Forest, ring.clone())], &nodes, &[]);
+        assert!(index.in_forest(Coordinate { lat: 45.005, lon: 5.005 }));
+    }
+
+    #[test]
+    fn test_forest_fraction_clips_segments_on_outlines() {
+        // Two overlapping forests covering lon 5.000..5.006 together
+        let nodes: HashMap<i64, (f64, f64, Option<f64>)> = HashMap::from([
+            (1, (44.99, 5.0, None)),
+            (2, (44.99, 5.004, None)),
+            (3, (45.01, 5.004, None)),
+            (4, (45.01, 5.0, None)),
+            (5, (44.99, 5.002, None)),
+            (6, (44.99, 5.006, None)),
+            (7, (45.01, 5.006, None)),
+            (8, (45.01, 5.002, None)),
+        ]);
+        let index = SceneryIndex::from_data(
+            &[(LandCover::Forest, vec![1, 2, 3, 4, 1]), (LandCover::Forest, vec![5, 6, 7, 8, 5])],
+            &nodes,
+            &[],
+        );
+
+        // A single straight segment from lon 4.998 to 5.008: 6 of its 10 thousandths are wooded
+        let polyline = [Coordinate { lat: 45.0, lon: 4.998 }, Coordinate { lat: 45.0, lon: 5.008 }];
+        assert!((index.forest_fraction(&polyline) - 0.6).abs() < 1e-6);
+        assert_eq!(index.forest_fraction(&polyline[..1]), 0.0);
+        assert_eq!(index.forest_fraction(&[Coordinate { lat: 45.0, lon: 5.001 }]), 1.0);
+    }
+}
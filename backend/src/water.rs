@@ -0,0 +1,298 @@
+//! Water resupply along a route.
+//!
+//! Water points are the `water` POIs (drinking water, springs) lying within
+//! [`WATER_BUFFER_M`] of the path. The constraint is on the longest stretch
+//! between the start, consecutive water points and the end.
+
+use crate::{
+    engine::RouteEngine,
+    geo_utils::{closest_point_on_path, haversine_km},
+    models::{Coordinate, RouteRequest, WaterReport, WaterStop},
+    poi::Poi,
+};
+use petgraph::graph::NodeIndex;
+
+/// A water point counts as on the route when the path passes this close
+pub const WATER_BUFFER_M: f64 = 150.0;
+/// How far from the path a water point may be to be worth a detour
+const WATER_SEARCH_RADIUS_M: f64 = 2000.0;
+/// Detours added to a single route before giving up on the constraint
+const MAX_WATER_DETOURS: usize = 6;
+
+pub fn validate_max_km_without_water(max_km: Option<f64>) -> Result<(), &'static str> {
+    match max_km {
+        Some(km) if !km.is_finite() || km <= 0.0 => {
+            Err("max_km_without_water must be strictly positive")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Water points along `path` and the longest stretch without one.
+pub fn water_report(pois: &[Poi], path: &[Coordinate], max_km_without_water: f64) -> WaterReport {
+    let cumulative = cumulative_km(path);
+    let total_km = cumulative.last().copied().unwrap_or(0.0);
+    let stops: Vec<WaterStop> = locate_water_pois(pois, path, &cumulative, WATER_BUFFER_M)
+        .into_iter()
+        .map(|(poi, distance_from_route_m, distance_along_km)| WaterStop {
+            location: poi.coordinate(),
+            name: poi.name.clone(),
+            distance_along_km,
+            distance_from_route_m,
+        })
+        .collect();
+
+    let longest_gap_km = gaps(&stops, total_km)
+        .map(|(from, to)| to - from)
+        .fold(0.0, f64::max);
+
+    WaterReport {
+        max_km_without_water,
+        longest_gap_km,
+        satisfied: longest_gap_km <= max_km_without_water,
+        stops,
+    }
+}
+
+/// Route `req.start → req.end` and, while some stretch exceeds
+/// `max_km_without_water`, re-route through the water point that lies the
+/// furthest into that stretch while staying reachable within the limit.
+///
+/// Returns the path, the graph nodes it goes through and the report, or
+/// `None` only when no path exists at all. When no suitable water point can
+/// be found the best path so far is returned with an unsatisfied report.
+pub fn route_with_water(
+    engine: &RouteEngine,
+    req: &RouteRequest,
+    max_km_without_water: f64,
+) -> Option<(Vec<Coordinate>, Vec<NodeIndex>, WaterReport)> {
+    let (mut path, mut nodes) = engine.find_path_returning_indices(req)?;
+    let mut vias: Vec<Coordinate> = Vec::new();
+
+    for _ in 0..MAX_WATER_DETOURS {
+        let report = water_report(engine.pois(), &path, max_km_without_water);
+        if report.satisfied {
+            return Some((path, nodes, report));
+        }
+
+        let cumulative = cumulative_km(&path);
+        let total_km = cumulative.last().copied().unwrap_or(0.0);
+        let Some((gap_start, _)) = gaps(&report.stops, total_km)
+            .find(|(from, to)| to - from > max_km_without_water)
+        else {
+            break;
+        };
+        let Some((via, via_along)) =
+            pick_detour(engine.pois(), &path, &cumulative, gap_start, max_km_without_water)
+        else {
+            tracing::debug!("No water point reachable after {:.1}km", gap_start);
+            break;
+        };
+
+        let position = vias
+            .iter()
+            .filter(|&&v| locate_on_path(v, &path, &cumulative).1 < via_along)
+            .count();
+        vias.insert(position, via);
+
+        match path_through(engine, req, &vias) {
+            Some(rerouted) => (path, nodes) = rerouted,
+            None => {
+                vias.remove(position);
+                break;
+            }
+        }
+    }
+
+    let report = water_report(engine.pois(), &path, max_km_without_water);
+    Some((path, nodes, report))
+}
+
+/// The water point off the route that extends the stretch starting at
+/// `gap_start_km` the furthest, counting the walk off the route to reach it.
+fn pick_detour(
+    pois: &[Poi],
+    path: &[Coordinate],
+    cumulative: &[f64],
+    gap_start_km: f64,
+    max_km: f64,
+) -> Option<(Coordinate, f64)> {
+    locate_water_pois(pois, path, cumulative, WATER_SEARCH_RADIUS_M)
+        .into_iter()
+        .filter(|(_, off_route_m, _)| *off_route_m > WATER_BUFFER_M)
+        .filter(|(_, off_route_m, along_km)| {
+            *along_km > gap_start_km && along_km + off_route_m / 1000.0 <= gap_start_km + max_km
+        })
+        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(poi, _, along_km)| (poi.coordinate(), along_km))
+}
+
+fn path_through(
+    engine: &RouteEngine,
+    req: &RouteRequest,
+    vias: &[Coordinate],
+) -> Option<(Vec<Coordinate>, Vec<NodeIndex>)> {
+    let mut result: Vec<Coordinate> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut leg_start = req.start;
+    for &leg_end in vias.iter().chain(std::iter::once(&req.end)) {
+        let (mut leg, leg_nodes) = engine.find_path_returning_indices(&RouteRequest {
+            start: leg_start,
+            end: leg_end,
+            ..req.clone()
+        })?;
+        if leg.is_empty() {
+            return None;
+        }
+        if !result.is_empty() {
+            leg.remove(0);
+        }
+        result.extend(leg);
+        RouteEngine::append_leg_nodes(&mut nodes, &leg_nodes);
+        leg_start = leg_end;
+    }
+    Some((result, nodes))
+}
+
+/// Consecutive `(from_km, to_km)` stretches between the start, the stops
+/// (sorted by distance along) and the end.
+fn gaps(stops: &[WaterStop], total_km: f64) -> impl Iterator<Item = (f64, f64)> + '_ {
+    let marks: Vec<f64> = std::iter::once(0.0)
+        .chain(stops.iter().map(|s| s.distance_along_km))
+        .chain(std::iter::once(total_km))
+        .collect();
+    (1..marks.len()).map(move |i| (marks[i - 1], marks[i]))
+}
+
+fn cumulative_km(path: &[Coordinate]) -> Vec<f64> {
+    let mut total = 0.0;
+    let mut cumulative = Vec::with_capacity(path.len());
+    for i in 0..path.len() {
+        if i > 0 {
+            total += haversine_km(path[i - 1], path[i]);
+        }
+        cumulative.push(total);
+    }
+    cumulative
+}
+
+/// Water POIs within `radius_m` of the path as `(poi, distance from route in
+/// meters, distance along in km)`, sorted by distance along.
+fn locate_water_pois<'a>(
+    pois: &'a [Poi],
+    path: &[Coordinate],
+    cumulative: &[f64],
+    radius_m: f64,
+) -> Vec<(&'a Poi, f64, f64)> {
+    if path.is_empty() {
+        return Vec::new();
+    }
+    let margin_deg = radius_m / 111_000.0;
+    let (min_lat, max_lat) = path
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), c| (lo.min(c.lat), hi.max(c.lat)));
+    let (min_lon, max_lon) = path
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), c| (lo.min(c.lon), hi.max(c.lon)));
+    let lon_margin_deg = margin_deg / min_lat.abs().max(max_lat.abs()).to_radians().cos().max(0.01);
+
+    let mut located: Vec<(&Poi, f64, f64)> = pois
+        .iter()
+        .filter(|poi| poi.poi_type == "water")
+        .filter(|poi| {
+            poi.lat >= min_lat - margin_deg
+                && poi.lat <= max_lat + margin_deg
+                && poi.lon >= min_lon - lon_margin_deg
+                && poi.lon <= max_lon + lon_margin_deg
+        })
+        .filter_map(|poi| {
+            let (distance_m, along_km) = locate_on_path(poi.coordinate(), path, cumulative);
+            (distance_m <= radius_m).then_some((poi, distance_m, along_km))
+        })
+        .collect();
+    located.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+    located
+}
+
+/// Distance in meters from `point` to the closest point of the path, and the
+/// distance along the path in km of that closest point.
+fn locate_on_path(point: Coordinate, path: &[Coordinate], cumulative: &[f64]) -> (f64, f64) {
+    match closest_point_on_path(point, path) {
+        Some((distance_m, i, t)) if i + 1 < cumulative.len() => {
+            (distance_m, cumulative[i] + t * (cumulative[i + 1] - cumulative[i]))
+        }
+        Some((distance_m, _, _)) => (distance_m, 0.0),
+        None => (f64::INFINITY, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn water(lat: f64, lon: f64) -> Poi {
+        Poi {
+            lat,
+            lon,
+            poi_type: "water".to_string(),
+            name: None,
+        }
+    }
+
+    /// ~11.1 km due north, one point per km
+    fn straight_path() -> Vec<Coordinate> {
+        (0..=10)
+            .map(|i| Coordinate { lat: 45.0 + i as f64 * 0.01, lon: 5.0 })
+            .collect()
+    }
+
+    #[test]
+    fn test_water_report_measures_gaps_between_stops() {
+        let path = straight_path();
+        let pois = vec![
+            water(45.03, 5.0005),
+            water(45.07, 5.0),
+            Poi { poi_type: "hut".to_string(), ..water(45.05, 5.0) },
+            water(45.05, 5.02), // ~1.6 km off the path
+        ];
+
+        let report = water_report(&pois, &path, 5.0);
+        assert_eq!(report.stops.len(), 2);
+        assert!((report.stops[0].distance_along_km - 3.336).abs() < 0.01);
+        assert!(report.stops[0].distance_from_route_m > 30.0);
+        assert!((report.stops[1].distance_along_km - 7.784).abs() < 0.01);
+        // 0 → 3.3 → 7.8 → 11.1: the middle stretch is the longest
+        assert!((report.longest_gap_km - 4.448).abs() < 0.01);
+        assert!(report.satisfied);
+        assert!(!water_report(&pois, &path, 4.0).satisfied);
+
+        let dry = water_report(&[], &path, 5.0);
+        assert!(dry.stops.is_empty());
+        assert!((dry.longest_gap_km - 11.12).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pick_detour_prefers_furthest_reachable_point() {
+        let path = straight_path();
+        let cumulative = cumulative_km(&path);
+        let pois = vec![
+            water(45.02, 5.005),  // ~2.2 km along, ~390 m off
+            water(45.04, 5.005),  // ~4.4 km along, ~390 m off
+            water(45.045, 5.005), // ~5 km along: too far once the detour is counted
+            water(45.03, 5.0),   // already on the route
+        ];
+
+        let (via, along) = pick_detour(&pois, &path, &cumulative, 0.0, 5.0).unwrap();
+        assert!((via.lat - 45.04).abs() < 1e-9);
+        assert!((along - 4.448).abs() < 0.01);
+        assert!(pick_detour(&pois, &path, &cumulative, 6.0, 5.0).is_none());
+    }
+
+    #[test]
+    fn test_validate_max_km_without_water() {
+        assert!(validate_max_km_without_water(None).is_ok());
+        assert!(validate_max_km_without_water(Some(8.0)).is_ok());
+        assert!(validate_max_km_without_water(Some(0.0)).is_err());
+        assert!(validate_max_km_without_water(Some(f64::NAN)).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+/// A waymarked route relation (`type=route`, `route=hiking|foot|bicycle|mtb`).
+///
+/// Stored once per graph in [`crate::graph::GraphFile::routes`]; edges refer
+/// to the routes they belong to by index into that table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaymarkedRoute {
+    pub osm_id: i64,
+    /// `hiking`, `foot`, `bicycle` or `mtb`
+    pub route: String,
+    /// `iwn`, `nwn`, `rwn` or `lwn` (and the `icn`... equivalents for cycling)
+    pub network: String,
+    pub name: Option<String>,
+    pub reference: Option<String>,
+    pub colour: Option<String>,
+    pub osmc_symbol: Option<String>,
+}
+
+impl WaymarkedRoute {
+    /// Build a route from an OSM relation's tags, or `None` if it is not a
+    /// walking or cycling route.
+    pub fn from_tags<'a>(osm_id: i64, tags: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut route = None;
+        let mut network = None;
+        let mut name = None;
+        let mut reference = None;
+        let mut colour = None;
+        let mut osmc_symbol = None;
+
+        for (key, value) in tags {
+            match key {
+                "route" if matches!(value, "hiking" | "foot" | "bicycle" | "mtb") => {
+                    route = Some(value.to_string())
+                }
+                "network" => network = Some(value.to_string()),
+                "name" => name = Some(value.to_string()),
+                "ref" => reference = Some(value.to_string()),
+                "colour" => colour = Some(value.to_string()),
+                "osmc:symbol" => osmc_symbol = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            osm_id,
+            route: route?,
+            // Untagged networks are mostly local PR loops
+            network: network.unwrap_or_else(|| "lwn".to_string()),
+            name,
+            reference,
+            colour,
+            osmc_symbol,
+        })
+    }
+
+    /// Whether this route is meant for walkers.
+    pub fn is_walking(&self) -> bool {
+        matches!(self.route.as_str(), "hiking" | "foot")
+    }
+
+    /// How strongly the route counts as a marked trail: 1 for international
+    /// and national routes (GR), down to 0.6 for local ones (PR).
+    pub fn network_score(&self) -> f64 {
+        match self.network.as_str() {
+            "iwn" | "nwn" | "icn" | "ncn" => 1.0,
+            "rwn" | "rcn" => 0.8,
+            _ => 0.6,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tags_keeps_only_walking_and_cycling_routes() {
+        let gr = WaymarkedRoute::from_tags(
+            42,
+            [
+                ("type", "route"),
+                ("route", "hiking"),
+                ("network", "nwn"),
+                ("name", "GR 9"),
+                ("ref", "GR 9"),
+                ("osmc:symbol", "red:white:red_bar"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(gr.network, "nwn");
+        assert_eq!(gr.reference.as_deref(), Some("GR 9"));
+        assert_eq!(gr.osmc_symbol.as_deref(), Some("red:white:red_bar"));
+        assert!(gr.is_walking());
+        assert_eq!(gr.network_score(), 1.0);
+
+        let local = WaymarkedRoute::from_tags(7, [("route", "foot")]).unwrap();
+        assert_eq!(local.network, "lwn");
+        assert_eq!(local.network_score(), 0.6);
+
+        assert!(!WaymarkedRoute::from_tags(1, [("route", "mtb")]).unwrap().is_walking());
+        assert!(WaymarkedRoute::from_tags(2, [("route", "bus"), ("name", "12")]).is_none());
+    }
+}
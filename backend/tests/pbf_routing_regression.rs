@@ -120,6 +120,7 @@ fn each_segment_finds_a_path() {
             end: Coordinate { lat: elat, lon: elon },
             w_pop: 1.0,
             w_paved: 1.0,
+            options: Default::default(),
         });
 
         assert!(
@@ -160,6 +161,7 @@ fn full_loop_distance_is_stable() {
                 end: Coordinate { lat: elat, lon: elon },
                 w_pop: 1.0,
                 w_paved: 1.0,
+                options: Default::default(),
             })
             .unwrap_or_else(|| panic!("No path for segment {} -> {}", i, i + 1));
 
@@ -210,6 +212,7 @@ fn path_coordinates_stay_near_waypoints() {
                 end: Coordinate { lat: elat, lon: elon },
                 w_pop: 1.0,
                 w_paved: 1.0,
+                options: Default::default(),
             })
             .expect("path should exist");
 
@@ -353,6 +356,7 @@ fn build_multi_point_route(
                 end: points[i + 1],
                 w_pop: 1.0,
                 w_paved: 1.0,
+                options: Default::default(),
             })
             .unwrap_or_else(|| panic!("No path for segment {} -> {}", i, i + 1));
 
@@ -366,7 +370,7 @@ fn build_multi_point_route(
 
         // Append to full path (dedup at segment boundaries)
         for &coord in &path {
-            if all_coords.last().map_or(true, |last: &Coordinate| {
+            if all_coords.last().is_none_or(|last: &Coordinate| {
                 (last.lat - coord.lat).abs() > 1e-7 || (last.lon - coord.lon).abs() > 1e-7
             }) {
                 all_coords.push(coord);
@@ -66,11 +66,12 @@ async fn route_respects_weights() {
         },
         w_pop: 0.0,
         w_paved: 0.0,
+        options: Default::default(),
     };
     let scenic = RouteRequest {
         w_pop: 0.0,
         w_paved: 5.0,
-        ..direct
+        ..direct.clone()
     };
 
     let make_request = |req: &RouteRequest| {
@@ -93,6 +94,47 @@ async fn route_respects_weights() {
     assert!(body_scenic.distance_km >= body_direct.distance_km);
 }
 
+#[tokio::test]
+async fn route_reports_water_gap_when_no_water_point_is_reachable() {
+    let app = test_app();
+    let payload = json!({
+        "start": {"lat": 45.0005, "lon": 5.0005},
+        "end": {"lat": 45.024, "lon": 5.034},
+        "max_km_without_water": 1.0
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/route")
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    let body: RouteResponse = serde_json::from_slice(&bytes).unwrap();
+    // The sample graph has no POIs: the whole route is one dry stretch
+    let water = body.water.expect("water report");
+    assert!(water.stops.is_empty());
+    assert!(!water.satisfied);
+    assert!((water.longest_gap_km - body.distance_km).abs() < 1e-6);
+
+    let invalid = json!({
+        "start": {"lat": 45.0005, "lon": 5.0005},
+        "end": {"lat": 45.024, "lon": 5.034},
+        "max_km_without_water": 0.0
+    });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/route")
+        .header("content-type", "application/json")
+        .body(Body::from(invalid.to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn regression_three_waypoint_itinerary() {
     let engine = RouteEngine::from_reader(SAMPLE_GRAPH.as_bytes()).expect("graph");
@@ -112,6 +154,7 @@ fn regression_three_waypoint_itinerary() {
             end: waypoint_b,
             w_pop,
             w_paved,
+            options: Default::default(),
         })
         .expect("path A→B should exist");
 
@@ -122,6 +165,7 @@ fn regression_three_waypoint_itinerary() {
             end: waypoint_c,
             w_pop,
             w_paved,
+            options: Default::default(),
         })
         .expect("path B→C should exist");
 
@@ -161,3 +205,87 @@ fn regression_three_waypoint_itinerary() {
         );
     }
 }
+
+#[tokio::test]
+async fn loop_stream_rejects_invalid_request_before_streaming() {
+    let app = test_app();
+    let payload = json!({
+        "start": {"lat": 45.0, "lon": 5.0},
+        "target_distance_km": 1.0
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/loops/stream")
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn loop_stream_emits_progress_then_final_event() {
+    let app = test_app();
+    // Far longer than anything the sample graph can offer: every attempt is
+    // rejected and the stream ends with an error event.
+    let payload = json!({
+        "start": {"lat": 45.0, "lon": 5.0},
+        "target_distance_km": 60.0,
+        "candidate_count": 4
+    })
+    .to_string();
+    let query: String = payload
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' | b'-' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(format!("/api/loops/stream?request={query}"))
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    let bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    let body = String::from_utf8(bytes.to_vec()).unwrap();
+    let progress_count = body.matches("event: progress").count();
+    assert_eq!(progress_count, 12, "one progress event per attempt (3 rings × 4)");
+    assert!(body.contains("distance_out_of_tolerance") || body.contains("no_path"));
+    let last_event = body.rfind("event: ").map(|i| &body[i..]).unwrap();
+    assert!(last_event.starts_with("event: error"), "got {last_event}");
+}
+
+#[tokio::test]
+async fn detour_endpoint_rejects_target_shorter_than_direct_route() {
+    let app = test_app();
+    let payload = json!({
+        "start": {"lat": 45.0005, "lon": 5.0005},
+        "end": {"lat": 45.024, "lon": 5.034},
+        "target_distance_km": 2.5,
+        "distance_tolerance_km": 0.5
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/route/detour")
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(body["message"].as_str().unwrap().contains("shorter than the direct route"));
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -15,7 +17,7 @@ impl Coordinate {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SurfaceType {
     Paved,
@@ -23,6 +25,17 @@ pub enum SurfaceType {
     Dirt,
 }
 
+impl SurfaceType {
+    /// Name as serialized: `paved`, `trail` or `dirt`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SurfaceType::Paved => "paved",
+            SurfaceType::Trail => "trail",
+            SurfaceType::Dirt => "dirt",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouteRequest {
     pub start: Coordinate,
@@ -31,6 +44,132 @@ pub struct RouteRequest {
     pub w_pop: f64,
     #[serde(default = "default_weight")]
     pub w_paved: f64,
+    #[serde(flatten)]
+    pub options: RouteOptions,
+}
+
+/// Optional preferences and constraints shared by route, multi-point and loop
+/// requests. Flattened into each request, so the fields sit at the top level
+/// of the JSON body.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouteOptions {
+    /// Longest stretch allowed between two water points (drinking water, springs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_km_without_water: Option<f64>,
+    /// Preference for waymarked walking routes (GR, PR...): positive values
+    /// favour them, negative values avoid them, 0 ignores them.
+    pub w_waymarked: f64,
+    /// Penalty for edges close to major roads and railways (0 ignores noise)
+    pub w_quiet: f64,
+    /// Penalty for edges away from forests, rivers, lakes and viewpoints
+    /// (0 ignores scenery)
+    pub w_scenery: f64,
+    /// Penalty for edges out of forest cover (0 ignores shade)
+    pub w_shade: f64,
+    /// Night mode: penalty for ways not tagged `lit=yes` (0 ignores lighting)
+    pub w_lit: f64,
+    pub profile: RoutingProfile,
+    /// Steepest grade allowed by the wheelchair profile, in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_grade_percent: Option<f64>,
+    /// Hardest `mtb:scale` (0 to 6) ridden by the cycling profile; harder
+    /// sections are walked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_mtb_scale: Option<u8>,
+    /// Hardest SAC grade allowed, 1 (T1) to 6 (T6); harder ways are never
+    /// used, whatever the profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_sac_scale: Option<u8>,
+    /// Waypoints further than this from any way are joined to the network
+    /// by an off-trail path across the terrain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub off_trail_beyond_m: Option<f64>,
+    /// Edges picked from `POST /api/nearest` for some waypoints, instead of
+    /// the nearest way
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub snap_choices: Vec<SnapChoice>,
+    /// Return the cost of each edge of the route in `debug`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub debug: bool,
+    /// With `debug`, also return the nodes the search settled
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub debug_search_space: bool,
+    /// Language of the turn-by-turn instructions
+    pub language: Language,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    Fr,
+    En,
+}
+
+/// Snap a waypoint onto a given edge. Applies to the waypoint at `point`
+/// (within a metre), so the choice survives the legs a request is split into.
+/// The edge is the one passing within a metre of `projected`, with the same
+/// `highway` and `name` when given: graphs are rebuilt around each request,
+/// so edges are found again by position rather than by id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapChoice {
+    /// The clicked point, as sent to `POST /api/nearest`
+    pub point: Coordinate,
+    /// `SnapCandidate::projected` of the chosen candidate
+    pub projected: Coordinate,
+    /// `SnapCandidate::highway` of the chosen candidate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highway: Option<String>,
+    /// `SnapCandidate::name` of the chosen candidate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Ways around a clicked point, to show how it snaps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearestRequest {
+    pub point: Coordinate,
+    #[serde(default = "default_nearest_count")]
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearestResponse {
+    /// Nearest first
+    pub candidates: Vec<SnapCandidate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapCandidate {
+    /// Closest point of the edge to the click
+    pub projected: Coordinate,
+    pub distance_from_click_m: f64,
+    pub surface: SurfaceType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highway: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Connected part of the graph holding the edge, 0 being the largest:
+    /// routes never cross between components
+    pub component: u32,
+    /// Whether routing snaps the click onto this edge by default (every
+    /// edge of the intersection when the click snaps onto one)
+    pub selected: bool,
+}
+
+/// Who the route is for; decides which ways are usable and how they cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingProfile {
+    #[default]
+    Hiking,
+    /// Wheelchairs and strollers: no steps, raised kerbs, rough surfaces or
+    /// steep grades
+    Wheelchair,
+    /// Bicycles: no ways closed to bikes, cycle infrastructure preferred,
+    /// footways and hard MTB sections walked
+    Cycling,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +181,8 @@ pub struct MultiPointRouteRequest {
     pub w_pop: f64,
     #[serde(default = "default_weight")]
     pub w_paved: f64,
+    #[serde(flatten)]
+    pub options: RouteOptions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +201,138 @@ pub struct LoopRouteRequest {
     pub max_total_ascent: Option<f64>,
     #[serde(default)]
     pub min_total_ascent: Option<f64>,
+    /// Only place loop waypoints within this compass sector.
+    #[serde(default)]
+    pub bearing_sector: Option<BearingSector>,
+    /// Polygon every loop must pass through; waypoints are placed on
+    /// bearings pointing into it.
+    #[serde(default)]
+    pub via_area: Option<Vec<Coordinate>>,
+    /// Points the loop must pass through, in any order.
+    #[serde(default)]
+    pub via_points: Vec<Coordinate>,
+    /// POI categories the loop must pass by at least once (e.g. "water", "hut").
+    #[serde(default)]
+    pub required_poi_types: Vec<String>,
+    /// Jitters waypoint bearings and distances; the same seed gives the same loops.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Which way round to walk each loop; unset keeps the generated direction.
+    #[serde(default)]
+    pub direction_preference: Option<LoopDirectionPreference>,
+    /// Order of the returned candidates
+    #[serde(default)]
+    pub sort_by: LoopSort,
+    #[serde(flatten)]
+    pub options: RouteOptions,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopSort {
+    /// Least total ascent first, then closest to the target distance
+    #[default]
+    Ascent,
+    /// Highest chemins noirs score first
+    CheminsNoirs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopDirectionPreference {
+    /// Lowest maximum sustained climb
+    GentleAscent,
+    /// Lowest maximum sustained descent (climb the steep side, spare the knees)
+    GentleDescent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopOrientation {
+    Clockwise,
+    Counterclockwise,
+}
+
+/// Steepest sustained grades met when walking a loop in one orientation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopDirectionGrades {
+    pub orientation: LoopOrientation,
+    pub max_sustained_ascent_pct: f64,
+    pub max_sustained_descent_pct: f64,
+}
+
+/// Point-to-point route deliberately longer than the shortest path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetourRouteRequest {
+    pub start: Coordinate,
+    pub end: Coordinate,
+    /// Desired walking distance, longer than the shortest A→B route
+    pub target_distance_km: f64,
+    #[serde(default = "default_distance_tolerance_km")]
+    pub distance_tolerance_km: f64,
+    #[serde(default = "default_loop_candidate_count")]
+    pub candidate_count: usize,
+    #[serde(default = "default_weight")]
+    pub w_pop: f64,
+    #[serde(default = "default_weight")]
+    pub w_paved: f64,
+    #[serde(default)]
+    pub max_total_ascent: Option<f64>,
+    #[serde(default)]
+    pub min_total_ascent: Option<f64>,
+    #[serde(flatten)]
+    pub options: RouteOptions,
+}
+
+/// GPS track recorded on the ground, to snap onto the routing graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapMatchRequest {
+    /// GPX file with at least one track, base64-encoded like
+    /// `RouteResponse::gpx_base64`
+    pub gpx_base64: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapMatchResponse {
+    /// The track on the network; unmatched stretches keep the recorded points
+    pub route: RouteResponse,
+    pub unmatched: Vec<UnmatchedSection>,
+}
+
+/// A stretch of a recorded track away from any way of the graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnmatchedSection {
+    /// Distances from the start, measured along the matched route
+    pub from_km: f64,
+    pub to_km: f64,
+    /// Recorded points in the stretch
+    pub point_count: usize,
+}
+
+/// Multi-day trek to split into daily stages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItineraryRequest {
+    /// Start, optional intermediate points, end
+    pub waypoints: Vec<Coordinate>,
+    /// Fixed number of days; when unset, stages follow `target_hours_per_day`
+    #[serde(default)]
+    pub days: Option<u32>,
+    #[serde(default = "default_hours_per_day")]
+    pub target_hours_per_day: f64,
+    #[serde(default = "default_weight")]
+    pub w_pop: f64,
+    #[serde(default = "default_weight")]
+    pub w_paved: f64,
+    #[serde(flatten)]
+    pub options: RouteOptions,
+}
+
+/// Compass sector in degrees clockwise from north. A sector whose minimum is
+/// larger than its maximum wraps through north (e.g. 300° → 60°).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BearingSector {
+    pub min_bearing_deg: f64,
+    pub max_bearing_deg: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,12 +381,259 @@ pub struct RouteResponse {
     /// Difficulty rating: "easy", "moderate", "difficult", "expert"
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub difficulty: Option<String>,
+    /// Stretch with the highest SAC grade, when the route has graded trails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardest_section: Option<HardestSection>,
     /// Surface type breakdown: vec of (surface_name, distance_km)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub surface_breakdown: Option<Vec<(String, f64)>>,
+    /// Distance per `highway=*` class, as (highway, distance_km)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highway_breakdown: Option<Vec<(String, f64)>>,
     /// Per-segment statistics for multi-point routes
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub segments: Option<Vec<SegmentStats>>,
+    /// Water points along the route, when `max_km_without_water` was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub water: Option<WaterReport>,
+    /// Waymarked routes followed, in order along the route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waymarked: Option<Vec<WaymarkedSection>>,
+    /// 0 (along major roads or railways) to 100 (away from any noise source)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quietness_score: Option<f64>,
+    /// Percentage of the route under forest cover
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shade_percent: Option<f64>,
+    /// Percentage of the route on lit ways
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lit_percent: Option<f64>,
+    /// Rideable and walked distances, for the cycling profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycling: Option<CyclingReport>,
+    /// Cross-country stretches, when `off_trail_beyond_m` was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub off_trail: Option<Vec<OffTrailSection>>,
+    /// Cost breakdown of the route, when `debug` was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug: Option<RouteDebug>,
+    /// Turn-by-turn instructions, from the departure to the arrival
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maneuvers: Option<Vec<Maneuver>>,
+    /// The route split into runs of edges sharing the same attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legs: Option<Vec<RouteLeg>>,
+    /// How much of the route is on quiet, unpaved backroads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chemins_noirs: Option<CheminsNoirsScore>,
+    /// `point` of the `snap_choices` whose edge was not found, snapped to
+    /// the nearest way instead
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unresolved_snap_choices: Vec<Coordinate>,
+}
+
+/// Composite route quality from 0 (busy paved roads through towns) to 100
+/// (quiet, waymarked tracks away from traffic). Each component runs from 0
+/// to 100 over the length of the route on the network.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CheminsNoirsScore {
+    /// Weighted mean of the components below
+    pub score: f64,
+    /// Paths and tracks rather than major roads
+    pub road_class: f64,
+    /// Unpaved surfaces
+    pub surface: f64,
+    /// Away from major roads and railways
+    pub quietness: f64,
+    /// Share on waymarked walking routes
+    pub waymarked: f64,
+}
+
+/// Consecutive edges of the route with the same surface, way, waymarked
+/// route and grade class.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLeg {
+    /// Range of `path` indices covered, both included
+    pub from_index: usize,
+    pub to_index: usize,
+    pub distance_km: f64,
+    /// Metres climbed, from the elevation profile (0 without one)
+    pub ascent_m: f64,
+    /// `None` off the network: snapping and off-trail stretches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surface: Option<SurfaceType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highway: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Most important waymarked route followed, by `ref` or name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waymarked: Option<String>,
+    /// From the steepest known grade of the edges (`incline` tag, DEM)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grade_class: Option<GradeClass>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GradeClass {
+    /// Under 5 %
+    Flat,
+    /// 5 to 10 %
+    Moderate,
+    /// 10 to 15 %
+    Steep,
+    /// Over 15 %
+    VerySteep,
+}
+
+/// A turn-by-turn instruction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Maneuver {
+    #[serde(rename = "type")]
+    pub kind: ManeuverType,
+    /// Change of heading at the maneuver, in degrees from -180 to 180,
+    /// positive to the right
+    pub bearing_change_deg: f64,
+    /// Distance along the route to the next maneuver
+    pub distance_to_next_km: f64,
+    /// Way taken: its name, or its kind (path, track...) when unnamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Index of the maneuver point in `path`
+    pub path_index: usize,
+    /// The maneuver phrased in the requested `language`
+    pub instruction: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManeuverType {
+    Depart,
+    Continue,
+    SlightLeft,
+    Left,
+    SharpLeft,
+    SlightRight,
+    Right,
+    SharpRight,
+    UTurn,
+    Arrive,
+}
+
+/// Why the search picked this route: what each edge between the snapped
+/// start and end cost. The terms are in km, so that a penalty of 0.5 on a
+/// 2 km edge makes it cost as much as a 3 km one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteDebug {
+    pub edges: Vec<EdgeCost>,
+    /// Sum of the edge costs, the value the search minimised
+    pub total_cost: f64,
+    /// Nodes the search settled, when `debug_search_space` was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settled_nodes: Option<GeoJsonMultiPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeCost {
+    pub from: Coordinate,
+    pub to: Coordinate,
+    pub length_km: f64,
+    /// `w_pop` term
+    pub population: f64,
+    /// `w_paved` term
+    pub paved: f64,
+    pub waymarked: f64,
+    pub quiet: f64,
+    pub scenery: f64,
+    pub shade: f64,
+    pub lit: f64,
+    /// Wheelchair or cycling penalty
+    pub profile: f64,
+    /// Penalty on edges excluded by the caller, e.g. the way out of a loop
+    pub exclusion: f64,
+    pub total: f64,
+}
+
+/// GeoJSON `MultiPoint` geometry, positions as `[lon, lat]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoJsonMultiPoint {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub coordinates: Vec<[f64; 2]>,
+}
+
+/// A stretch of the route away from any way, across the terrain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OffTrailSection {
+    /// Distances from the start, measured along the route
+    pub from_km: f64,
+    pub to_km: f64,
+}
+
+/// First stretch of the route at its highest SAC grade.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardestSection {
+    /// `T1` to `T6`, raised for poor trail visibility
+    pub sac_scale: String,
+    /// Worst `trail_visibility` on the stretch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trail_visibility: Option<String>,
+    /// Distances from the start, measured along the route
+    pub from_km: f64,
+    pub to_km: f64,
+}
+
+/// How much of a cycling route is ridden.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CyclingReport {
+    pub rideable_km: f64,
+    /// Pushed or carried: steps, footways, `bicycle=dismount` and MTB
+    /// sections above `max_mtb_scale`
+    pub hike_a_bike_km: f64,
+    /// Hardest `mtb:scale` along the route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_mtb_scale: Option<u8>,
+}
+
+/// A stretch of the route following a waymarked route relation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaymarkedSection {
+    pub osm_id: i64,
+    /// `hiking`, `foot`, `bicycle` or `mtb`
+    pub route: String,
+    /// `iwn`, `nwn`, `rwn` or `lwn`
+    pub network: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colour: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub osmc_symbol: Option<String>,
+    /// Distances from the start, measured along the route
+    pub from_km: f64,
+    pub to_km: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterReport {
+    pub max_km_without_water: f64,
+    /// Longest distance between the start, the water stops and the end
+    pub longest_gap_km: f64,
+    pub satisfied: bool,
+    pub stops: Vec<WaterStop>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterStop {
+    pub location: Coordinate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Distance from the start, measured along the route
+    pub distance_along_km: f64,
+    pub distance_from_route_m: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,11 +678,91 @@ pub fn default_distance_tolerance_km() -> f64 {
     3.0
 }
 
+pub fn default_hours_per_day() -> f64 {
+    6.0
+}
+
+pub fn default_nearest_count() -> usize {
+    5
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItineraryResponse {
+    pub total_distance_km: f64,
+    /// Naismith estimate for the whole trek
+    pub total_time_minutes: u32,
+    pub stages: Vec<ItineraryStage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItineraryStage {
+    /// 1-based day number
+    pub day: u32,
+    pub route: RouteResponse,
+    /// Where the night is spent; `None` on the last day, or when no hut,
+    /// shelter or village fits and the stage ends on the trail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overnight: Option<StageStop>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageStop {
+    /// "hut", "shelter" or "village"
+    pub poi_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub location: Coordinate,
+    pub distance_from_route_m: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoopRouteResponse {
     pub target_distance_km: f64,
     pub distance_tolerance_km: f64,
     pub candidates: Vec<LoopCandidate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<LoopAttemptStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetourRouteResponse {
+    pub target_distance_km: f64,
+    pub distance_tolerance_km: f64,
+    /// Length of the shortest A→B route, for comparison
+    pub shortest_distance_km: f64,
+    pub candidates: Vec<DetourCandidate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetourCandidate {
+    pub route: RouteResponse,
+    pub distance_error_km: f64,
+    /// Intermediate waypoint the detour was routed through
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub via: Option<Coordinate>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoopAttemptStats {
+    /// Waypoint attempts routed and evaluated
+    pub tried: usize,
+    pub accepted: usize,
+    /// Rejected attempts per reason ("no_path", "distance_out_of_tolerance"...)
+    pub rejected_by_reason: BTreeMap<String, usize>,
+}
+
+/// Outcome of a single loop attempt, streamed while loops are generated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopProgress {
+    /// Distance ring of the attempt; `None` for the loop through the via points alone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ring: Option<usize>,
+    /// Attempt index within its ring
+    pub attempt: usize,
+    pub bearing_deg: f64,
+    pub accepted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -170,4 +770,38 @@ pub struct LoopCandidate {
     pub route: RouteResponse,
     pub distance_error_km: f64,
     pub bearing_deg: f64,
+    /// One entry per requested via point and POI category
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<LoopConstraintStatus>,
+    /// Orientation of `route.path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<LoopOrientation>,
+    /// Grades for both orientations, the returned one first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directions: Vec<LoopDirectionGrades>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopConstraintKind {
+    /// A mandatory via point
+    ViaPoint,
+    /// A required POI category
+    Poi,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopConstraintStatus {
+    pub kind: LoopConstraintKind,
+    /// Via point as "lat,lon", or the requested POI category
+    pub target: String,
+    pub satisfied: bool,
+    /// Via point, or the POI the loop was routed past
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Coordinate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Shortest distance between `location` and the loop
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance_from_route_m: Option<f64>,
 }